@@ -10,6 +10,9 @@ fn main() {
             };
             for p in ports {
                 println!("  {}", p.port_name);
+                if let Some(ref name) = p.friendly_name {
+                    println!("    Name: {}", name);
+                }
                 match p.port_type {
                     SerialPortType::UsbPort(info) => {
                         println!("    Type: USB");
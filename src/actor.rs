@@ -0,0 +1,281 @@
+//! Driving a port from a background thread through channels
+//!
+//! Blocking reads don't fit into the main loop of a GUI or a game engine. An `Actor` moves the
+//! port to a dedicated I/O thread instead: data to send is passed to it through a channel, the
+//! data it receives comes back through another one, and settings and lines are changed with
+//! `control()`. The main loop only ever polls the channels:
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use std::time::Duration;
+//! use serialport::actor::Actor;
+//! use serialport::VirtualPort;
+//!
+//! let (port, mut device) = VirtualPort::pair();
+//! let actor = Actor::spawn(Box::new(port)).unwrap();
+//!
+//! actor.writer().send(b"ping".to_vec()).unwrap();
+//! let mut request = [0; 4];
+//! device.read_exact(&mut request).unwrap();
+//! device.write_all(b"pong").unwrap();
+//!
+//! // In a main loop this would be `try_recv()`
+//! let response = actor.receiver().recv_timeout(Duration::from_secs(1)).unwrap();
+//! assert_eq!(response, b"pong");
+//! let port = actor.shutdown().unwrap();
+//! ```
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::cancel::is_interrupted;
+use crate::{ClearBuffer, Error, ErrorKind, Result, SerialPort, SerialPortSettings};
+
+/// How long the I/O thread waits for data before serving the channels again
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A change to make to the port of an `Actor`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Control {
+    /// Applies all of the settings with `SerialPort::set_all()`
+    ///
+    /// The timeout only takes effect once the actor shuts down and hands back the port.
+    SetAll(SerialPortSettings),
+    /// Sets the baud rate
+    SetBaudRate(u32),
+    /// Sets RTS to the level
+    WriteRequestToSend(bool),
+    /// Sets DTR to the level
+    WriteDataTerminalReady(bool),
+    /// Sends a break for the duration
+    SendBreak(Duration),
+    /// Discards the data in the buffer
+    Clear(ClearBuffer),
+}
+
+/// What the I/O thread is asked to do besides writing
+enum Message {
+    Control(Control, Sender<Result<()>>),
+    Stop,
+}
+
+/// A port owned by a background I/O thread
+///
+/// The thread reads from the port all the time, sending every chunk of received data to
+/// `receiver()`, and writes the data sent through `writer()` in the order it was sent. The port's
+/// timeout is only used for the final writes on `shutdown()`; while the thread runs, reads wait for
+/// a few milliseconds at a time so control commands and writes are served promptly.
+///
+/// If reading or writing fails, with anything but a timeout, the thread stops. `receiver()` then
+/// reports that the channel is disconnected once the data received before is read, and
+/// `shutdown()` returns the error. The thread also stops when the actor is dropped.
+#[derive(Debug)]
+pub struct Actor {
+    writer: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    messages: Sender<Message>,
+    thread: Option<JoinHandle<Result<Box<dyn SerialPort>>>>,
+}
+
+impl Actor {
+    /// Moves `port` to a new I/O thread
+    ///
+    /// # Errors
+    ///
+    /// * `Io` if the thread could not be spawned, or the port's timeout could not be set.
+    pub fn spawn(mut port: Box<dyn SerialPort>) -> Result<Actor> {
+        let timeout = port.set_timeout(POLL_INTERVAL)?;
+        let (writer, written) = mpsc::channel();
+        let (received, receiver) = mpsc::channel();
+        let (messages, inbox) = mpsc::channel();
+
+        let io = Io {
+            port,
+            timeout,
+            written,
+            received,
+            inbox,
+            pending: Vec::new(),
+        };
+        let thread = thread::Builder::new()
+            .name("serialport-actor".into())
+            .spawn(move || io.run())?;
+
+        Ok(Actor {
+            writer,
+            receiver,
+            messages,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns a channel for sending data to write to the port
+    ///
+    /// Clones of the sender can be handed to other threads.
+    pub fn writer(&self) -> Sender<Vec<u8>> {
+        self.writer.clone()
+    }
+
+    /// Returns the channel the data received from the port arrives through
+    pub fn receiver(&self) -> &Receiver<Vec<u8>> {
+        &self.receiver
+    }
+
+    /// Changes the port's settings or lines, waiting until that's done
+    ///
+    /// Commands are served between reads of the port, so this blocks for a few milliseconds at
+    /// most, plus the time the command itself takes.
+    ///
+    /// # Errors
+    ///
+    /// * `Unknown` if the I/O thread has stopped.
+    /// * Any error the port returns for the command.
+    pub fn control(&self, command: Control) -> Result<()> {
+        let (reply, result) = mpsc::channel();
+        if self
+            .messages
+            .send(Message::Control(command, reply))
+            .is_err()
+        {
+            return Err(stopped());
+        }
+        result.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+
+    /// Stops the I/O thread and returns the port
+    ///
+    /// Data that was sent for writing is written out first, within the port's timeout, which is
+    /// then set back on the port.
+    ///
+    /// # Errors
+    ///
+    /// * `Unknown` if the I/O thread panicked.
+    /// * The error that stopped the I/O thread, or any error of the final writes.
+    pub fn shutdown(mut self) -> Result<Box<dyn SerialPort>> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<Box<dyn SerialPort>> {
+        let _ = self.messages.send(Message::Stop);
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .unwrap_or_else(|_| Err(Error::new(ErrorKind::Unknown, "The I/O thread panicked"))),
+            None => Err(stopped()),
+        }
+    }
+}
+
+impl Drop for Actor {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            let _ = self.stop();
+        }
+    }
+}
+
+fn stopped() -> Error {
+    Error::new(ErrorKind::Unknown, "The I/O thread has stopped")
+}
+
+/// The state of the I/O thread
+struct Io {
+    port: Box<dyn SerialPort>,
+    /// The timeout to set back on the port when stopping
+    timeout: Duration,
+    written: Receiver<Vec<u8>>,
+    received: Sender<Vec<u8>>,
+    inbox: Receiver<Message>,
+    /// Data taken from `written` that the port didn't accept yet
+    pending: Vec<u8>,
+}
+
+impl Io {
+    fn run(mut self) -> Result<Box<dyn SerialPort>> {
+        let mut buf = [0u8; 1024];
+        loop {
+            loop {
+                match self.inbox.try_recv() {
+                    Ok(Message::Control(command, reply)) => {
+                        let _ = reply.send(self.apply(command));
+                    }
+                    // The actor is gone once its sender is, so there's no one left to stop it
+                    Ok(Message::Stop) | Err(TryRecvError::Disconnected) => return self.finish(),
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
+            self.take_written();
+            if !self.pending.is_empty() {
+                match self.port.write(&self.pending) {
+                    Ok(n) => {
+                        self.pending.drain(..n);
+                    }
+                    Err(ref e) if is_retryable(e) => (),
+                    Err(e) => return Err(e.into()),
+                }
+                // Waiting for data would hold up the rest of the writes, so only read what's there
+                if !self.pending.is_empty() {
+                    if let Ok(0) = self.port.bytes_to_read() {
+                        continue;
+                    }
+                }
+            }
+
+            match self.port.read(&mut buf) {
+                Ok(0) => {
+                    return Err(Error::new(
+                        ErrorKind::Io(io::ErrorKind::UnexpectedEof),
+                        "Port reported end-of-file",
+                    ))
+                }
+                Ok(n) => {
+                    // Nobody is listening anymore, but the actor may still want the port back
+                    let _ = self.received.send(buf[..n].to_vec());
+                }
+                Err(ref e) if is_retryable(e) => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn apply(&mut self, command: Control) -> Result<()> {
+        match command {
+            Control::SetAll(settings) => {
+                self.port.set_all(&settings)?;
+                self.timeout = settings.timeout;
+                self.port.set_timeout(POLL_INTERVAL).map(|_| ())
+            }
+            Control::SetBaudRate(baud_rate) => self.port.set_baud_rate(baud_rate),
+            Control::WriteRequestToSend(level) => self.port.write_request_to_send(level),
+            Control::WriteDataTerminalReady(level) => self.port.write_data_terminal_ready(level),
+            Control::SendBreak(duration) => self.port.send_break(duration),
+            Control::Clear(buffer_to_clear) => self.port.clear(buffer_to_clear),
+        }
+    }
+
+    /// Moves all data sent for writing so far to `pending`
+    fn take_written(&mut self) {
+        while let Ok(data) = self.written.try_recv() {
+            self.pending.extend_from_slice(&data);
+        }
+    }
+
+    /// Writes out the queued data and hands back the port
+    fn finish(mut self) -> Result<Box<dyn SerialPort>> {
+        self.port.set_timeout(self.timeout)?;
+        self.take_written();
+        if !self.pending.is_empty() {
+            self.port.write_all(&self.pending)?;
+        }
+        Ok(self.port)
+    }
+}
+
+/// Checks for errors after which reading or writing can simply be tried again
+fn is_retryable(e: &io::Error) -> bool {
+    let kind = e.kind();
+    kind == io::ErrorKind::TimedOut || kind == io::ErrorKind::WouldBlock || is_interrupted(e)
+}
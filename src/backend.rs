@@ -0,0 +1,238 @@
+//! Plugging custom transports into `open()` and `available_ports()`
+//!
+//! Ports are normally devices of the OS, opened by their path. A `PortBackend` provides ports of
+//! its own, such as USB-CDC devices driven through libusb, raw TCP bridges or simulators, under a
+//! URI-like prefix: once a backend for the scheme `tcp` is registered, `serialport::new()` opens
+//! paths starting with `tcp://` through it, and `available_ports()` lists its ports too.
+
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Once, RwLock};
+
+use bitflags::bitflags;
+
+use crate::{Error, ErrorKind, Result, SerialPort, SerialPortInfo, SerialPortSettings};
+
+bitflags! {
+    /// The features a backend's ports support beyond reading and writing
+    ///
+    /// Ports without a capability still implement the whole `SerialPort` trait, but the methods
+    /// for it return errors or have no effect.
+    pub struct Capabilities: u32 {
+        /// The baud rate, framing and flow control settings are applied
+        const CONFIGURE = 0x01;
+        /// RTS and DTR can be set
+        const MODEM_CONTROL = 0x02;
+        /// CTS, DSR, RI and CD can be read
+        const MODEM_STATUS = 0x04;
+        /// Breaks can be sent
+        const BREAK = 0x08;
+        /// `available_ports()` lists the backend's ports
+        const ENUMERATE = 0x10;
+    }
+}
+
+/// A source of serial ports other than the OS's devices
+///
+/// Register an implementation with `register_backend()` to make its ports available through
+/// `open()` and `available_ports()`.
+pub trait PortBackend: Send + Sync {
+    /// Returns the scheme of the paths this backend opens, e.g. `tcp` for `tcp://host:port`
+    ///
+    /// Schemes consist of ASCII letters, digits, `+`, `-` and `.`, start with a letter, and are
+    /// matched ignoring case.
+    fn scheme(&self) -> &str;
+
+    /// Opens the port at `path` with `settings`
+    ///
+    /// `path` is the whole path given to `serialport::new()`, including the scheme. The lines
+    /// chosen with `dtr_on_open()` and `rts_on_open()` are set after this returns, while
+    /// `access_mode()` only applies to the OS's devices.
+    ///
+    /// # Errors
+    ///
+    /// * `NoDevice` if there is no port at `path`.
+    /// * `InvalidInput` if `path` or `settings` aren't valid for this backend.
+    /// * `Io` for any other type of I/O error.
+    fn open(&self, path: &str, settings: &SerialPortSettings) -> Result<Box<dyn SerialPort>>;
+
+    /// Returns the backend's ports
+    ///
+    /// Their `port_name` must be a path that `open()` accepts, so it starts with the scheme. The
+    /// default implementation returns no ports, for backends like network bridges that can't
+    /// list theirs. Errors are ignored by `serialport::available_ports()`, which then lists the
+    /// other ports without this backend's.
+    fn available_ports(&self) -> Result<Vec<SerialPortInfo>> {
+        Ok(Vec::new())
+    }
+
+    /// Returns what the backend's ports support
+    fn capabilities(&self) -> Capabilities;
+}
+
+type Registry = RwLock<Vec<Entry>>;
+
+struct Entry {
+    backend: Arc<dyn PortBackend>,
+    /// Whether this is one of the crate's own backends, which may be replaced
+    builtin: bool,
+}
+
+/// Returns the registered backends, creating the registry with the built-in ones on first use
+fn registry() -> &'static Registry {
+    static INIT: Once = Once::new();
+    static REGISTRY: AtomicPtr<Registry> = AtomicPtr::new(ptr::null_mut());
+
+    INIT.call_once(|| {
+        let backends = builtin_backends();
+        // Leaked on purpose, the registry lives as long as the process
+        let registry = Box::new(RwLock::new(backends));
+        REGISTRY.store(Box::into_raw(registry), Ordering::SeqCst);
+    });
+    // The pointer was set once by `call_once()` and is never freed
+    unsafe { &*REGISTRY.load(Ordering::SeqCst) }
+}
+
+/// Returns the backends the crate provides
+fn builtin_backends() -> Vec<Entry> {
+    #[cfg(feature = "rfc2217")]
+    return vec![Entry {
+        backend: Arc::new(crate::rfc2217::Rfc2217Backend),
+        builtin: true,
+    }];
+
+    #[cfg(not(feature = "rfc2217"))]
+    Vec::new()
+}
+
+/// Makes the ports of `backend` available through `open()` and `available_ports()`
+///
+/// Paths starting with the backend's scheme followed by `://` are then opened by it. This replaces
+/// a built-in backend for the same scheme, such as the one for `rfc2217://` paths.
+///
+/// ## Errors
+///
+/// * `InvalidInput` if the scheme isn't valid or another registered backend already uses it.
+///
+/// ## Examples
+///
+/// ```
+/// use serialport::{Capabilities, PortBackend, SerialPort, SerialPortSettings, VirtualPort};
+///
+/// struct Loopback;
+///
+/// impl PortBackend for Loopback {
+///     fn scheme(&self) -> &str {
+///         "loopback-example"
+///     }
+///
+///     fn open(&self, _: &str, settings: &SerialPortSettings) -> serialport::Result<Box<dyn SerialPort>> {
+///         let (mut port, _) = VirtualPort::pair();
+///         port.set_all(settings)?;
+///         Ok(Box::new(port))
+///     }
+///
+///     fn capabilities(&self) -> Capabilities {
+///         Capabilities::CONFIGURE
+///     }
+/// }
+///
+/// serialport::register_backend(Box::new(Loopback)).unwrap();
+/// let port = serialport::new("loopback-example://0", 115_200).open().unwrap();
+/// assert_eq!(port.baud_rate().unwrap(), 115_200);
+/// ```
+pub fn register_backend(backend: Box<dyn PortBackend>) -> Result<()> {
+    let scheme = backend.scheme().to_string();
+    if !is_scheme(&scheme) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("`{}` is not a valid scheme", scheme),
+        ));
+    }
+    let mut backends = registry()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if backends
+        .iter()
+        .any(|other| other.backend.scheme().eq_ignore_ascii_case(&scheme) && !other.builtin)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("A backend for `{}://` is already registered", scheme),
+        ));
+    }
+    backends.retain(|other| !other.backend.scheme().eq_ignore_ascii_case(&scheme));
+    backends.push(Entry {
+        backend: Arc::from(backend),
+        builtin: false,
+    });
+    Ok(())
+}
+
+/// Removes the backend registered for `scheme`
+///
+/// Ports that were opened through it stay open. Returns `false` if no backend was registered for
+/// `scheme`.
+pub fn unregister_backend(scheme: &str) -> bool {
+    let mut backends = registry()
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let before = backends.len();
+    backends.retain(|entry| !entry.backend.scheme().eq_ignore_ascii_case(scheme));
+    backends.len() != before
+}
+
+/// Returns the backend that opens `path`, if it starts with the scheme of a registered one
+///
+/// This allows checking a backend's `capabilities()` before opening a port.
+pub fn find_backend(path: &str) -> Option<Arc<dyn PortBackend>> {
+    let scheme = scheme_of(path)?;
+    registry()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .find(|entry| entry.backend.scheme().eq_ignore_ascii_case(scheme))
+        .map(|entry| entry.backend.clone())
+}
+
+/// Returns the ports of all registered backends
+///
+/// Backends that fail to list their ports are left out, so one unreachable bridge or simulator
+/// doesn't hide the ports of the others and of the OS.
+pub(crate) fn available_ports() -> Vec<SerialPortInfo> {
+    // Don't hold the lock while the backends enumerate, they may register others
+    let backends: Vec<Arc<dyn PortBackend>> = registry()
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .map(|entry| entry.backend.clone())
+        .collect();
+    let mut ports = Vec::new();
+    for backend in backends {
+        if let Ok(found) = backend.available_ports() {
+            ports.extend(found);
+        }
+    }
+    ports
+}
+
+/// Returns the scheme `path` starts with, if it has the form `<scheme>://...`
+pub(crate) fn scheme_of(path: &str) -> Option<&str> {
+    let end = path.find("://")?;
+    let scheme = &path[..end];
+    if is_scheme(scheme) {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+/// Checks for a scheme as defined by RFC 3986
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+}
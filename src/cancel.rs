@@ -0,0 +1,222 @@
+//! Cancelling blocking reads and writes from another thread
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(unix)]
+use crate::posix::WakePipe;
+use crate::{Error, ErrorKind, Result};
+
+#[cfg(windows)]
+use winapi::shared::minwindef::{FALSE, TRUE};
+#[cfg(windows)]
+use winapi::um::handleapi::CloseHandle;
+#[cfg(windows)]
+use winapi::um::synchapi::{CreateEventW, SetEvent};
+#[cfg(windows)]
+use winapi::um::winnt::HANDLE;
+
+/// A handle for cancelling the blocking reads and writes of a port from another thread
+///
+/// Returned by [`SerialPort::cancellation_token`]. Clones refer to the same port, so the port can
+/// be moved to a reader thread while another thread keeps a clone to shut it down.
+///
+/// [`SerialPort::cancellation_token`]: trait.SerialPort.html#method.cancellation_token
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    cancelled: AtomicBool,
+    #[cfg(unix)]
+    wake: WakePipe,
+    #[cfg(windows)]
+    event: Event,
+}
+
+impl CancellationToken {
+    pub(crate) fn new() -> Result<CancellationToken> {
+        Ok(CancellationToken {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                #[cfg(unix)]
+                wake: WakePipe::new()?,
+                #[cfg(windows)]
+                event: Event::new()?,
+            }),
+        })
+    }
+
+    /// Cancels the port's blocking reads and writes
+    ///
+    /// Operations currently blocked on the port, as well as any started later, fail with an
+    /// `Interrupted` error. Through `io::Error` this shows up as `io::ErrorKind::Interrupted`,
+    /// with the original `serialport::Error` available from `io::Error::get_ref()`. Data that was
+    /// already read or written when the operation was cancelled is still reported.
+    ///
+    /// The helpers of `SerialPort` and the port's `read_exact()` stop at a cancellation, but code
+    /// that retries `io::ErrorKind::Interrupted`, like `io::Read::read_to_end()` and `io::copy()`
+    /// from std, keeps retrying the failing reads.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        #[cfg(unix)]
+        self.inner.wake.wake();
+        #[cfg(windows)]
+        self.inner.event.set();
+    }
+
+    /// Returns `true` if `cancel()` was called on this token or any of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the error for operations that were cancelled through this token
+    pub(crate) fn error(&self) -> io::Error {
+        io::Error::from(Error::new(
+            ErrorKind::Interrupted,
+            "Operation was cancelled",
+        ))
+    }
+
+    /// Fails with `Interrupted` if the token was cancelled
+    pub(crate) fn check(&self) -> io::Result<()> {
+        if self.is_cancelled() {
+            Err(self.error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the pipe that becomes readable once the token is cancelled
+    #[cfg(unix)]
+    pub(crate) fn wake_pipe(&self) -> &WakePipe {
+        &self.inner.wake
+    }
+
+    /// Returns the event that is signaled once the token is cancelled
+    #[cfg(windows)]
+    pub(crate) fn event(&self) -> HANDLE {
+        self.inner.event.0
+    }
+}
+
+/// The cancellation token of a port, shared by all of its clones
+///
+/// The token is only created once it's asked for, since it holds a pipe or an event. Clones made
+/// before that still get it, so cancelling stops the blocking operations of every handle to the
+/// port, including both halves of a split port.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SharedToken(Arc<Mutex<Option<CancellationToken>>>);
+
+impl SharedToken {
+    fn token(&self) -> MutexGuard<'_, Option<CancellationToken>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the token if it has been created
+    pub(crate) fn get(&self) -> Option<CancellationToken> {
+        self.token().clone()
+    }
+
+    /// Returns the token, creating it if this is the first time it's asked for
+    pub(crate) fn get_or_create(&self) -> Result<CancellationToken> {
+        let mut token = self.token();
+        match *token {
+            Some(ref token) => Ok(token.clone()),
+            None => {
+                let created = CancellationToken::new()?;
+                *token = Some(created.clone());
+                Ok(created)
+            }
+        }
+    }
+
+    /// Stores a token that was created elsewhere, such as by a wrapped port
+    pub(crate) fn set(&self, token: CancellationToken) {
+        *self.token() = Some(token);
+    }
+}
+
+/// Checks for an interrupted system call, after which the operation can be retried
+///
+/// Operations cancelled through this library fail with `io::ErrorKind::Interrupted` too, but those
+/// fail again right away, so loops that retry interruptions must not retry them.
+pub(crate) fn is_interrupted(e: &io::Error) -> bool {
+    if e.kind() != io::ErrorKind::Interrupted {
+        return false;
+    }
+    match e.get_ref().and_then(|e| e.downcast_ref::<Error>()) {
+        Some(error) => error.kind() != ErrorKind::Interrupted,
+        None => true,
+    }
+}
+
+/// Like `io::Read::read_exact()`, except that reads cancelled through `token` aren't retried
+///
+/// A cancelled token keeps failing reads with `Interrupted`, which the default implementation
+/// would retry forever.
+pub(crate) fn read_exact<R: io::Read>(
+    reader: &mut R,
+    mut buf: &mut [u8],
+    token: Option<&CancellationToken>,
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        match reader.read(buf) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "failed to fill whole buffer",
+                ))
+            }
+            Ok(n) => {
+                let tmp = buf;
+                buf = &mut tmp[n..];
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {
+                if let Some(token) = token {
+                    token.check()?;
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// A manual-reset event that stays signaled once set
+#[cfg(windows)]
+#[derive(Debug)]
+struct Event(HANDLE);
+
+// The event handle may be used and closed from any thread
+#[cfg(windows)]
+unsafe impl Send for Event {}
+#[cfg(windows)]
+unsafe impl Sync for Event {}
+
+#[cfg(windows)]
+impl Event {
+    fn new() -> Result<Event> {
+        let handle = unsafe { CreateEventW(std::ptr::null_mut(), TRUE, FALSE, std::ptr::null()) };
+        if handle.is_null() {
+            return Err(Error::from(io::Error::last_os_error()));
+        }
+        Ok(Event(handle))
+    }
+
+    fn set(&self) {
+        unsafe { SetEvent(self.0) };
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
@@ -0,0 +1,440 @@
+//! Splitting the byte stream of a port into frames
+//!
+//! Firmware protocols usually send messages as frames, marked by a delimiter or a length prefix.
+//! `Framed` wraps a port to read and write whole frames, using a `Codec` for the framing:
+//!
+//! * `Cobs` for Consistent Overhead Byte Stuffing, with frames ending in a zero byte.
+//! * `Slip` for the Serial Line Internet Protocol of RFC 1055.
+//! * `Delimited` for frames ending in a delimiter byte, like lines of text.
+//! * `LengthPrefixed` for frames starting with their length.
+//!
+//! ```
+//! use serialport::framing::{Cobs, Framed};
+//! use serialport::VirtualPort;
+//!
+//! let (a, b) = VirtualPort::pair();
+//! let mut sender = Framed::new(a, Cobs);
+//! let mut receiver = Framed::new(b, Cobs);
+//! sender.write_frame(&[0x01, 0x00, 0x02]).unwrap();
+//! assert_eq!(receiver.read_frame().unwrap(), vec![0x01, 0x00, 0x02]);
+//! ```
+
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Error, ErrorKind, Result, SerialPort};
+
+/// A way of marking frames in a byte stream
+pub trait Codec {
+    /// Appends the encoding of `frame` to `dst`
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidInput` if `frame` can't be encoded, e.g. because it's too long.
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<()>;
+
+    /// Removes the first complete frame from `src` and returns it decoded
+    ///
+    /// Returns `None` if `src` doesn't hold a complete frame yet. A frame that can't be decoded is
+    /// removed from `src` as well, so decoding continues with the next one.
+    ///
+    /// # Errors
+    ///
+    /// * `Io(InvalidData)` if the first frame is malformed.
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>>;
+}
+
+/// A port that is read and written in frames
+///
+/// Received data that follows a frame is kept for the next `read_frame()`, so reading and writing
+/// the port directly through `get_mut()` can lose data or mix up frames.
+pub struct Framed<P: ?Sized, C> {
+    codec: C,
+    /// Received data that wasn't decoded yet
+    buffer: Vec<u8>,
+    max_frame_len: usize,
+    // Boxed so ports opened with `open()` can be used as they are
+    port: Box<P>,
+}
+
+impl<P: ?Sized, C: fmt::Debug> fmt::Debug for Framed<P, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Framed")
+            .field("codec", &self.codec)
+            .field("buffered", &self.buffer.len())
+            .field("max_frame_len", &self.max_frame_len)
+            .finish()
+    }
+}
+
+impl<P: SerialPort, C: Codec> Framed<P, C> {
+    /// Frames the data of `port` with `codec`
+    pub fn new(port: P, codec: C) -> Framed<P, C> {
+        Framed::from_box(Box::new(port), codec)
+    }
+}
+
+impl<P: SerialPort + ?Sized, C: Codec> Framed<P, C> {
+    /// Frames the data of a boxed port, like those returned by `open()`, with `codec`
+    pub fn from_box(port: Box<P>, codec: C) -> Framed<P, C> {
+        Framed {
+            codec,
+            buffer: Vec::new(),
+            max_frame_len: 64 * 1024,
+            port,
+        }
+    }
+
+    /// Returns the port
+    pub fn get_ref(&self) -> &P {
+        &self.port
+    }
+
+    /// Returns the port mutably
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    /// Returns the port, dropping any data received after the last frame that was read
+    pub fn into_inner(self) -> Box<P> {
+        self.port
+    }
+
+    /// Returns the codec
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Sets the longest a frame may be when encoded, which defaults to 64 KiB
+    ///
+    /// This bounds the memory used for frames that never end, e.g. because their end was lost.
+    pub fn set_max_frame_len(&mut self, len: usize) {
+        self.max_frame_len = len;
+    }
+
+    /// Reads the next frame
+    ///
+    /// The port's timeout limits how long this waits for the whole frame, rather than for each
+    /// byte of it.
+    ///
+    /// # Errors
+    ///
+    /// * `TimedOut` if no complete frame was received in time. The data received so far is
+    ///   kept, so the next call may complete the frame.
+    /// * `Io(InvalidData)` if the frame is malformed or longer than the maximum length. It's
+    ///   dropped, so the next call reads the following frame.
+    /// * Any error that reading from the port can return.
+    pub fn read_frame(&mut self) -> Result<Vec<u8>> {
+        let timeout = self.port.timeout();
+        let start = Instant::now();
+        let mut chunk = [0u8; 256];
+        let mut read = false;
+
+        let result = loop {
+            match self.codec.decode(&mut self.buffer) {
+                Ok(Some(frame)) => break Ok(frame),
+                Ok(None) => (),
+                Err(e) => break Err(e),
+            }
+            if self.buffer.len() > self.max_frame_len {
+                self.buffer.clear();
+                break Err(Error::new(
+                    ErrorKind::Io(io::ErrorKind::InvalidData),
+                    "Frame exceeds the maximum length",
+                ));
+            }
+            let remaining = timeout
+                .checked_sub(start.elapsed())
+                .unwrap_or_else(|| Duration::from_secs(0));
+            // Read at least once, so a port without a timeout still returns the data it has
+            if read && remaining == Duration::from_secs(0) {
+                break Err(Error::new(ErrorKind::TimedOut, "Operation timed out"));
+            }
+            if let Err(e) = self.port.set_timeout(remaining) {
+                break Err(e);
+            }
+            read = true;
+
+            match self.port.read(&mut chunk) {
+                Ok(0) => {
+                    break Err(Error::new(
+                        ErrorKind::Io(io::ErrorKind::UnexpectedEof),
+                        "Port reported end-of-file",
+                    ))
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        let restored = self.port.set_timeout(timeout);
+        match (result, restored) {
+            (Ok(frame), Ok(_)) => Ok(frame),
+            (Err(e), _) | (Ok(_), Err(e)) => Err(e),
+        }
+    }
+
+    /// Writes `frame`
+    ///
+    /// The port's timeout limits how long this waits for the whole frame to be written.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidInput` if the codec can't encode `frame`.
+    /// * `TimedOut` if the frame couldn't be written in time. Part of it may have been sent.
+    /// * Any error that writing to the port can return.
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
+        let mut encoded = Vec::with_capacity(frame.len() + 2);
+        self.codec.encode(frame, &mut encoded)?;
+        let timeout = self.port.timeout();
+        self.port
+            .write_all_or_partial(&encoded, timeout)
+            .map_err(|(_, e)| e)
+    }
+}
+
+fn invalid_data(description: &str) -> Error {
+    Error::new(ErrorKind::Io(io::ErrorKind::InvalidData), description)
+}
+
+/// Removes the bytes up to the first `delimiter` from `src` and returns them without it
+fn split_off_delimited(src: &mut Vec<u8>, delimiter: u8) -> Option<Vec<u8>> {
+    let end = src.iter().position(|&byte| byte == delimiter)?;
+    let frame = src[..end].to_vec();
+    src.drain(..=end);
+    Some(frame)
+}
+
+/// Consistent Overhead Byte Stuffing, with each frame ending in a zero byte
+///
+/// COBS replaces the zero bytes of a frame, so the zero marking its end can't occur within it.
+/// This costs at most one byte per 254 bytes of frame, plus the delimiter. Empty frames are
+/// skipped when decoding, so senders may use extra zeros to resynchronize the receiver.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Cobs;
+
+impl Codec for Cobs {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<()> {
+        let mut code_index = dst.len();
+        let mut code = 1u8;
+        dst.push(0);
+        for &byte in frame {
+            if byte != 0 {
+                dst.push(byte);
+                code += 1;
+            }
+            if byte == 0 || code == 0xFF {
+                dst[code_index] = code;
+                code_index = dst.len();
+                code = 1;
+                dst.push(0);
+            }
+        }
+        dst[code_index] = code;
+        dst.push(0);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        loop {
+            let encoded = match split_off_delimited(src, 0) {
+                Some(encoded) => encoded,
+                None => return Ok(None),
+            };
+            if encoded.is_empty() {
+                continue;
+            }
+
+            let mut frame = Vec::with_capacity(encoded.len());
+            let mut i = 0;
+            while i < encoded.len() {
+                let code = encoded[i] as usize;
+                if i + code > encoded.len() {
+                    return Err(invalid_data("Truncated COBS frame"));
+                }
+                frame.extend_from_slice(&encoded[i + 1..i + code]);
+                i += code;
+                // A full block isn't followed by a zero
+                if code < 0xFF && i < encoded.len() {
+                    frame.push(0);
+                }
+            }
+            return Ok(Some(frame));
+        }
+    }
+}
+
+// Special bytes of SLIP, from RFC 1055
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// The Serial Line Internet Protocol of RFC 1055
+///
+/// Frames end with `0xC0`, and occurrences of `0xC0` and `0xDB` within them are escaped. Frames
+/// are also sent starting with `0xC0`, which discards any line noise received before them, so
+/// empty frames are skipped when decoding.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Slip;
+
+impl Codec for Slip {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<()> {
+        dst.push(SLIP_END);
+        for &byte in frame {
+            match byte {
+                SLIP_END => dst.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => dst.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                _ => dst.push(byte),
+            }
+        }
+        dst.push(SLIP_END);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        loop {
+            let encoded = match split_off_delimited(src, SLIP_END) {
+                Some(encoded) => encoded,
+                None => return Ok(None),
+            };
+            if encoded.is_empty() {
+                continue;
+            }
+
+            let mut frame = Vec::with_capacity(encoded.len());
+            let mut bytes = encoded.iter();
+            while let Some(&byte) = bytes.next() {
+                if byte != SLIP_ESC {
+                    frame.push(byte);
+                    continue;
+                }
+                match bytes.next() {
+                    Some(&SLIP_ESC_END) => frame.push(SLIP_END),
+                    Some(&SLIP_ESC_ESC) => frame.push(SLIP_ESC),
+                    _ => return Err(invalid_data("Invalid escape in SLIP frame")),
+                }
+            }
+            return Ok(Some(frame));
+        }
+    }
+}
+
+/// Frames ending in a delimiter byte, like lines of text ending in `\n`
+///
+/// The delimiter is removed from decoded frames. There's no escaping, so frames can't contain the
+/// delimiter.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Delimited {
+    delimiter: u8,
+}
+
+impl Delimited {
+    /// Creates a codec for frames ending in `delimiter`
+    pub fn new(delimiter: u8) -> Delimited {
+        Delimited { delimiter }
+    }
+
+    /// Returns the delimiter
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+}
+
+impl Codec for Delimited {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<()> {
+        if frame.contains(&self.delimiter) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Frame contains the delimiter",
+            ));
+        }
+        dst.extend_from_slice(frame);
+        dst.push(self.delimiter);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        Ok(split_off_delimited(src, self.delimiter))
+    }
+}
+
+/// Frames starting with their length as an unsigned integer of one or two bytes
+///
+/// The length counts the bytes of the frame after the prefix. Receivers can't resynchronize with
+/// a sender once a byte was lost, so this suits reliable links, or protocols with their own way of
+/// recovering.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LengthPrefixed {
+    width: usize,
+    big_endian: bool,
+}
+
+impl LengthPrefixed {
+    /// Creates a codec for frames with a one-byte length, of up to 255 bytes
+    pub fn u8() -> LengthPrefixed {
+        LengthPrefixed {
+            width: 1,
+            big_endian: true,
+        }
+    }
+
+    /// Creates a codec for frames with a two-byte big-endian length, of up to 65535 bytes
+    pub fn u16_be() -> LengthPrefixed {
+        LengthPrefixed {
+            width: 2,
+            big_endian: true,
+        }
+    }
+
+    /// Creates a codec for frames with a two-byte little-endian length, of up to 65535 bytes
+    pub fn u16_le() -> LengthPrefixed {
+        LengthPrefixed {
+            width: 2,
+            big_endian: false,
+        }
+    }
+}
+
+impl Codec for LengthPrefixed {
+    fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<()> {
+        let len = frame.len();
+        match self.width {
+            1 if len <= 0xFF => dst.push(len as u8),
+            2 if len <= 0xFFFF => {
+                let len = len as u16;
+                if self.big_endian {
+                    dst.extend_from_slice(&len.to_be_bytes());
+                } else {
+                    dst.extend_from_slice(&len.to_le_bytes());
+                }
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Frame is too long for its length prefix",
+                ))
+            }
+        }
+        dst.extend_from_slice(frame);
+        Ok(())
+    }
+
+    fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>> {
+        if src.len() < self.width {
+            return Ok(None);
+        }
+        let len = match self.width {
+            1 => src[0] as usize,
+            _ if self.big_endian => u16::from_be_bytes([src[0], src[1]]) as usize,
+            _ => u16::from_le_bytes([src[0], src[1]]) as usize,
+        };
+        if src.len() < self.width + len {
+            return Ok(None);
+        }
+        let frame = src[self.width..self.width + len].to_vec();
+        src.drain(..self.width + len);
+        Ok(Some(frame))
+    }
+}
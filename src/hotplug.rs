@@ -0,0 +1,205 @@
+//! Notifications of serial ports being added to or removed from the system
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::time::Duration;
+
+use crate::{available_ports, Error, ErrorKind, Result, SerialPortInfo};
+
+#[cfg(all(target_os = "linux", not(target_env = "musl"), feature = "libudev"))]
+use crate::posix::ChangeMonitor;
+#[cfg(windows)]
+use crate::windows::ChangeMonitor;
+
+/// A change to the set of serial ports available on the system
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortEvent {
+    /// A port was added, such as a USB serial adapter being plugged in
+    Added(SerialPortInfo),
+    /// The port with this name was removed
+    Removed(String),
+}
+
+/// Reports serial ports being added to or removed from the system
+///
+/// Returned by `watch_ports()`. The ports are watched from a background thread, which is stopped
+/// when the watcher is dropped. Iterating over the watcher waits for one change after another,
+/// like calling `recv()` in a loop, and only ends if the background thread stops.
+#[derive(Debug)]
+pub struct PortWatcher {
+    events: Receiver<Result<PortEvent>>,
+    _monitor: ChangeMonitor,
+}
+
+impl PortWatcher {
+    /// Waits for the next change to the available ports
+    ///
+    /// ## Errors
+    ///
+    /// * Any error that `available_ports()` can return, if the ports could not be listed after a
+    ///   change. The watcher keeps going after such an error.
+    /// * `Unknown` if the background thread has stopped.
+    pub fn recv(&self) -> Result<PortEvent> {
+        match self.events.recv() {
+            Ok(event) => event,
+            Err(_) => Err(stopped()),
+        }
+    }
+
+    /// Waits up to `timeout` for the next change to the available ports
+    ///
+    /// Returns `None` if nothing changed within `timeout`.
+    ///
+    /// ## Errors
+    ///
+    /// Same as for `recv()`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<PortEvent>> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => event.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(stopped()),
+        }
+    }
+
+    /// Returns the next change to the available ports, or `None` if there isn't one yet
+    ///
+    /// ## Errors
+    ///
+    /// Same as for `recv()`.
+    pub fn try_recv(&self) -> Result<Option<PortEvent>> {
+        match self.events.try_recv() {
+            Ok(event) => event.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(stopped()),
+        }
+    }
+}
+
+impl Iterator for PortWatcher {
+    type Item = Result<PortEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.recv().ok()
+    }
+}
+
+fn stopped() -> Error {
+    Error::new(ErrorKind::Unknown, "The port watcher has stopped")
+}
+
+/// Starts watching for serial ports being added to or removed from the system
+///
+/// This is available with the `hotplug` feature. Changes are detected with udev on Linux and
+/// device notifications on Windows, after which the ports are listed again with
+/// `available_ports()` to find out what changed. Other platforms, as well as Linux without the
+/// `libudev` feature, list the ports once a second instead.
+///
+/// Ports that are already present aren't reported.
+///
+/// ## Errors
+///
+/// * Any error that `available_ports()` can return.
+/// * `Io` if the background thread or the platform's notifications could not be set up.
+pub fn watch_ports() -> Result<PortWatcher> {
+    let mut ports = available_ports()?;
+    let (sender, events) = mpsc::channel();
+    let monitor = ChangeMonitor::start(Box::new(move || match available_ports() {
+        Ok(current) => {
+            let sent = diff_ports(&ports, &current)
+                .into_iter()
+                .all(|event| sender.send(Ok(event)).is_ok());
+            ports = current;
+            sent
+        }
+        Err(e) => sender.send(Err(e)).is_ok(),
+    }))?;
+    Ok(PortWatcher {
+        events,
+        _monitor: monitor,
+    })
+}
+
+/// Returns the events that turn the `old` list of ports into the `new` one
+fn diff_ports(old: &[SerialPortInfo], new: &[SerialPortInfo]) -> Vec<PortEvent> {
+    let removed = old
+        .iter()
+        .filter(|port| !new.iter().any(|p| p.port_name == port.port_name))
+        .map(|port| PortEvent::Removed(port.port_name.clone()));
+    let added = new
+        .iter()
+        .filter(|port| !old.iter().any(|p| p.port_name == port.port_name))
+        .map(|port| PortEvent::Added(port.clone()));
+    removed.chain(added).collect()
+}
+
+#[cfg(not(any(
+    windows,
+    all(target_os = "linux", not(target_env = "musl"), feature = "libudev")
+)))]
+use self::polling::ChangeMonitor;
+
+/// Detection of changes by listing the ports periodically, where there are no notifications
+#[cfg(not(any(
+    windows,
+    all(target_os = "linux", not(target_env = "musl"), feature = "libudev")
+)))]
+mod polling {
+    use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::Result;
+
+    /// How often the ports are listed
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    /// A background thread that calls back every `INTERVAL`, stopped when this is dropped
+    #[derive(Debug)]
+    pub(crate) struct ChangeMonitor {
+        _stop: Sender<()>,
+    }
+
+    impl ChangeMonitor {
+        /// Starts calling `on_change` until it returns `false`
+        pub(crate) fn start(mut on_change: Box<dyn FnMut() -> bool + Send>) -> Result<Self> {
+            let (stop, stopped) = mpsc::channel::<()>();
+            thread::Builder::new()
+                .name("serialport-hotplug".into())
+                .spawn(move || {
+                    // Nothing is ever sent, the sender being dropped is the signal to stop
+                    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(INTERVAL) {
+                        if !on_change() {
+                            return;
+                        }
+                    }
+                })?;
+            Ok(ChangeMonitor { _stop: stop })
+        }
+    }
+}
+
+#[cfg(test)]
+fn port(name: &str) -> SerialPortInfo {
+    SerialPortInfo {
+        port_name: name.to_string(),
+        port_type: crate::SerialPortType::Unknown,
+        location: None,
+        aliases: Vec::new(),
+        driver: None,
+        friendly_name: None,
+        hardware_ids: Vec::new(),
+    }
+}
+
+#[test]
+fn test_diff_ports() {
+    let old = vec![port("/dev/ttyS0"), port("/dev/ttyUSB0")];
+    let new = vec![port("/dev/ttyS0"), port("/dev/ttyUSB1")];
+    assert_eq!(
+        diff_ports(&old, &new),
+        vec![
+            PortEvent::Removed("/dev/ttyUSB0".to_string()),
+            PortEvent::Added(port("/dev/ttyUSB1")),
+        ]
+    );
+    assert!(diff_ports(&new, &new).is_empty());
+}
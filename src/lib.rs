@@ -30,17 +30,47 @@ use std::convert::From;
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
-use std::time::Duration;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use bitflags::bitflags;
 
 #[cfg(unix)]
 mod posix;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub use posix::Rs485Config;
+#[cfg(all(unix, feature = "tokio"))]
+pub use posix::SerialStream;
 #[cfg(unix)]
-pub use posix::{BreakDuration, TTYPort};
+pub use posix::{poll, BreakDuration, Deadline, ModemSignals, ParityCheck, TTYPort};
 
 #[cfg(windows)]
 mod windows;
+#[cfg(all(windows, feature = "mio07"))]
+pub use windows::MioCOMPort;
 #[cfg(windows)]
-pub use windows::COMPort;
+pub use windows::{poll, COMPort};
+
+#[cfg(feature = "actor")]
+pub mod actor;
+mod backend;
+mod cancel;
+pub mod framing;
+#[cfg(feature = "hotplug")]
+mod hotplug;
+pub mod mock;
+#[cfg(feature = "rfc2217")]
+mod rfc2217;
+pub mod trace;
+mod virtual_port;
+mod watchdog;
+pub use backend::{find_backend, register_backend, unregister_backend, Capabilities, PortBackend};
+pub use cancel::CancellationToken;
+#[cfg(feature = "hotplug")]
+pub use hotplug::{watch_ports, PortEvent, PortWatcher};
+#[cfg(feature = "rfc2217")]
+pub use rfc2217::Rfc2217Port;
+pub use virtual_port::VirtualPort;
 
 /// A type for results generated by interacting with serial ports
 ///
@@ -55,8 +85,8 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum ErrorKind {
     /// The device is not available.
     ///
-    /// This could indicate that the device is in use by another process or was
-    /// disconnected while performing I/O.
+    /// This could indicate that the device doesn't exist or was disconnected while performing
+    /// I/O.
     NoDevice,
 
     /// A parameter was incorrect.
@@ -65,6 +95,26 @@ pub enum ErrorKind {
     /// An unknown error occurred.
     Unknown,
 
+    /// The operation was cancelled.
+    ///
+    /// This is only returned when an operation is cancelled through this library, e.g. with
+    /// `CancellationToken::cancel()` or `Deadline::cancel()`, and never for an interrupted system
+    /// call.
+    Interrupted,
+
+    /// The operation didn't complete within its timeout.
+    TimedOut,
+
+    /// The process lacks permission to access the device.
+    PermissionDenied,
+
+    /// The device is in use, e.g. held in exclusive mode or locked by another process.
+    Busy,
+
+    /// The other end of the connection went away, e.g. the master side of a pseudo terminal was
+    /// closed.
+    BrokenPipe,
+
     /// An I/O error occurred.
     ///
     /// The type of I/O error is determined by the inner `io::ErrorKind`.
@@ -72,12 +122,17 @@ pub enum ErrorKind {
 }
 
 /// An error type for serial port operations
+///
+/// Errors reported by the operating system keep the underlying `io::Error`, which is returned by
+/// `source()`, so the OS error code is available through `raw_os_error()`.
 #[derive(Debug)]
 pub struct Error {
     /// The kind of error this is
     pub kind: ErrorKind,
     /// A description of the error suitable for end-users
     pub description: String,
+    /// The error reported by the operating system, if any
+    source: Option<io::Error>,
 }
 
 impl Error {
@@ -86,6 +141,7 @@ impl Error {
         Error {
             kind,
             description: description.into(),
+            source: None,
         }
     }
 
@@ -93,6 +149,20 @@ impl Error {
     pub fn kind(&self) -> ErrorKind {
         self.kind
     }
+
+    /// Returns the OS error code this error was created from, if any
+    ///
+    /// This is an `errno` value on POSIX platforms and a `GetLastError()` code on Windows, as
+    /// returned by `io::Error::raw_os_error()`.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.as_ref().and_then(io::Error::raw_os_error)
+    }
+
+    /// Attaches the OS error `code` this error was created from
+    pub(crate) fn with_os_error(mut self, code: i32) -> Self {
+        self.source = Some(io::Error::from_raw_os_error(code));
+        self
+    }
 }
 
 impl fmt::Display for Error {
@@ -105,11 +175,36 @@ impl StdError for Error {
     fn description(&self) -> &str {
         &self.description
     }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source as &(dyn StdError + 'static))
+    }
 }
 
 impl From<io::Error> for Error {
     fn from(io_error: io::Error) -> Error {
-        Error::new(ErrorKind::Io(io_error.kind()), format!("{}", io_error))
+        // Errors that started out as one of ours, e.g. from `io::Read` on a port, keep their kind
+        // instead of collapsing `NoDevice` into `Io(NotFound)` and the like
+        if let Some(error) = io_error.get_ref().and_then(|e| e.downcast_ref::<Error>()) {
+            let copy = Error::new(error.kind, error.description.clone());
+            return match error.raw_os_error() {
+                Some(code) => copy.with_os_error(code),
+                None => copy,
+            };
+        }
+        let kind = match io_error.kind() {
+            io::ErrorKind::TimedOut => ErrorKind::TimedOut,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            io::ErrorKind::BrokenPipe => ErrorKind::BrokenPipe,
+            kind => ErrorKind::Io(kind),
+        };
+        Error {
+            kind,
+            description: format!("{}", io_error),
+            source: Some(io_error),
+        }
     }
 }
 
@@ -119,15 +214,27 @@ impl From<Error> for io::Error {
             ErrorKind::NoDevice => io::ErrorKind::NotFound,
             ErrorKind::InvalidInput => io::ErrorKind::InvalidInput,
             ErrorKind::Unknown => io::ErrorKind::Other,
+            ErrorKind::Interrupted => io::ErrorKind::Interrupted,
+            ErrorKind::TimedOut => io::ErrorKind::TimedOut,
+            ErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+            ErrorKind::Busy => io::ErrorKind::Other,
+            ErrorKind::BrokenPipe => io::ErrorKind::BrokenPipe,
             ErrorKind::Io(kind) => kind,
         };
 
-        io::Error::new(kind, error.description)
+        // Keep the original error around so the `ErrorKind` can be recovered through
+        // `io::Error::get_ref()`
+        io::Error::new(kind, error)
     }
 }
 
 /// Number of bits per character
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum DataBits {
     /// 5 bits per character
     Five,
@@ -142,6 +249,49 @@ pub enum DataBits {
     Eight,
 }
 
+// `#[derive(Default)]` on enums requires a newer compiler than the minimum supported version
+#[allow(clippy::derivable_impls)]
+impl Default for DataBits {
+    fn default() -> Self {
+        DataBits::Eight
+    }
+}
+
+impl DataBits {
+    /// Returns the number of data bits, from 5 to 8
+    ///
+    /// This is a stable numeric form suitable for logs and wire formats.
+    pub fn code(&self) -> u8 {
+        match *self {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        }
+    }
+}
+
+impl fmt::Display for DataBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for DataBits {
+    type Err = Error;
+
+    /// Parses the number of data bits, from `"5"` to `"8"`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "5" => Ok(DataBits::Five),
+            "6" => Ok(DataBits::Six),
+            "7" => Ok(DataBits::Seven),
+            "8" => Ok(DataBits::Eight),
+            _ => Err(parse_error("data bits", s)),
+        }
+    }
+}
+
 /// Parity checking modes
 ///
 /// When parity checking is enabled (`Odd` or `Even`) an extra bit is transmitted with
@@ -152,6 +302,11 @@ pub enum DataBits {
 /// Parity checking is disabled by setting `None`, in which case parity bits are not
 /// transmitted.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum Parity {
     /// No parity bit.
     None,
@@ -161,22 +316,146 @@ pub enum Parity {
 
     /// Parity bit sets even number of 1 bits.
     Even,
+
+    /// Parity bit is always 1.
+    ///
+    /// Multidrop protocols use this together with `Space` as a ninth data bit that marks address
+    /// bytes. Not supported by termios on macOS and BSDs.
+    Mark,
+
+    /// Parity bit is always 0.
+    Space,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for Parity {
+    fn default() -> Self {
+        Parity::None
+    }
+}
+
+impl Parity {
+    /// Returns a numeric code for the parity mode
+    ///
+    /// The codes are `None` = 0, `Odd` = 1, `Even` = 2, `Mark` = 3, and `Space` = 4. They are a
+    /// stable numeric form suitable for logs and wire formats.
+    pub fn code(&self) -> u8 {
+        match *self {
+            Parity::None => 0,
+            Parity::Odd => 1,
+            Parity::Even => 2,
+            Parity::Mark => 3,
+            Parity::Space => 4,
+        }
+    }
+}
+
+impl fmt::Display for Parity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Parity::None => "none",
+            Parity::Odd => "odd",
+            Parity::Even => "even",
+            Parity::Mark => "mark",
+            Parity::Space => "space",
+        })
+    }
+}
+
+impl FromStr for Parity {
+    type Err = Error;
+
+    /// Parses `"none"`, `"odd"`, `"even"`, `"mark"`, or `"space"`, ignoring case
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(Parity::None),
+            "odd" => Ok(Parity::Odd),
+            "even" => Ok(Parity::Even),
+            "mark" => Ok(Parity::Mark),
+            "space" => Ok(Parity::Space),
+            _ => Err(parse_error("parity", s)),
+        }
+    }
 }
 
 /// Number of stop bits
 ///
 /// Stop bits are transmitted after every character.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum StopBits {
     /// One stop bit.
     One,
 
+    /// One and a half stop bits.
+    ///
+    /// This is only available with `DataBits::Five`. POSIX platforms have no separate setting for
+    /// it and rely on the UART sending 1.5 stop bits when asked for two with five data bits, so
+    /// any other combination returns an `InvalidInput` error there.
+    OnePointFive,
+
     /// Two stop bits.
+    ///
+    /// On POSIX platforms this can't be combined with `DataBits::Five`, as the UART then sends
+    /// 1.5 stop bits instead. Attempting to configure that combination returns an
+    /// `InvalidInput` error.
     Two,
 }
 
+#[allow(clippy::derivable_impls)]
+impl Default for StopBits {
+    fn default() -> Self {
+        StopBits::One
+    }
+}
+
+impl StopBits {
+    /// Returns the number of stop bits, 1 or 2, or 3 for 1.5 stop bits
+    ///
+    /// This is a stable numeric form suitable for logs and wire formats.
+    pub fn code(&self) -> u8 {
+        match *self {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+            StopBits::OnePointFive => 3,
+        }
+    }
+}
+
+impl fmt::Display for StopBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            StopBits::OnePointFive => f.write_str("1.5"),
+            _ => write!(f, "{}", self.code()),
+        }
+    }
+}
+
+impl FromStr for StopBits {
+    type Err = Error;
+
+    /// Parses the number of stop bits, `"1"`, `"1.5"`, or `"2"`
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "1" => Ok(StopBits::One),
+            "1.5" => Ok(StopBits::OnePointFive),
+            "2" => Ok(StopBits::Two),
+            _ => Err(parse_error("stop bits", s)),
+        }
+    }
+}
+
 /// Flow control modes
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum FlowControl {
     /// No flow control.
     None,
@@ -186,6 +465,113 @@ pub enum FlowControl {
 
     /// Flow control using RTS/CTS signals.
     Hardware,
+
+    /// Flow control using DTR/DSR signals.
+    ///
+    /// This is supported on Windows, macOS, iOS, FreeBSD and DragonFly BSD, and for ports of
+    /// RFC 2217 servers. Other platforms, such as Linux, NetBSD and OpenBSD, have no support for
+    /// it in termios, so setting it fails with `InvalidInput` there.
+    DtrDsr,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for FlowControl {
+    fn default() -> Self {
+        FlowControl::None
+    }
+}
+
+impl fmt::Display for FlowControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            FlowControl::None => "none",
+            FlowControl::Software => "software",
+            FlowControl::Hardware => "hardware",
+            FlowControl::DtrDsr => "dtr/dsr",
+        })
+    }
+}
+
+impl FromStr for FlowControl {
+    type Err = Error;
+
+    /// Parses `"none"`, `"software"`, `"hardware"`, or `"dtr/dsr"`, ignoring case
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(FlowControl::None),
+            "software" => Ok(FlowControl::Software),
+            "hardware" => Ok(FlowControl::Hardware),
+            "dtr/dsr" => Ok(FlowControl::DtrDsr),
+            _ => Err(parse_error("flow control", s)),
+        }
+    }
+}
+
+/// Builds the error for a string that isn't a valid `setting`
+fn parse_error(setting: &str, s: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("Invalid {} setting: {:?}", setting, s),
+    )
+}
+
+/// Which directions a port is opened for
+///
+/// Opening a port for one direction only makes it impossible to use it for the other by
+/// accident, e.g. to tap a bus without any risk of transmitting on it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessMode {
+    /// The port can only be read from.
+    ReadOnly,
+
+    /// The port can only be written to.
+    WriteOnly,
+
+    /// The port can be both read from and written to.
+    ReadWrite,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for AccessMode {
+    fn default() -> Self {
+        AccessMode::ReadWrite
+    }
+}
+
+impl AccessMode {
+    /// Returns `true` if ports opened with this mode can be read from
+    pub fn can_read(&self) -> bool {
+        *self != AccessMode::WriteOnly
+    }
+
+    /// Returns `true` if ports opened with this mode can be written to
+    pub fn can_write(&self) -> bool {
+        *self != AccessMode::ReadOnly
+    }
+
+    /// Returns an `InvalidInput` error if this mode doesn't allow reading
+    pub(crate) fn check_read(&self) -> io::Result<()> {
+        if self.can_read() {
+            Ok(())
+        } else {
+            Err(io::Error::from(Error::new(
+                ErrorKind::InvalidInput,
+                "Port was opened write-only",
+            )))
+        }
+    }
+
+    /// Returns an `InvalidInput` error if this mode doesn't allow writing
+    pub(crate) fn check_write(&self) -> io::Result<()> {
+        if self.can_write() {
+            Ok(())
+        } else {
+            Err(io::Error::from(Error::new(
+                ErrorKind::InvalidInput,
+                "Port was opened read-only",
+            )))
+        }
+    }
 }
 
 /// Specifies which buffer or buffers to purge when calling [`clear`]
@@ -201,6 +587,312 @@ pub enum ClearBuffer {
     All,
 }
 
+/// The levels of the modem status lines at one instant, as returned by [`signals`]
+///
+/// [`signals`]: trait.SerialPort.html#method.signals
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SignalState {
+    /// Whether CTS (Clear To Send) is asserted
+    pub clear_to_send: bool,
+    /// Whether DSR (Data Set Ready) is asserted
+    pub data_set_ready: bool,
+    /// Whether RI (Ring Indicator) is asserted
+    pub ring_indicator: bool,
+    /// Whether CD (Carrier Detect) is asserted
+    pub carrier_detect: bool,
+}
+
+bitflags! {
+    /// A set of modem status lines
+    ///
+    /// Selects the lines `TTYPort::wait_for_signal_change()` and
+    /// `COMPort::wait_for_signal_change()` wait on.
+    pub struct SignalMask: u8 {
+        /// CTS (Clear To Send)
+        const CLEAR_TO_SEND = 0x01;
+        /// DSR (Data Set Ready)
+        const DATA_SET_READY = 0x02;
+        /// RI (Ring Indicator)
+        const RING_INDICATOR = 0x04;
+        /// CD (Carrier Detect)
+        const CARRIER_DETECT = 0x08;
+    }
+}
+
+/// A sequence for resetting the device at the other end of a port with [`reset_device`]
+///
+/// [`reset_device`]: trait.SerialPort.html#method.reset_device
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResetMethod {
+    /// Clear DTR for the given time, then assert it again
+    ///
+    /// Boards like the Arduino Uno have DTR wired to the microcontroller's reset pin through a
+    /// capacitor, so this resets them.
+    DtrPulse(Duration),
+    /// Clear RTS for the given time, then assert it again
+    RtsPulse(Duration),
+    /// Switch to the baud rate `rate` and clear DTR for `hold`, then restore both
+    ///
+    /// This is the "1200bps touch" that boards with native USB, such as the Arduino Leonardo,
+    /// watch for to enter their bootloader. Note that the device usually re-enumerates afterwards,
+    /// possibly under a different port name.
+    BaudTouch {
+        /// The baud rate that triggers the reset
+        rate: u32,
+        /// How long to stay at `rate` with DTR cleared
+        hold: Duration,
+    },
+}
+
+/// Counters for the I/O performed on a port
+///
+/// These accumulate from when the port was opened or the counters were last reset with
+/// [`SerialPort::reset_stats`]. Ports created by `try_clone()` start with their own zeroed counters.
+///
+/// [`SerialPort::reset_stats`]: trait.SerialPort.html#method.reset_stats
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PortStats {
+    /// Total number of bytes read
+    pub bytes_read: u64,
+    /// Total number of bytes written
+    pub bytes_written: u64,
+    /// Number of reads that failed for a reason other than timing out
+    pub read_errors: u64,
+    /// Number of writes that failed for a reason other than timing out
+    pub write_errors: u64,
+    /// Number of reads and writes that timed out
+    pub timeouts: u64,
+}
+
+impl PortStats {
+    pub(crate) fn record_read(&mut self, result: &io::Result<usize>) {
+        match *result {
+            Ok(n) => self.bytes_read += n as u64,
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => self.timeouts += 1,
+            // Not a failure, a non-blocking port just isn't ready yet
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(_) => self.read_errors += 1,
+        }
+    }
+
+    pub(crate) fn record_write(&mut self, result: &io::Result<usize>) {
+        match *result {
+            Ok(n) => self.bytes_written += n as u64,
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => self.timeouts += 1,
+            // Not a failure, a non-blocking port just isn't ready yet
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => (),
+            Err(_) => self.write_errors += 1,
+        }
+    }
+}
+
+/// Receive errors detected by the driver
+///
+/// Returned by [`SerialPort::take_errors`]. Bytes received with parity or framing errors are
+/// still delivered unless the port is set up to drop or mark them, so these counts are how to tell
+/// that received data may be corrupted.
+///
+/// [`SerialPort::take_errors`]: trait.SerialPort.html#method.take_errors
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PortErrors {
+    /// Bytes received with a parity error
+    pub parity: u32,
+    /// Bytes received without a valid stop bit
+    pub framing: u32,
+    /// Overruns of the UART's receive FIFO, which lose data
+    pub overrun: u32,
+    /// Overflows of the driver's input buffer, which lose data
+    pub buffer_overrun: u32,
+    /// Breaks received
+    pub breaks: u32,
+}
+
+impl PortErrors {
+    /// Returns `true` if no errors were detected
+    pub fn is_empty(&self) -> bool {
+        *self == PortErrors::default()
+    }
+
+    /// Returns the errors counted in `self` that were detected after those in `earlier`
+    #[cfg(unix)]
+    pub(crate) fn since(&self, earlier: &PortErrors) -> PortErrors {
+        // The kernel's counters are allowed to wrap around
+        PortErrors {
+            parity: self.parity.wrapping_sub(earlier.parity),
+            framing: self.framing.wrapping_sub(earlier.framing),
+            overrun: self.overrun.wrapping_sub(earlier.overrun),
+            buffer_overrun: self.buffer_overrun.wrapping_sub(earlier.buffer_overrun),
+            breaks: self.breaks.wrapping_sub(earlier.breaks),
+        }
+    }
+}
+
+/// A setting that the driver did not apply as requested
+///
+/// These are reported by [`SerialPortBuilder::open_verified`] after reading back the configuration
+/// of a newly-opened port.
+///
+/// [`SerialPortBuilder::open_verified`]: struct.SerialPortBuilder.html#method.open_verified
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SettingMismatch {
+    /// The baud rate differs, usually because the adapter rounded it to one it supports
+    BaudRate {
+        /// The requested baud rate
+        requested: u32,
+        /// The baud rate read back from the port
+        actual: u32,
+    },
+
+    /// The number of data bits differs
+    DataBits {
+        /// The requested number of data bits
+        requested: DataBits,
+        /// The number of data bits read back from the port
+        actual: DataBits,
+    },
+
+    /// The flow control mode differs
+    FlowControl {
+        /// The requested flow control mode
+        requested: FlowControl,
+        /// The flow control mode read back from the port
+        actual: FlowControl,
+    },
+
+    /// The parity mode differs
+    Parity {
+        /// The requested parity mode
+        requested: Parity,
+        /// The parity mode read back from the port
+        actual: Parity,
+    },
+
+    /// The number of stop bits differs
+    StopBits {
+        /// The requested number of stop bits
+        requested: StopBits,
+        /// The number of stop bits read back from the port
+        actual: StopBits,
+    },
+}
+
+impl fmt::Display for SettingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SettingMismatch::BaudRate { requested, actual } => {
+                write!(f, "baud rate {} was set to {}", requested, actual)
+            }
+            SettingMismatch::DataBits { requested, actual } => {
+                write!(f, "data bits {:?} were set to {:?}", requested, actual)
+            }
+            SettingMismatch::FlowControl { requested, actual } => {
+                write!(f, "flow control {:?} was set to {:?}", requested, actual)
+            }
+            SettingMismatch::Parity { requested, actual } => {
+                write!(f, "parity {:?} was set to {:?}", requested, actual)
+            }
+            SettingMismatch::StopBits { requested, actual } => {
+                write!(f, "stop bits {:?} were set to {:?}", requested, actual)
+            }
+        }
+    }
+}
+
+/// A complete set of serial port settings
+///
+/// The `Default` impl is the common 9600 baud 8N1 configuration with no flow control.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerialPortSettings {
+    /// The baud rate in symbols-per-second
+    pub baud_rate: u32,
+    /// Number of bits used to represent a character sent on the line
+    pub data_bits: DataBits,
+    /// The type of signalling to use for controlling data transfer
+    pub flow_control: FlowControl,
+    /// The type of parity to use for error checking
+    pub parity: Parity,
+    /// Number of bits to use to signal the end of a character
+    pub stop_bits: StopBits,
+    /// Amount of time to wait to receive data before timing out
+    pub timeout: Duration,
+}
+
+impl Default for SerialPortSettings {
+    fn default() -> Self {
+        SerialPortSettings {
+            baud_rate: 9600,
+            data_bits: DataBits::Eight,
+            flow_control: FlowControl::None,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            timeout: Duration::from_millis(0),
+        }
+    }
+}
+
+impl SerialPortSettings {
+    /// Returns the settings that need to change to go from `self` to `other`
+    ///
+    /// Every field of the result is `None` where both sets of settings agree and holds the value
+    /// from `other` otherwise.
+    pub fn diff(&self, other: &SerialPortSettings) -> SettingsDiff {
+        fn changed<T: PartialEq + Copy>(from: T, to: T) -> Option<T> {
+            if from == to {
+                None
+            } else {
+                Some(to)
+            }
+        }
+
+        SettingsDiff {
+            baud_rate: changed(self.baud_rate, other.baud_rate),
+            data_bits: changed(self.data_bits, other.data_bits),
+            flow_control: changed(self.flow_control, other.flow_control),
+            parity: changed(self.parity, other.parity),
+            stop_bits: changed(self.stop_bits, other.stop_bits),
+            timeout: changed(self.timeout, other.timeout),
+        }
+    }
+}
+
+/// The difference between two `SerialPortSettings`, as returned by `SerialPortSettings::diff()`
+///
+/// Fields that are `None` are left unchanged by `SerialPort::apply_diff()`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SettingsDiff {
+    /// The new baud rate, if it changed
+    pub baud_rate: Option<u32>,
+    /// The new number of data bits, if it changed
+    pub data_bits: Option<DataBits>,
+    /// The new flow control mode, if it changed
+    pub flow_control: Option<FlowControl>,
+    /// The new parity mode, if it changed
+    pub parity: Option<Parity>,
+    /// The new number of stop bits, if it changed
+    pub stop_bits: Option<StopBits>,
+    /// The new timeout, if it changed
+    pub timeout: Option<Duration>,
+}
+
+impl SettingsDiff {
+    /// Returns `true` if no settings changed
+    pub fn is_empty(&self) -> bool {
+        *self == SettingsDiff::default()
+    }
+}
+
+/// The framing settings a port accepts, as probed by `SerialPort::supported_settings()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SupportedSettings {
+    /// The supported numbers of data bits
+    pub data_bits: Vec<DataBits>,
+    /// The supported parity modes
+    pub parity: Vec<Parity>,
+    /// The supported numbers of stop bits
+    pub stop_bits: Vec<StopBits>,
+}
+
 /// A struct containing all serial port settings
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SerialPortBuilder {
@@ -218,6 +910,12 @@ pub struct SerialPortBuilder {
     stop_bits: StopBits,
     /// Amount of time to wait to receive data before timing out
     timeout: Duration,
+    /// The state to set the DTR line to when opening the port, if any
+    dtr_on_open: Option<bool>,
+    /// The state to set the RTS line to when opening the port, if any
+    rts_on_open: Option<bool>,
+    /// The directions to open the port for
+    access_mode: AccessMode,
 }
 
 impl SerialPortBuilder {
@@ -263,13 +961,50 @@ impl SerialPortBuilder {
         self
     }
 
+    /// Set the state of the DTR (Data Terminal Ready) line as part of opening the port
+    ///
+    /// On Windows the line is set in the same `SetCommState()` call as the other settings, so it
+    /// isn't toggled again if the driver opens the port with DTR cleared. POSIX kernels assert DTR
+    /// and RTS as the device is opened, before any setting can be applied, so there the line is
+    /// set right after opening; boards that reset on a DTR edge may still see a short pulse. If
+    /// this isn't called the line is left in whatever state the OS puts it in.
+    pub fn dtr_on_open(mut self, state: bool) -> Self {
+        self.dtr_on_open = Some(state);
+        self
+    }
+
+    /// Set the state of the RTS (Request To Send) line as part of opening the port
+    ///
+    /// This is applied like `dtr_on_open()`, except while hardware flow control drives the line.
+    /// If this isn't called the line is left in whatever state the OS puts it in.
+    pub fn rts_on_open(mut self, state: bool) -> Self {
+        self.rts_on_open = Some(state);
+        self
+    }
+
+    /// Set the directions to open the port for
+    ///
+    /// Reading from a port opened `WriteOnly`, or writing to one opened `ReadOnly`, fails with an
+    /// `InvalidInput` error. Ports are opened `ReadWrite` by default.
+    pub fn access_mode(mut self, mode: AccessMode) -> Self {
+        self.access_mode = mode;
+        self
+    }
+
     /// Open a cross-platform interface to the port with the specified settings
-    pub fn open(self) -> Result<Box<dyn SerialPort>> {
+    ///
+    /// The builder isn't consumed, so it can be used to open the same port again or, after
+    /// changing its `path()`, to open other ports with the same settings.
+    pub fn open(&self) -> Result<Box<dyn SerialPort>> {
+        if let Some(scheme) = backend::scheme_of(&self.path) {
+            return self.open_backend(scheme);
+        }
+
         #[cfg(unix)]
-        return posix::TTYPort::open(&self).map(|p| Box::new(p) as Box<dyn SerialPort>);
+        return posix::TTYPort::open(self).map(|p| Box::new(p) as Box<dyn SerialPort>);
 
         #[cfg(windows)]
-        return windows::COMPort::open(&self).map(|p| Box::new(p) as Box<dyn SerialPort>);
+        return windows::COMPort::open(self).map(|p| Box::new(p) as Box<dyn SerialPort>);
 
         #[cfg(not(any(unix, windows)))]
         Err(Error::new(
@@ -278,16 +1013,121 @@ impl SerialPortBuilder {
         ))
     }
 
+    /// Open a cross-platform interface to the port and confirm the specified settings took effect
+    ///
+    /// After opening, the configuration is read back from the port and compared against the
+    /// settings of this builder. This catches adapters that silently round the baud rate or ignore
+    /// a setting. If `strict` is `false` the port is returned along with any settings that didn't
+    /// match, otherwise any mismatch is an error.
+    ///
+    /// # Errors
+    ///
+    /// * `Unknown` if `strict` is `true` and any setting was not applied as requested. The
+    ///   description lists every mismatched setting.
+    /// * Any error that `open()` or reading back the settings can return.
+    pub fn open_verified(
+        &self,
+        strict: bool,
+    ) -> Result<(Box<dyn SerialPort>, Vec<SettingMismatch>)> {
+        let port = self.open()?;
+        let mismatches = self.mismatches(port.as_ref())?;
+
+        if strict && !mismatches.is_empty() {
+            let list: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+            return Err(Error::new(
+                ErrorKind::Unknown,
+                format!("Settings did not apply correctly: {}", list.join(", ")),
+            ));
+        }
+
+        Ok((port, mismatches))
+    }
+
+    /// Opens the port through the backend registered for `scheme`
+    fn open_backend(&self, scheme: &str) -> Result<Box<dyn SerialPort>> {
+        let backend = backend::find_backend(&self.path).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NoDevice,
+                format!("No backend is registered for `{}://` paths", scheme),
+            )
+        })?;
+        let mut port = backend.open(&self.path, &self.settings())?;
+        if let Some(dtr) = self.dtr_on_open {
+            port.write_data_terminal_ready(dtr)?;
+        }
+        if let Some(rts) = self.rts_on_open {
+            port.write_request_to_send(rts)?;
+        }
+        Ok(port)
+    }
+
+    /// Returns the settings chosen with this builder
+    ///
+    /// Every way of opening a port applies these, whether the port is a device or a backend's.
+    pub(crate) fn settings(&self) -> SerialPortSettings {
+        SerialPortSettings {
+            baud_rate: self.baud_rate,
+            data_bits: self.data_bits,
+            flow_control: self.flow_control,
+            parity: self.parity,
+            stop_bits: self.stop_bits,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Compares the settings of this builder against those read back from `port`
+    fn mismatches(&self, port: &dyn SerialPort) -> Result<Vec<SettingMismatch>> {
+        let mut mismatches = Vec::new();
+
+        let baud_rate = port.baud_rate()?;
+        if baud_rate != self.baud_rate {
+            mismatches.push(SettingMismatch::BaudRate {
+                requested: self.baud_rate,
+                actual: baud_rate,
+            });
+        }
+        let data_bits = port.data_bits()?;
+        if data_bits != self.data_bits {
+            mismatches.push(SettingMismatch::DataBits {
+                requested: self.data_bits,
+                actual: data_bits,
+            });
+        }
+        let flow_control = port.flow_control()?;
+        if flow_control != self.flow_control {
+            mismatches.push(SettingMismatch::FlowControl {
+                requested: self.flow_control,
+                actual: flow_control,
+            });
+        }
+        let parity = port.parity()?;
+        if parity != self.parity {
+            mismatches.push(SettingMismatch::Parity {
+                requested: self.parity,
+                actual: parity,
+            });
+        }
+        let stop_bits = port.stop_bits()?;
+        if stop_bits != self.stop_bits {
+            mismatches.push(SettingMismatch::StopBits {
+                requested: self.stop_bits,
+                actual: stop_bits,
+            });
+        }
+
+        Ok(mismatches)
+    }
+
     /// Open a platform-specific interface to the port with the specified settings
     #[cfg(unix)]
-    pub fn open_native(self) -> Result<TTYPort> {
-        posix::TTYPort::open(&self)
+    pub fn open_native(&self) -> Result<TTYPort> {
+        posix::TTYPort::open(self)
     }
 
     /// Open a platform-specific interface to the port with the specified settings
     #[cfg(windows)]
-    pub fn open_native(self) -> Result<COMPort> {
-        windows::COMPort::open(&self)
+    pub fn open_native(&self) -> Result<COMPort> {
+        windows::COMPort::open(self)
     }
 }
 
@@ -295,6 +1135,9 @@ impl SerialPortBuilder {
 ///
 /// This trait is all that's necessary to implement a new serial port driver
 /// for a new platform.
+///
+/// Writes are not buffered in userspace, so `io::Write::flush()` has nothing to do and returns
+/// right away on all platforms. Use `drain()` to wait for written data to be transmitted.
 pub trait SerialPort: Send + io::Read + io::Write {
     // Port settings getters
 
@@ -311,6 +1154,15 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// baud rate.
     fn baud_rate(&self) -> Result<u32>;
 
+    /// Queries the OS for the baud rate the port is actually running at
+    ///
+    /// Drivers may program the nearest rate the hardware can generate rather than the rate that
+    /// was requested, so comparing this against the requested rate shows whether it could be met.
+    /// Unlike `baud_rate()` this never returns a cached value.
+    fn actual_baud_rate(&self) -> Result<u32> {
+        self.baud_rate()
+    }
+
     /// Returns the character size.
     ///
     /// This function returns `None` if the character size could not be determined. This may occur
@@ -327,6 +1179,13 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// control mode to a supported value.
     fn flow_control(&self) -> Result<FlowControl>;
 
+    /// Returns the bytes used for XON and XOFF by software flow control, in that order.
+    ///
+    /// The default implementation returns the standard DC1 (0x11) and DC3 (0x13) characters.
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        Ok((0x11, 0x13))
+    }
+
     /// Returns the parity-checking mode.
     ///
     /// This function returns `None` if the parity mode could not be determined. This may occur if
@@ -345,10 +1204,29 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// Returns the current timeout.
     fn timeout(&self) -> Duration;
 
+    /// Returns the delay inserted between transmitted bytes.
+    ///
+    /// The default implementation returns zero.
+    fn inter_byte_write_delay(&self) -> Duration {
+        Duration::from_secs(0)
+    }
+
+    /// Returns the longest gap allowed between received bytes before a read returns, if any.
+    ///
+    /// The default implementation returns `None`.
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        None
+    }
+
     // Port settings setters
 
     /// Sets the baud rate.
     ///
+    /// Any rate can be requested, not just the standard ones. It's programmed with `termios2` and
+    /// `BOTHER` on Linux and Android, with `IOSSIOSPEED` on macOS and iOS and directly on BSDs
+    /// and Windows. Linux musl and PowerPC targets are limited to the rates with a `Bxxx`
+    /// constant.
+    ///
     /// ## Errors
     ///
     /// If the implementation does not support the requested baud rate, this function may return an
@@ -362,6 +1240,30 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// Sets the flow control mode.
     fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()>;
 
+    /// Sets the bytes used for XON and XOFF by software flow control.
+    ///
+    /// Ports use the ASCII DC1 (0x11) and DC3 (0x13) characters by default. The setting is kept
+    /// independently of the flow control mode, but only has an effect with
+    /// `FlowControl::Software`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if `xon` and `xoff` are the same byte.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port can't change the characters. The default implementation returns
+    ///   this for anything but the standard ones.
+    /// * `Io` for any other error while applying the setting.
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        check_xon_xoff_chars(xon, xoff)?;
+        if (xon, xoff) != (0x11, 0x13) {
+            return Err(Error::new(
+                ErrorKind::Unknown,
+                "Changing the XON/XOFF characters is not supported by this port",
+            ));
+        }
+        Ok(())
+    }
+
     /// Sets the parity-checking mode.
     fn set_parity(&mut self, parity: Parity) -> Result<()>;
 
@@ -369,18 +1271,460 @@ pub trait SerialPort: Send + io::Read + io::Write {
     fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()>;
 
     /// Sets the timeout for future I/O operations.
-    fn set_timeout(&mut self, timeout: Duration) -> Result<()>;
-
-    // Functions for setting non-data control signal pins
+    ///
+    /// Returns the previous timeout, so a temporary change can be undone with another call to
+    /// `set_timeout()`.
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration>;
 
-    /// Sets the state of the RTS (Request To Send) control signal.
+    /// Sets a delay to insert between transmitted bytes.
     ///
-    /// Setting a value of `true` asserts the RTS control signal. `false` clears the signal.
+    /// This is for slow receivers that can't keep up at the full line rate. When the delay is
+    /// nonzero, writes hand bytes to the driver one at a time and sleep for `delay` in between, so
+    /// the gap on the line is at least `delay` minus one character time. The port's timeout then
+    /// applies to each byte rather than to a whole `io::Write::write_all()`. A delay of zero, the
+    /// default, writes at full speed.
     ///
-    /// ## Errors
+    /// # Errors
     ///
-    /// This function returns an error if the RTS control signal could not be set to the desired
-    /// state on the underlying hardware:
+    /// * `Unknown` if the port can't delay its writes. The default implementation returns this for
+    ///   any delay but zero.
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        if delay == Duration::from_secs(0) {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Inter-byte write delays are not supported by this port",
+        ))
+    }
+
+    /// Sets the longest gap allowed between received bytes before a read returns.
+    ///
+    /// Without an inter-byte timeout, the default, a read returns as soon as any data is
+    /// available. With one, a read that has received data keeps going until `buf` is full or the
+    /// line has been quiet for `timeout`, which is how many binary protocols delimit frames. The
+    /// port's timeout still limits the wait for the first byte as well as the read as a whole. This
+    /// maps to `ReadIntervalTimeout` on Windows, which has a resolution of one millisecond, and is
+    /// emulated with `poll()` on POSIX platforms. Non-blocking reads aren't affected.
+    ///
+    /// # Errors
+    ///
+    /// * `Unknown` if the port doesn't support inter-byte timeouts. The default implementation
+    ///   returns this for any timeout but `None`.
+    /// * `Io` if the timeout could not be applied to the port.
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        if timeout.is_none() {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Inter-byte timeouts are not supported by this port",
+        ))
+    }
+
+    /// Calls `on_stall` whenever no data has been received for `timeout`.
+    ///
+    /// This is meant for links that should always carry periodic traffic, such as telemetry. A
+    /// background thread tracks the time since the last byte read from this port and calls
+    /// `on_stall` from that thread once the link has been silent for `timeout`, and again for every
+    /// further `timeout` it stays silent. The timer starts when the watchdog is set. Reads made
+    /// through clones of the port don't count as received data.
+    ///
+    /// Setting a new watchdog replaces the current one. The watchdog is stopped when the port is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// * `Unknown` if the port doesn't track received data, which is what the default
+    ///   implementation returns.
+    /// * `Io` if the watchdog thread could not be spawned.
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        let _ = (timeout, on_stall);
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Receive watchdogs are not supported by this port",
+        ))
+    }
+
+    /// Reads exactly enough bytes to fill `buf`, keeping whatever was read if that fails.
+    ///
+    /// Unlike `io::Read::read_exact()` the number of bytes already read into `buf` is returned
+    /// along with the error, so a read that times out can be resumed without losing data.
+    /// `timeout` limits the whole operation for this call rather than each read. The port's own
+    /// timeout is restored afterwards.
+    ///
+    /// # Errors
+    ///
+    /// On error, returns the number of bytes at the start of `buf` that were filled, along with:
+    ///
+    /// * `TimedOut` if `buf` wasn't filled within `timeout`.
+    /// * `Io(UnexpectedEof)` if the port reported end-of-file.
+    /// * Any other error that `io::Read::read()` or `set_timeout()` can return.
+    fn read_exact_or_partial(
+        &mut self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> std::result::Result<(), (usize, Error)> {
+        let original_timeout = self.timeout();
+        let start = Instant::now();
+        let mut filled = 0;
+
+        let result = loop {
+            if filled == buf.len() {
+                break Ok(());
+            }
+            let remaining = match timeout.checked_sub(start.elapsed()) {
+                Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+                _ => break Err(Error::new(ErrorKind::TimedOut, "Operation timed out")),
+            };
+            if let Err(e) = self.set_timeout(remaining) {
+                break Err(e);
+            }
+
+            match self.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    break Err(Error::new(
+                        ErrorKind::Io(io::ErrorKind::UnexpectedEof),
+                        "Port reported end-of-file",
+                    ))
+                }
+                Ok(n) => filled += n,
+                Err(ref e) if cancel::is_interrupted(e) => (),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        let restored = self.set_timeout(original_timeout);
+        match (result, restored) {
+            (Ok(()), Ok(_)) => Ok(()),
+            (Err(e), _) | (Ok(()), Err(e)) => Err((filled, e)),
+        }
+    }
+
+    /// Reads exactly enough bytes to fill `buf` before `deadline`, reporting how much was read if
+    /// that fails.
+    ///
+    /// This is `read_exact_or_partial()` with a point in time instead of a duration, so one
+    /// deadline can be shared by several reads, e.g. for the header and the body of a message,
+    /// without working out the time left for each of them.
+    ///
+    /// # Errors
+    ///
+    /// The same as `read_exact_or_partial()`, with `TimedOut` if `deadline` passed before
+    /// `buf` was filled.
+    fn read_exact_deadline(
+        &mut self,
+        buf: &mut [u8],
+        deadline: Instant,
+    ) -> std::result::Result<(), (usize, Error)> {
+        let now = Instant::now();
+        let timeout = if deadline > now {
+            deadline - now
+        } else {
+            Duration::from_secs(0)
+        };
+        self.read_exact_or_partial(buf, timeout)
+    }
+
+    /// Reads bytes into `buf` until `delim` is found, like `io::BufRead::read_until()`.
+    ///
+    /// The bytes are appended to `buf`, including the delimiter, and their number is returned.
+    /// `timeout` limits the whole operation rather than each read, and at most `max_len` bytes
+    /// are read. Bytes are read one at a time so nothing after the delimiter is consumed. If this
+    /// fails, the bytes read so far are left in `buf`. The port's own timeout is restored
+    /// afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * `TimedOut` if `delim` wasn't received within `timeout`.
+    /// * `Io(InvalidData)` if `delim` wasn't among the first `max_len` bytes.
+    /// * `Io(UnexpectedEof)` if the port reported end-of-file.
+    /// * Any other error that `io::Read::read()` or `set_timeout()` can return.
+    fn read_until(
+        &mut self,
+        delim: u8,
+        buf: &mut Vec<u8>,
+        max_len: usize,
+        timeout: Duration,
+    ) -> Result<usize> {
+        let original_timeout = self.timeout();
+        let start = Instant::now();
+        let mut read = 0;
+        let mut byte = [0u8];
+
+        let result = loop {
+            if read > 0 && byte[0] == delim {
+                break Ok(read);
+            }
+            if read >= max_len {
+                break Err(Error::new(
+                    ErrorKind::Io(io::ErrorKind::InvalidData),
+                    "Delimiter not found within the maximum length",
+                ));
+            }
+            let remaining = match timeout.checked_sub(start.elapsed()) {
+                Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+                _ => break Err(Error::new(ErrorKind::TimedOut, "Operation timed out")),
+            };
+            if let Err(e) = self.set_timeout(remaining) {
+                break Err(e);
+            }
+
+            match self.read(&mut byte) {
+                Ok(0) => {
+                    break Err(Error::new(
+                        ErrorKind::Io(io::ErrorKind::UnexpectedEof),
+                        "Port reported end-of-file",
+                    ))
+                }
+                Ok(_) => {
+                    buf.push(byte[0]);
+                    read += 1;
+                }
+                Err(ref e) if cancel::is_interrupted(e) => (),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        let restored = self.set_timeout(original_timeout);
+        match (result, restored) {
+            (Ok(read), Ok(_)) => Ok(read),
+            (Err(e), _) | (Ok(_), Err(e)) => Err(e),
+        }
+    }
+
+    /// Writes all of `buf`, reporting how much was written if that fails.
+    ///
+    /// This is the counterpart of `read_exact_or_partial()`: `timeout` limits the whole operation
+    /// and on error the number of bytes from the start of `buf` that were accepted by the driver
+    /// is returned, so the write can be resumed from there. The data is written with `try_write()`
+    /// while checking for space every millisecond, so the port's own timeout and inter-byte write
+    /// delay don't apply.
+    ///
+    /// # Errors
+    ///
+    /// On error, returns the number of bytes at the start of `buf` that were written, along with:
+    ///
+    /// * `TimedOut` if not all of `buf` was written within `timeout`.
+    /// * `Io(WriteZero)` if the port stopped accepting data.
+    /// * Any other error that `try_write()` can return.
+    fn write_all_or_partial(
+        &mut self,
+        buf: &[u8],
+        timeout: Duration,
+    ) -> std::result::Result<(), (usize, Error)> {
+        let start = Instant::now();
+        let mut written = 0;
+        while written < buf.len() {
+            match self.try_write(&buf[written..]) {
+                Ok(0) => {
+                    return Err((
+                        written,
+                        Error::new(
+                            ErrorKind::Io(io::ErrorKind::WriteZero),
+                            "Failed to write whole buffer",
+                        ),
+                    ))
+                }
+                Ok(n) => written += n,
+                Err(ref e)
+                    if e.kind() == ErrorKind::Io(io::ErrorKind::WouldBlock)
+                        || e.kind() == ErrorKind::Io(io::ErrorKind::Interrupted) =>
+                {
+                    if start.elapsed() >= timeout {
+                        return Err((
+                            written,
+                            Error::new(ErrorKind::TimedOut, "Operation timed out"),
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Err(e) => return Err((written, e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Measures the throughput of a loopback connection in bytes per second.
+    ///
+    /// This requires an external loopback that connects TX to RX. `size` bytes of test data are
+    /// written in small chunks, each of which is read back before the next one is written so the
+    /// input buffer can't overflow. `timeout` limits how long each chunk may take to come back.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidInput` if `size` is 0.
+    /// * `TimedOut` if the data didn't come back in time, e.g. because there's no loopback.
+    /// * `Unknown` if the data read back differs from what was written.
+    /// * Any other error that reading from or writing to the port can return.
+    fn measure_throughput(&mut self, size: usize, timeout: Duration) -> Result<f64> {
+        const CHUNK_SIZE: usize = 64;
+
+        if size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Can't measure throughput without any data",
+            ));
+        }
+
+        let data: Vec<u8> = (0..size).map(|i| i as u8).collect();
+        let mut received = [0u8; CHUNK_SIZE];
+
+        let start = Instant::now();
+        for chunk in data.chunks(CHUNK_SIZE) {
+            self.write_all(chunk)?;
+            let received = &mut received[..chunk.len()];
+            self.read_exact_or_partial(received, timeout)
+                .map_err(|(_, e)| e)?;
+            if received != chunk {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    "Data read back differs from the data written",
+                ));
+            }
+        }
+        let elapsed = start.elapsed();
+
+        let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        Ok(size as f64 / seconds)
+    }
+
+    /// Returns all current settings of the port.
+    ///
+    /// # Errors
+    ///
+    /// Any error that the individual getters can return.
+    fn settings(&self) -> Result<SerialPortSettings> {
+        Ok(SerialPortSettings {
+            baud_rate: self.baud_rate()?,
+            data_bits: self.data_bits()?,
+            flow_control: self.flow_control()?,
+            parity: self.parity()?,
+            stop_bits: self.stop_bits()?,
+            timeout: self.timeout(),
+        })
+    }
+
+    /// Applies all of `settings` at once.
+    ///
+    /// Unlike calling the individual setters, the driver is reconfigured with a single call, so
+    /// either all of the settings take effect or none of them do. On macOS a non-standard baud rate
+    /// takes a second call, which can fail after the other settings were applied.
+    ///
+    /// The default implementation calls the individual setters in turn, so if one of them fails
+    /// the settings before it remain applied.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `InvalidInput` if the combination of settings is not supported.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        self.set_baud_rate(settings.baud_rate)?;
+        self.set_data_bits(settings.data_bits)?;
+        self.set_flow_control(settings.flow_control)?;
+        self.set_parity(settings.parity)?;
+        self.set_stop_bits(settings.stop_bits)?;
+        self.set_timeout(settings.timeout)?;
+        Ok(())
+    }
+
+    /// Applies only the settings that are present in `diff`.
+    ///
+    /// This avoids disturbing the port more than necessary when reconfiguring it, e.g. the baud rate
+    /// isn't touched if only the timeout changed. Settings are applied one at a time, so if one of
+    /// them fails the ones before it remain applied.
+    ///
+    /// # Errors
+    ///
+    /// Any error that the individual setters can return.
+    fn apply_diff(&mut self, diff: &SettingsDiff) -> Result<()> {
+        if let Some(baud_rate) = diff.baud_rate {
+            self.set_baud_rate(baud_rate)?;
+        }
+        if let Some(data_bits) = diff.data_bits {
+            self.set_data_bits(data_bits)?;
+        }
+        if let Some(flow_control) = diff.flow_control {
+            self.set_flow_control(flow_control)?;
+        }
+        if let Some(parity) = diff.parity {
+            self.set_parity(parity)?;
+        }
+        if let Some(stop_bits) = diff.stop_bits {
+            self.set_stop_bits(stop_bits)?;
+        }
+        if let Some(timeout) = diff.timeout {
+            self.set_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Probes which data bits, parity, and stop bits settings the port accepts.
+    ///
+    /// Each option is set in turn and read back, and only those that the driver kept are reported.
+    /// The other settings stay as they are while an option is probed, so an option that only
+    /// works in combination with a different value of another setting is not reported. The
+    /// original settings are restored afterwards.
+    ///
+    /// Probing reconfigures the port repeatedly, so don't use it while communicating.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` if the current settings could not be read or restored.
+    fn supported_settings(&mut self) -> Result<SupportedSettings> {
+        Ok(SupportedSettings {
+            data_bits: probe_settings(
+                self,
+                &[
+                    DataBits::Five,
+                    DataBits::Six,
+                    DataBits::Seven,
+                    DataBits::Eight,
+                ],
+                Self::set_data_bits,
+                Self::data_bits,
+            )?,
+            parity: probe_settings(
+                self,
+                &[
+                    Parity::None,
+                    Parity::Odd,
+                    Parity::Even,
+                    Parity::Mark,
+                    Parity::Space,
+                ],
+                Self::set_parity,
+                Self::parity,
+            )?,
+            stop_bits: probe_settings(
+                self,
+                &[StopBits::One, StopBits::OnePointFive, StopBits::Two],
+                Self::set_stop_bits,
+                Self::stop_bits,
+            )?,
+        })
+    }
+
+    // Functions for setting non-data control signal pins
+
+    /// Sets the state of the RTS (Request To Send) control signal.
+    ///
+    /// Setting a value of `true` asserts the RTS control signal. `false` clears the signal.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the RTS control signal could not be set to the desired
+    /// state on the underlying hardware:
     ///
     /// * `NoDevice` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
@@ -399,6 +1743,48 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// * `Io` for any other type of I/O error.
     fn write_data_terminal_ready(&mut self, level: bool) -> Result<()>;
 
+    /// Sets the state of the RTS (Request To Send) control signal.
+    ///
+    /// This is an alias for `write_request_to_send()`.
+    fn set_rts(&mut self, level: bool) -> Result<()> {
+        self.write_request_to_send(level)
+    }
+
+    /// Sets the state of the DTR (Data Terminal Ready) control signal.
+    ///
+    /// This is an alias for `write_data_terminal_ready()`.
+    fn set_dtr(&mut self, level: bool) -> Result<()> {
+        self.write_data_terminal_ready(level)
+    }
+
+    /// Checks whether the RTS/CTS handshake lines appear to be connected.
+    ///
+    /// RTS is toggled and CTS is checked to follow it, which requires an external loopback that
+    /// connects RTS to CTS, or a peer that mirrors RTS onto CTS. Returns `false` if CTS doesn't
+    /// follow, which usually means the adapter or cable doesn't wire up those lines and
+    /// `FlowControl::Hardware` will never pause transmission. RTS is left asserted afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the RTS or CTS control signals could not be accessed:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn test_hardware_flow(&mut self) -> Result<bool> {
+        // Give the lines some time to settle after each change
+        let settle = Duration::from_millis(10);
+
+        self.write_request_to_send(false)?;
+        std::thread::sleep(settle);
+        let cts_low = self.read_clear_to_send()?;
+
+        self.write_request_to_send(true)?;
+        std::thread::sleep(settle);
+        let cts_high = self.read_clear_to_send()?;
+
+        Ok(cts_high && !cts_low)
+    }
+
     // Functions for reading additional pins
 
     /// Reads the state of the CTS (Clear To Send) control signal.
@@ -414,6 +1800,44 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// * `Io` for any other type of I/O error.
     fn read_clear_to_send(&mut self) -> Result<bool>;
 
+    /// Waits for the CTS (Clear To Send) control signal to hold `state` for `stable_for`.
+    ///
+    /// The signal is sampled periodically and must not change for the whole of `stable_for`, so
+    /// transients on a bouncing line are ignored. Returns `true` once the signal has been stable
+    /// or `false` if that didn't happen within `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the state of the CTS control signal could not be read
+    /// from the underlying hardware:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn wait_for_cts(
+        &mut self,
+        state: bool,
+        stable_for: Duration,
+        timeout: Duration,
+    ) -> Result<bool> {
+        let start = Instant::now();
+        let mut stable_since = None;
+        loop {
+            let now = Instant::now();
+            if self.read_clear_to_send()? == state {
+                let since = *stable_since.get_or_insert(now);
+                if now.duration_since(since) >= stable_for {
+                    return Ok(true);
+                }
+            } else {
+                stable_since = None;
+            }
+            if now.duration_since(start) >= timeout {
+                return Ok(false);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
     /// Reads the state of the Data Set Ready control signal.
     ///
     /// This function returns a boolean that indicates whether the DSR control signal is asserted.
@@ -453,8 +1877,33 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// * `Io` for any other type of I/O error.
     fn read_carrier_detect(&mut self) -> Result<bool>;
 
+    /// Reads the state of all modem status lines at once.
+    ///
+    /// The lines are read together with a single call to the driver, so unlike calling the
+    /// individual `read_*()` methods in turn, the levels are all from the same instant.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the state of the control signals could not be read from
+    /// the underlying hardware:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port can't read the lines at once, which is what the default
+    ///   implementation returns.
+    /// * `Io` for any other type of I/O error.
+    fn signals(&self) -> Result<SignalState> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Reading all modem lines at once is not supported by this port",
+        ))
+    }
+
     /// Gets the number of bytes available to be read from the input buffer.
     ///
+    /// The count is only a snapshot: more data may be received as soon as it's been taken. It is
+    /// safe to rely on that many bytes being readable without blocking, as long as nothing else
+    /// reads from the port in the meantime.
+    ///
     /// # Errors
     ///
     /// This function may return the following errors:
@@ -463,8 +1912,25 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// * `Io` for any other type of I/O error.
     fn bytes_to_read(&self) -> Result<u32>;
 
+    /// Returns whether there is any data available to be read from the input buffer.
+    ///
+    /// This is a cheaper check than a read with a short timeout as it doesn't wait on the port.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn has_data(&self) -> Result<bool> {
+        Ok(self.bytes_to_read()? > 0)
+    }
+
     /// Get the number of bytes written to the output buffer, awaiting transmission.
     ///
+    /// The count is only a snapshot: the driver keeps transmitting, so it may already be lower by
+    /// the time it's returned.
+    ///
     /// # Errors
     ///
     /// This function may return the following errors:
@@ -483,16 +1949,494 @@ pub trait SerialPort: Send + io::Read + io::Write {
     /// * `Io` for any other type of I/O error.
     fn clear(&self, buffer_to_clear: ClearBuffer) -> Result<()>;
 
+    /// Discards incoming data until the line has been idle for `idle_gap`.
+    ///
+    /// This resynchronizes with protocols that delimit frames with gaps: after an error, any rest
+    /// of the current frame is thrown away so parsing can resume at the start of the next one.
+    /// Returns the number of bytes discarded. The port's own timeout is restored afterwards.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `TimedOut` if data kept arriving for all of `timeout`.
+    /// * `NoDevice` if the device was disconnected.
+    /// * Any other error that `io::Read::read()` or `set_timeout()` can return.
+    fn discard_until_idle(&mut self, idle_gap: Duration, timeout: Duration) -> Result<usize> {
+        let start = Instant::now();
+        let original_timeout = self.timeout();
+        let mut scratch = [0u8; 256];
+        let mut discarded = 0;
+
+        let result = loop {
+            let remaining = match timeout.checked_sub(start.elapsed()) {
+                Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+                _ => {
+                    break Err(Error::new(
+                        ErrorKind::TimedOut,
+                        "Line did not go idle before the timeout",
+                    ))
+                }
+            };
+            let wait = if remaining < idle_gap {
+                remaining
+            } else {
+                idle_gap
+            };
+            if let Err(e) = self.set_timeout(wait) {
+                break Err(e);
+            }
+
+            match self.read(&mut scratch) {
+                // Nothing more will arrive after end-of-file, so the line is as idle as it gets
+                Ok(0) => break Ok(discarded),
+                Ok(n) => discarded += n,
+                // Only a full gap counts as idle, not the shorter wait at the end of `timeout`
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut && wait == idle_gap => {
+                    break Ok(discarded)
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => (),
+                Err(ref e) if cancel::is_interrupted(e) => (),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        let restored = self.set_timeout(original_timeout);
+        match (result, restored) {
+            (Ok(discarded), Ok(_)) => Ok(discarded),
+            (Err(e), _) | (Ok(_), Err(e)) => Err(e),
+        }
+    }
+
+    /// Copies incoming data to `dst` until `stop` returns `true`.
+    ///
+    /// Unlike `io::copy()`, read timeouts aren't treated as errors: a serial line has no
+    /// end-of-file, so an idle period just means waiting for more data. `stop` is checked before
+    /// every read, which means it's noticed at the latest one port timeout after it starts
+    /// returning `true`. A port with a zero timeout would check it in a busy loop, so it waits for
+    /// data 10ms at a time while copying instead, and gets its timeout back afterwards. Copying
+    /// also ends if the port reports end-of-file. Returns the number of bytes copied.
+    ///
+    /// This takes trait objects so it can be called on a `dyn SerialPort`. The free function
+    /// `serialport::copy_until()` accepts any writer and closure.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * Any error other than a timeout or interruption that `io::Read::read()` or
+    ///   `io::Write::write_all()` on `dst` returns.
+    /// * Any error that `set_timeout()` can return, for ports with a zero timeout.
+    fn copy_until(&mut self, dst: &mut dyn io::Write, stop: &dyn Fn() -> bool) -> Result<u64> {
+        let original_timeout = self.timeout();
+        let polling = original_timeout == Duration::from_secs(0);
+        if polling {
+            self.set_timeout(COPY_POLL_INTERVAL)?;
+        }
+        let mut buf = [0u8; 1024];
+        let mut copied = 0;
+
+        let result = loop {
+            if stop() {
+                break Ok(copied);
+            }
+            match self.read(&mut buf) {
+                Ok(0) => break Ok(copied),
+                Ok(n) => {
+                    if let Err(e) = dst.write_all(&buf[..n]) {
+                        break Err(e.into());
+                    }
+                    copied += n as u64;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => (),
+                Err(ref e) if cancel::is_interrupted(e) => (),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        if !polling {
+            return result;
+        }
+        let restored = self.set_timeout(original_timeout);
+        match (result, restored) {
+            (Ok(copied), Ok(_)) => Ok(copied),
+            (Err(e), _) | (Ok(_), Err(e)) => Err(e),
+        }
+    }
+
+    /// Waits until all data written to the port has been transmitted.
+    ///
+    /// This blocks until the OS output buffer is empty, which can take indefinitely long if flow
+    /// control stalls transmission. See `flush_timeout()` for a bounded alternative.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port can't wait for its output to drain, which is what the default
+    ///   implementation returns.
+    /// * `Io` for any other type of I/O error.
+    fn drain(&mut self) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Draining the output is not supported by this port",
+        ))
+    }
+
+    /// Waits for the output buffer to empty, giving up once `timeout` has elapsed.
+    ///
+    /// This is a bounded alternative to `drain()`, which blocks indefinitely if flow
+    /// control stalls transmission. The output buffer is checked periodically through
+    /// `bytes_to_write()`, so bytes that have already been handed to the UART hardware may still be
+    /// in transit when this returns.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `TimedOut` if the output buffer didn't empty before `timeout` elapsed.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn flush_timeout(&mut self, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            if self.bytes_to_write()? == 0 {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                return Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "Output buffer did not empty before the timeout",
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Waits until everything written to the port has been transmitted, giving up on the output
+    /// buffer once `timeout` has elapsed.
+    ///
+    /// Returns `true` if all data was transmitted and `false` if the output buffer didn't empty in
+    /// time, which is not treated as an error. This is meant for confirming that a message has fully
+    /// gone out before, say, switching the direction of a half-duplex transceiver.
+    ///
+    /// Only the wait for the output buffer, as in `flush_timeout()`, is bounded by `timeout`. Once it
+    /// is empty, `drain()` waits for the UART to finish sending the last bytes, which can't time
+    /// out. That usually takes no longer than sending a FIFO's worth of bytes, but blocks
+    /// indefinitely if flow control stalls the UART at that moment.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn ensure_transmitted(&mut self, timeout: Duration) -> Result<bool> {
+        match self.flush_timeout(timeout) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == ErrorKind::TimedOut => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        self.drain()?;
+        Ok(true)
+    }
+
+    /// Starts transmitting a break.
+    ///
+    /// The line is held in the break state until `clear_break()` is called. A break that is still
+    /// asserted when the port is dropped is cleared then, so an early return or a panic can't
+    /// leave the line stuck. Each clone of a port keeps track of the breaks it set itself.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port can't transmit a break, which is what the default implementation
+    ///   returns.
+    /// * `Io` for any other error while setting the break.
+    fn set_break(&self) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Breaks are not supported by this port",
+        ))
+    }
+
+    /// Stops transmitting a break.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port can't transmit a break, which is what the default implementation
+    ///   returns.
+    /// * `Io` for any other error while clearing the break.
+    fn clear_break(&self) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Breaks are not supported by this port",
+        ))
+    }
+
+    /// Transmits a break for `duration`.
+    ///
+    /// This is `set_break()` followed by `clear_break()` once `duration` has passed. Unlike
+    /// `TTYPort::send_break()`, whose duration is up to the platform, the break lasts at least
+    /// `duration`. The sleep resumes after being interrupted by a signal, so the break is always
+    /// cleared before returning.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while setting or clearing the break.
+    fn send_break(&self, duration: Duration) -> Result<()> {
+        self.set_break()?;
+        std::thread::sleep(duration);
+        self.clear_break()
+    }
+
+    /// Holds the line idle for `bit_times` bit periods at the current baud rate.
+    ///
+    /// The output buffer is drained first so the idle period follows any previously written data.
+    /// This is meant for protocols such as LIN that require a minimum idle time before a frame.
+    ///
+    /// The timing is only as precise as the OS: the idle period is produced by sleeping, which may
+    /// overshoot by the scheduler's granularity, and bytes still in the UART's hardware FIFO after
+    /// draining are transmitted during the idle period. Treat `bit_times` as a lower bound.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `InvalidInput` if the current baud rate is 0.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn send_idle(&mut self, bit_times: u32) -> Result<()> {
+        self.drain()?;
+        let baud_rate = self.baud_rate()?;
+        if baud_rate == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Can't compute bit times with a baud rate of 0",
+            ));
+        }
+        let nanos = u64::from(bit_times) * 1_000_000_000 / u64::from(baud_rate);
+        std::thread::sleep(Duration::from_nanos(nanos));
+        Ok(())
+    }
+
+    /// Resets the device at the other end of the port using `method`.
+    ///
+    /// Afterwards DTR and RTS are asserted and the baud rate is the one the port had before, no
+    /// matter which method was used. See [`ResetMethod`] for what each method does.
+    ///
+    /// [`ResetMethod`]: enum.ResetMethod.html
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `InvalidInput` if the `BaudTouch` rate isn't supported.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn reset_device(&mut self, method: ResetMethod) -> Result<()> {
+        match method {
+            ResetMethod::DtrPulse(duration) => {
+                self.write_request_to_send(true)?;
+                self.write_data_terminal_ready(false)?;
+                std::thread::sleep(duration);
+                self.write_data_terminal_ready(true)
+            }
+            ResetMethod::RtsPulse(duration) => {
+                self.write_data_terminal_ready(true)?;
+                self.write_request_to_send(false)?;
+                std::thread::sleep(duration);
+                self.write_request_to_send(true)
+            }
+            ResetMethod::BaudTouch { rate, hold } => {
+                let original = self.baud_rate()?;
+                self.set_baud_rate(rate)?;
+                let touched = self.write_data_terminal_ready(false);
+                if touched.is_ok() {
+                    std::thread::sleep(hold);
+                }
+                // Restore the baud rate even if DTR couldn't be cleared
+                let restored = self.set_baud_rate(original);
+                touched?;
+                restored?;
+                self.write_request_to_send(true)?;
+                self.write_data_terminal_ready(true)
+            }
+        }
+    }
+
     // Misc methods
 
+    /// Switches reads and writes between blocking and non-blocking mode.
+    ///
+    /// In non-blocking mode, `io::Read::read()` and `io::Write::write()` behave like `try_read()`
+    /// and `try_write()`: they fail with `io::ErrorKind::WouldBlock` instead of waiting for the
+    /// port's timeout, as with `TcpStream::set_nonblocking()`. `io::Write::write_all()` returns
+    /// that error as well once the driver stops accepting data, in which case some of the data
+    /// may already have been written. The mode is shared with clones made by `try_clone()`, so it
+    /// applies to both halves of a split port as well.
+    ///
+    /// # Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port doesn't support non-blocking mode. The default implementation
+    ///   returns this when switching to non-blocking mode.
+    /// * `Io` for any other type of I/O error.
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        if !nonblocking {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Non-blocking mode is not supported by this port",
+        ))
+    }
+
+    /// Writes as much of `buf` as the driver accepts right away, without waiting.
+    ///
+    /// This makes a single write attempt and ignores the port's timeout, which is useful when the
+    /// caller does its own polling for writability. Returns the number of bytes accepted.
+    ///
+    /// On Windows a write can't be made without waiting at all, so this waits for up to a
+    /// millisecond.
+    ///
+    /// ## Errors
+    ///
+    /// * `Io(WouldBlock)` if the driver couldn't accept any data.
+    /// * `InvalidInput` if the port was opened read-only.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port doesn't support non-blocking writes, which is what the default
+    ///   implementation returns.
+    /// * `Io` for any other type of I/O error.
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        let _ = buf;
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Non-blocking writes are not supported by this port",
+        ))
+    }
+
+    /// Reads whatever data is available right away, without waiting.
+    ///
+    /// This makes a single read attempt and ignores the port's timeout, which is useful when the
+    /// caller does its own polling for readability. Returns the number of bytes read, which can be
+    /// 0 only if `read_is_eof_possible()` returns `true`.
+    ///
+    /// ## Errors
+    ///
+    /// * `Io(WouldBlock)` if no data was available.
+    /// * `InvalidInput` if the port was opened write-only.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the port doesn't support non-blocking reads, which is what the default
+    ///   implementation returns.
+    /// * `Io` for any other type of I/O error.
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let _ = buf;
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Non-blocking reads are not supported by this port",
+        ))
+    }
+
+    /// Returns whether `io::Read::read()` can return `Ok(0)` to signal end-of-file.
+    ///
+    /// A read that times out always returns a `TimedOut` error, so on ports where this returns
+    /// `false` a successful read always returns at least one byte. On POSIX platforms some devices,
+    /// such as pseudo-terminals and USB gadgets, can report end-of-file when the other side goes
+    /// away, which then shows up as `Ok(0)`. A hangup reported by the driver is returned as a
+    /// `BrokenPipe` error instead.
+    ///
+    /// The default implementation returns `true`, which is always safe to assume.
+    fn read_is_eof_possible(&self) -> bool {
+        true
+    }
+
+    /// Returns the I/O counters accumulated since the port was opened or last reset.
+    ///
+    /// The default implementation doesn't count anything and always returns zero counters.
+    fn stats(&self) -> PortStats {
+        PortStats::default()
+    }
+
+    /// Resets all I/O counters returned by `stats()` to zero.
+    ///
+    /// The default implementation does nothing.
+    fn reset_stats(&mut self) {}
+
+    /// Returns the receive errors detected since the previous call.
+    ///
+    /// This lets a protocol ask for data to be sent again when it may have been corrupted, even
+    /// if the bytes themselves look fine. Parity errors are only detected while parity is enabled.
+    ///
+    /// On Linux and Android this uses the driver's interrupt counters (`TIOCGICOUNT`), which most
+    /// UART and USB serial drivers keep but pseudo-terminals don't. Counting starts when the port
+    /// is opened, or with the first call for ports created with `try_clone()` or from a raw file
+    /// descriptor. On Windows the driver only reports which kinds of errors occurred since it was
+    /// last asked, so each count is the number of times that happened. Other platforms don't count
+    /// receive errors, but `TTYPort::set_parity_check()` can mark the affected bytes instead.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `Unknown` if the platform or driver doesn't count receive errors, which is what the
+    ///   default implementation returns.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Receive errors are not counted by this port",
+        ))
+    }
+
+    /// Returns a handle for cancelling this port's blocking reads and writes from another thread
+    ///
+    /// This allows a thread that's blocked reading from the port to be shut down without waiting
+    /// for the port's timeout, see `CancellationToken::cancel()`. Every call returns a clone of
+    /// the same token, and clones of the port made with `try_clone()`, including the halves of a
+    /// split port, share it too. Cancelling it stops the blocking reads and writes of all of them.
+    ///
+    /// Waiting for the port's output to drain and waiting for modem line changes on POSIX
+    /// platforms aren't cancelled. On Windows, cancelling needs a port opened for overlapped I/O,
+    /// which is the case unless it was created from a raw handle opened without it.
+    ///
+    /// # Errors
+    ///
+    /// This function may return the following errors:
+    ///
+    /// * `Unknown` if the port's operations can't be cancelled, which is what the default
+    ///   implementation returns.
+    /// * `Io` if the resources for waking up blocked operations could not be created.
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Cancelling operations is not supported by this port",
+        ))
+    }
+
     /// Attempts to clone the `SerialPort`. This allow you to write and read simultaneously from the
     /// same serial connection. Please note that if you want a real asynchronous serial port you
     /// should look at [mio-serial](https://crates.io/crates/mio-serial) or
     /// [tokio-serial](https://crates.io/crates/tokio-serial).
     ///
-    /// Also, you must be very carefull when changing the settings of a cloned `SerialPort` : since
-    /// the settings are cached on a per object basis, trying to modify them from two different
-    /// objects can cause some nasty behavior.
+    /// The clone refers to the same underlying port through a duplicated file descriptor or
+    /// handle, so one thread can block on reads while another writes. Because the port itself is
+    /// shared, changes to its settings, such as the baud rate, parity, or flow control, made
+    /// through one clone are visible through all others. State kept by the object rather than the
+    /// driver is not shared: each clone has its own timeout, I/O counters, and inter-byte write
+    /// delay. On macOS the baud rate is also cached per object, so `baud_rate()` on one clone
+    /// doesn't reflect a change made through another.
     ///
     /// # Errors
     ///
@@ -500,8 +2444,213 @@ pub trait SerialPort: Send + io::Read + io::Write {
     fn try_clone(&self) -> Result<Box<dyn SerialPort>>;
 }
 
+/// The reading half of a port, returned by `TTYPort::split()` or `COMPort::split()`
+///
+/// Each half owns its own clone of the port, so the halves can be moved to different threads
+/// without any locking. They have their own timeouts, with the caveats described for
+/// `SerialPort::try_clone()`.
+#[derive(Debug)]
+pub struct ReadHalf<P> {
+    port: P,
+}
+
+/// The writing half of a port, returned by `TTYPort::split()` or `COMPort::split()`
+///
+/// See `ReadHalf` for how the halves relate to each other.
+#[derive(Debug)]
+pub struct WriteHalf<P> {
+    port: P,
+}
+
+/// How long `SerialPort::copy_until()` waits for data at a time on ports with a zero timeout
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Writes all of `buf` to a port in non-blocking mode, failing with `WouldBlock` once it's full
+///
+/// This is what `io::Write::write_all()` does by default, which the ports override to take their
+/// timeout into account.
+pub(crate) fn write_all_nonblocking<W: io::Write>(port: &mut W, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match port.write(buf) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "Failed to write whole buffer",
+                ))
+            }
+            Ok(n) => buf = &buf[n..],
+            Err(ref e) if cancel::is_interrupted(e) => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that software flow control can tell `xon` and `xoff` apart
+pub(crate) fn check_xon_xoff_chars(xon: u8, xoff: u8) -> Result<()> {
+    if xon == xoff {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "XON and XOFF must be different bytes",
+        ));
+    }
+    Ok(())
+}
+
+/// Splits `port` into halves that own `port` and a clone of it respectively
+pub(crate) fn split<P, F>(port: P, try_clone: F) -> Result<(ReadHalf<P>, WriteHalf<P>)>
+where
+    F: FnOnce(&P) -> Result<P>,
+{
+    let writer = try_clone(&port)?;
+    Ok((ReadHalf { port }, WriteHalf { port: writer }))
+}
+
+impl<P: SerialPort> ReadHalf<P> {
+    /// Returns the current read timeout.
+    pub fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    /// Sets the read timeout, returning the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::set_timeout()` can return.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        self.port.set_timeout(timeout)
+    }
+
+    /// Gets the number of bytes available to be read from the input buffer.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::bytes_to_read()` can return.
+    pub fn bytes_to_read(&self) -> Result<u32> {
+        self.port.bytes_to_read()
+    }
+
+    /// Switches reads between blocking and non-blocking mode.
+    ///
+    /// The mode is shared with the writing half.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::set_nonblocking()` can return.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.port.set_nonblocking(nonblocking)
+    }
+
+    /// Returns a handle for cancelling blocking reads and writes from another thread.
+    ///
+    /// The token is shared with the writing half, so cancelling it stops both halves.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::cancellation_token()` can return.
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.port.cancellation_token()
+    }
+}
+
+impl<P: io::Read> io::Read for ReadHalf<P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.port.read_exact(buf)
+    }
+}
+
+impl<P: SerialPort> WriteHalf<P> {
+    /// Returns the current write timeout.
+    pub fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    /// Sets the write timeout, returning the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::set_timeout()` can return.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        self.port.set_timeout(timeout)
+    }
+
+    /// Gets the number of bytes written to the output buffer that haven't been sent yet.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::bytes_to_write()` can return.
+    pub fn bytes_to_write(&self) -> Result<u32> {
+        self.port.bytes_to_write()
+    }
+
+    /// Switches writes between blocking and non-blocking mode.
+    ///
+    /// The mode is shared with the reading half.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::set_nonblocking()` can return.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.port.set_nonblocking(nonblocking)
+    }
+
+    /// Returns a handle for cancelling blocking reads and writes from another thread.
+    ///
+    /// The token is shared with the reading half, so cancelling it stops both halves.
+    ///
+    /// # Errors
+    ///
+    /// Any error that `SerialPort::cancellation_token()` can return.
+    pub fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.port.cancellation_token()
+    }
+}
+
+impl<P: io::Write> io::Write for WriteHalf<P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.port.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.port.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+/// Returns those of `candidates` that `set` accepts and `get` reads back unchanged
+///
+/// The setting's original value is restored afterwards.
+fn probe_settings<P, T>(
+    port: &mut P,
+    candidates: &[T],
+    set: fn(&mut P, T) -> Result<()>,
+    get: fn(&P) -> Result<T>,
+) -> Result<Vec<T>>
+where
+    P: SerialPort + ?Sized,
+    T: Copy + PartialEq,
+{
+    let original = get(port)?;
+    let mut supported = Vec::new();
+    for &candidate in candidates {
+        if set(port, candidate).is_ok() && get(port)? == candidate {
+            supported.push(candidate);
+        }
+    }
+    set(port, original)?;
+    Ok(supported)
+}
+
 /// Contains all possible USB information about a `SerialPort`
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UsbPortInfo {
     /// Vendor ID
     pub vid: u16,
@@ -517,6 +2666,7 @@ pub struct UsbPortInfo {
 
 /// The physical type of a `SerialPort`
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SerialPortType {
     /// The serial port is connected via USB
     UsbPort(UsbPortInfo),
@@ -530,11 +2680,102 @@ pub enum SerialPortType {
 
 /// A device-independent implementation of serial port information
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SerialPortInfo {
     /// The short name of the serial port
     pub port_name: String,
     /// The hardware device type that exposes this port
     pub port_type: SerialPortType,
+    /// Where the port is physically attached, if known
+    ///
+    /// This is a platform-specific description meant to tell apart ports whose names don't
+    /// reflect the hardware they belong to, such as the channels of a multiport card. On Linux
+    /// it's the udev `ID_PATH` (e.g. `pci-0000:03:00.0`) or the device's path under
+    /// `/sys/devices/`, on Windows the device's location information (e.g. `Port_#0001.Hub_#0003`).
+    pub location: Option<String>,
+    /// Other paths the port can be opened by, such as symlinks created by udev rules
+    ///
+    /// `port_name` is always the canonical device name, so a port is listed only once no matter
+    /// how many names it has. This is only filled in on Linux with the `libudev` feature enabled.
+    pub aliases: Vec<String>,
+    /// The name of the driver backing the port, if known
+    ///
+    /// On Linux this is the kernel driver, e.g. `ftdi_sio` or `cdc_acm`, and on Windows the
+    /// driver's service name, e.g. `FTDIBUS` or `usbser`.
+    pub driver: Option<String>,
+    /// The name shown to users for the port, if known
+    ///
+    /// This is only filled in on Windows, where it's the device's friendly name as shown in the
+    /// Device Manager, e.g. `USB Serial Port (COM7)`.
+    pub friendly_name: Option<String>,
+    /// The identifiers the device's driver was matched by, most specific first
+    ///
+    /// This is only filled in on Windows, where these are the device's hardware IDs, e.g.
+    /// `FTDIBUS\COMPORT&VID_0403&PID_6001`.
+    pub hardware_ids: Vec<String>,
+}
+
+/// Criteria for picking ports out of the ones listed by `available_ports()`
+///
+/// A filter is built up by calling its methods, each of which adds a criterion that a port has to
+/// meet. The default filter matches every port. Criteria on USB properties only match USB ports.
+///
+/// ```no_run
+/// let filter = serialport::PortFilter::new().vid(0x0403).serial_number("A7");
+/// let ports = serialport::available_ports_filtered(&filter).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortFilter {
+    vid: Option<u16>,
+    pid: Option<u16>,
+    serial_number: Option<String>,
+}
+
+impl PortFilter {
+    /// Creates a filter that matches every port
+    pub fn new() -> Self {
+        PortFilter::default()
+    }
+
+    /// Only match USB ports with this vendor ID
+    pub fn vid(mut self, vid: u16) -> Self {
+        self.vid = Some(vid);
+        self
+    }
+
+    /// Only match USB ports with this product ID
+    pub fn pid(mut self, pid: u16) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Only match USB ports whose serial number contains `serial_number`, ignoring case
+    pub fn serial_number(mut self, serial_number: &str) -> Self {
+        self.serial_number = Some(serial_number.to_lowercase());
+        self
+    }
+
+    /// Returns whether `port` meets all the criteria of this filter
+    pub fn matches(&self, port: &SerialPortInfo) -> bool {
+        if *self == PortFilter::default() {
+            return true;
+        }
+        let usb = match port.port_type {
+            SerialPortType::UsbPort(ref usb) => usb,
+            _ => return false,
+        };
+        if self.vid.is_some() && self.vid != Some(usb.vid) {
+            return false;
+        }
+        if self.pid.is_some() && self.pid != Some(usb.pid) {
+            return false;
+        }
+        match (&self.serial_number, &usb.serial_number) {
+            (Some(wanted), Some(serial)) => serial.to_lowercase().contains(wanted.as_str()),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
 }
 
 /// Construct a builder of `SerialPort` objects
@@ -556,23 +2797,104 @@ pub fn new<'a>(path: impl Into<std::borrow::Cow<'a, str>>, baud_rate: u32) -> Se
         parity: Parity::None,
         stop_bits: StopBits::One,
         timeout: Duration::from_millis(0),
+        dtr_on_open: None,
+        rts_on_open: None,
+        access_mode: AccessMode::ReadWrite,
     }
 }
 
 /// Returns a list of all serial ports on system
 ///
 /// It is not guaranteed that these ports exist or are available even if they're
-/// returned by this function.
+/// returned by this function. The ports of registered backends are listed after the OS's, leaving
+/// out those of backends that failed to list them.
 pub fn available_ports() -> Result<Vec<SerialPortInfo>> {
     #[cfg(unix)]
-    return crate::posix::available_ports();
+    let mut ports = crate::posix::available_ports()?;
 
     #[cfg(windows)]
-    return crate::windows::available_ports();
+    let mut ports = crate::windows::available_ports()?;
 
     #[cfg(not(any(unix, windows)))]
-    Err(Error::new(
+    return Err(Error::new(
         ErrorKind::Unknown,
         "available_ports() not implemented for platform",
-    ))
+    ));
+
+    #[cfg(any(unix, windows))]
+    {
+        ports.extend(backend::available_ports());
+        Ok(ports)
+    }
+}
+
+/// Returns the ports listed by `available_ports()` that match `filter`
+///
+/// ## Errors
+///
+/// Any error that `available_ports()` can return.
+pub fn available_ports_filtered(filter: &PortFilter) -> Result<Vec<SerialPortInfo>> {
+    let mut ports = available_ports()?;
+    ports.retain(|port| filter.matches(port));
+    Ok(ports)
+}
+
+/// Returns the USB ports with the given vendor and product IDs
+///
+/// This is a shorthand for `available_ports_filtered()` with a `PortFilter` on both IDs.
+///
+/// ## Errors
+///
+/// Any error that `available_ports()` can return.
+pub fn find_by_vid_pid(vid: u16, pid: u16) -> Result<Vec<SerialPortInfo>> {
+    available_ports_filtered(&PortFilter::new().vid(vid).pid(pid))
+}
+
+/// Returns the baud rate produced by a UART with the given base clock and divisor
+///
+/// This assumes the common 16x oversampling, giving `base_clock / (16 * divisor)`. For example a
+/// 1.8432 MHz clock with a divisor of 12 gives 9600 baud.
+///
+/// # Panics
+///
+/// Panics if `divisor` is 0.
+pub fn baud_rate_from_divisor(base_clock: u32, divisor: u32) -> u32 {
+    (u64::from(base_clock) / (16 * u64::from(divisor))) as u32
+}
+
+/// Returns the divisor that gets closest to `baud_rate` with the given base clock
+///
+/// The result is the divisor along with the relative error of the baud rate it produces, where
+/// `0.01` means the actual rate is 1% faster than requested. The divisor is never less than 1.
+///
+/// # Panics
+///
+/// Panics if `baud_rate` is 0.
+pub fn nearest_divisor(baud_rate: u32, base_clock: u32) -> (u32, f64) {
+    assert!(baud_rate != 0, "baud rate must not be 0");
+
+    let ideal = f64::from(base_clock) / (16.0 * f64::from(baud_rate));
+    let divisor = (ideal.round() as u32).max(1);
+    let actual = f64::from(base_clock) / (16.0 * f64::from(divisor));
+    (
+        divisor,
+        (actual - f64::from(baud_rate)) / f64::from(baud_rate),
+    )
+}
+
+/// Copies incoming data from `port` to `dst` until `stop` returns `true`
+///
+/// This is `SerialPort::copy_until()` for any writer and closure, rather than the trait objects
+/// the method takes so it can be called on a `dyn SerialPort`.
+///
+/// ## Errors
+///
+/// Any error that `SerialPort::copy_until()` can return.
+pub fn copy_until<P, W, F>(port: &mut P, dst: &mut W, stop: F) -> Result<u64>
+where
+    P: SerialPort + ?Sized,
+    W: io::Write,
+    F: Fn() -> bool,
+{
+    port.copy_until(dst, &stop)
 }
@@ -0,0 +1,564 @@
+//! A scriptable port for unit testing code that talks to serial devices
+//!
+//! A `MockSerialPort` plays back a script of the traffic a test expects: the bytes the code under
+//! test should write, the responses the device sends back, and injected timeouts, errors and
+//! modem-line changes. The steps run in the order they were queued.
+//!
+//! ```
+//! use std::io::{BufRead, BufReader, Write};
+//! use serialport::mock::MockSerialPort;
+//!
+//! let port = MockSerialPort::new();
+//! port.expect_write(b"AT\r").respond(b"OK\r\n");
+//!
+//! let mut driver = port.try_clone_native().unwrap();
+//! driver.write_all(b"AT\r").unwrap();
+//! let mut line = String::new();
+//! BufReader::new(driver).read_line(&mut line).unwrap();
+//! assert_eq!(line, "OK\r\n");
+//! assert!(port.is_finished());
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::cancel::SharedToken;
+use crate::watchdog::RxWatchdog;
+use crate::{
+    CancellationToken, ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity, PortErrors,
+    PortStats, Result, SerialPort, SerialPortSettings, SignalState, StopBits,
+};
+
+/// A port that plays back a script instead of talking to a device
+///
+/// Queue the expected traffic with `expect_write()`, `respond()` and the other scripting methods,
+/// then hand the port, or a clone of it, to the code under test. All clones share the script, so
+/// a test can keep one to check `is_finished()` after the port was moved into a driver.
+///
+/// Writes must match the expected bytes exactly, in any number of calls. Writing anything else, or
+/// writing while the script expects a read, panics with a description of the mismatch, which
+/// fails the test. Each read returns data from at most one `respond()` step, so tests control how
+/// the responses are split up. A read while the script expects a write, or after it ran out,
+/// fails with `TimedOut` right away instead of waiting for the port's timeout.
+///
+/// Settings are stored and reported back but don't affect the script. The levels of RTS and DTR
+/// are recorded for `request_to_send()` and `data_terminal_ready()`.
+#[derive(Debug)]
+pub struct MockSerialPort {
+    shared: Arc<Mutex<State>>,
+    settings: SerialPortSettings,
+    xon_xoff_chars: (u8, u8),
+    inter_byte_write_delay: Duration,
+    inter_byte_timeout: Option<Duration>,
+    /// Whether reads and writes fail with `WouldBlock` instead of waiting. Shared with all clones.
+    nonblocking: Arc<AtomicBool>,
+    stats: PortStats,
+    rx_watchdog: Option<RxWatchdog>,
+    /// Cancels blocking reads and writes once `SerialPort::cancellation_token()` was called.
+    /// Shared with all clones.
+    cancel: SharedToken,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    steps: VecDeque<Step>,
+    signals: SignalState,
+    request_to_send: bool,
+    data_terminal_ready: bool,
+    bytes_to_write: u32,
+}
+
+#[derive(Debug)]
+enum Step {
+    /// Bytes the code under test must write next
+    Write(Vec<u8>),
+    /// Bytes for the code under test to read
+    Read(Vec<u8>),
+    /// The next read times out
+    ReadTimeout,
+    /// The next read fails
+    ReadError(Error),
+    /// The next write fails
+    WriteError(Error),
+    /// The modem status lines change
+    Signals(SignalState),
+}
+
+impl State {
+    /// Applies the line changes at the front of the script
+    fn apply_signals(&mut self) {
+        while let Some(&Step::Signals(signals)) = self.steps.front() {
+            self.signals = signals;
+            self.steps.pop_front();
+        }
+    }
+}
+
+impl MockSerialPort {
+    /// Creates a port with an empty script
+    ///
+    /// The port starts with the default `SerialPortSettings`, except for a timeout of 100ms, and
+    /// with all modem status lines deasserted.
+    pub fn new() -> MockSerialPort {
+        MockSerialPort {
+            shared: Arc::new(Mutex::new(State::default())),
+            settings: SerialPortSettings {
+                timeout: Duration::from_millis(100),
+                ..SerialPortSettings::default()
+            },
+            xon_xoff_chars: (0x11, 0x13),
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            nonblocking: Arc::new(AtomicBool::new(false)),
+            stats: PortStats::default(),
+            rx_watchdog: None,
+            cancel: SharedToken::default(),
+        }
+    }
+
+    /// Expects the code under test to write `data` next
+    pub fn expect_write(&self, data: &[u8]) -> &Self {
+        if !data.is_empty() {
+            self.push(Step::Write(data.to_vec()));
+        }
+        self
+    }
+
+    /// Makes `data` available to the next read
+    pub fn respond(&self, data: &[u8]) -> &Self {
+        if !data.is_empty() {
+            self.push(Step::Read(data.to_vec()));
+        }
+        self
+    }
+
+    /// Makes the next read fail with `TimedOut`
+    ///
+    /// This simulates a device that doesn't answer in time, e.g. to test retries.
+    pub fn read_timeout(&self) -> &Self {
+        self.push(Step::ReadTimeout);
+        self
+    }
+
+    /// Makes the next read fail with `error`
+    pub fn read_error(&self, error: Error) -> &Self {
+        self.push(Step::ReadError(error));
+        self
+    }
+
+    /// Makes the next write fail with `error`
+    ///
+    /// The failed write doesn't consume any of the expected bytes.
+    pub fn write_error(&self, error: Error) -> &Self {
+        self.push(Step::WriteError(error));
+        self
+    }
+
+    /// Changes the modem status lines once the script gets here
+    pub fn set_signals(&self, signals: SignalState) -> &Self {
+        self.push(Step::Signals(signals));
+        self.state().apply_signals();
+        self
+    }
+
+    /// Sets the number of bytes `bytes_to_write()` reports, right away
+    ///
+    /// This simulates data stuck in the output buffer, e.g. because flow control stalled it.
+    pub fn set_bytes_to_write(&self, count: u32) -> &Self {
+        self.state().bytes_to_write = count;
+        self
+    }
+
+    /// Returns `true` once every step of the script has run
+    pub fn is_finished(&self) -> bool {
+        self.state().steps.is_empty()
+    }
+
+    /// Returns the level the code under test last set RTS to
+    pub fn request_to_send(&self) -> bool {
+        self.state().request_to_send
+    }
+
+    /// Returns the level the code under test last set DTR to
+    pub fn data_terminal_ready(&self) -> bool {
+        self.state().data_terminal_ready
+    }
+
+    /// Attempts to clone the port
+    ///
+    /// This is the same as `SerialPort::try_clone()` but returns the concrete type instead. The
+    /// clone shares the script and has its own settings and I/O counters.
+    pub fn try_clone_native(&self) -> Result<MockSerialPort> {
+        let mut port = MockSerialPort::new();
+        port.shared = self.shared.clone();
+        port.settings = self.settings;
+        port.xon_xoff_chars = self.xon_xoff_chars;
+        port.inter_byte_write_delay = self.inter_byte_write_delay;
+        port.inter_byte_timeout = self.inter_byte_timeout;
+        port.nonblocking = self.nonblocking.clone();
+        port.cancel = self.cancel.clone();
+        Ok(port)
+    }
+
+    fn push(&self, step: Step) {
+        self.state().steps.push_back(step);
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs the next read step of the script, `unavailable` being the error if there is none
+    fn read_step(&self, buf: &mut [u8], unavailable: Error) -> Result<usize> {
+        if let Some(token) = self.cancel.get() {
+            token.check()?;
+        }
+        let mut state = self.state();
+        state.apply_signals();
+        let result = match state.steps.pop_front() {
+            Some(Step::Read(mut data)) => {
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                if n < data.len() {
+                    data.drain(..n);
+                    state.steps.push_front(Step::Read(data));
+                }
+                Ok(n)
+            }
+            Some(Step::ReadTimeout) => Err(Error::new(ErrorKind::TimedOut, "Operation timed out")),
+            Some(Step::ReadError(e)) => Err(e),
+            Some(step) => {
+                state.steps.push_front(step);
+                Err(unavailable)
+            }
+            None => Err(unavailable),
+        };
+        state.apply_signals();
+        result
+    }
+
+    /// Checks `buf` against the expected writes and consumes the bytes that match
+    fn write_step(&self, buf: &[u8]) -> Result<usize> {
+        if let Some(token) = self.cancel.get() {
+            token.check()?;
+        }
+        let mut state = self.state();
+        let mut written = 0;
+        while written < buf.len() {
+            state.apply_signals();
+            match state.steps.pop_front() {
+                Some(Step::Write(mut expected)) => {
+                    let n = expected.len().min(buf.len() - written);
+                    if expected[..n] != buf[written..written + n] {
+                        // Don't poison the script for other clones
+                        drop(state);
+                        panic!(
+                            "MockSerialPort expected a write of {:?} but got {:?}",
+                            String::from_utf8_lossy(&expected),
+                            String::from_utf8_lossy(&buf[written..]),
+                        );
+                    }
+                    if n < expected.len() {
+                        expected.drain(..n);
+                        state.steps.push_front(Step::Write(expected));
+                    }
+                    written += n;
+                }
+                Some(Step::WriteError(e)) => {
+                    if written > 0 {
+                        // Report the bytes that made it out, the error shows up on the next write
+                        state.steps.push_front(Step::WriteError(e));
+                        break;
+                    }
+                    return Err(e);
+                }
+                step => {
+                    let next = match step {
+                        Some(ref step) => format!("{:?}", step),
+                        None => "the end of the script".to_string(),
+                    };
+                    drop(state);
+                    panic!(
+                        "MockSerialPort got an unexpected write of {:?} while waiting for {}",
+                        String::from_utf8_lossy(&buf[written..]),
+                        next,
+                    );
+                }
+            }
+        }
+        state.apply_signals();
+        Ok(written)
+    }
+}
+
+impl Default for MockSerialPort {
+    fn default() -> MockSerialPort {
+        MockSerialPort::new()
+    }
+}
+
+impl io::Read for MockSerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_read(buf).map_err(io::Error::from);
+        }
+        let unavailable = Error::new(ErrorKind::TimedOut, "Operation timed out");
+        let result = self.read_step(buf, unavailable).map_err(io::Error::from);
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
+        }
+        result
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let token = self.cancel.get();
+        crate::cancel::read_exact(self, buf, token.as_ref())
+    }
+}
+
+impl io::Write for MockSerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let result = self.write_step(buf).map_err(io::Error::from);
+        self.stats.record_write(&result);
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for MockSerialPort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+
+    fn baud_rate(&self) -> Result<u32> {
+        Ok(self.settings.baud_rate)
+    }
+
+    fn data_bits(&self) -> Result<DataBits> {
+        Ok(self.settings.data_bits)
+    }
+
+    fn flow_control(&self) -> Result<FlowControl> {
+        Ok(self.settings.flow_control)
+    }
+
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        Ok(self.xon_xoff_chars)
+    }
+
+    fn parity(&self) -> Result<Parity> {
+        Ok(self.settings.parity)
+    }
+
+    fn stop_bits(&self) -> Result<StopBits> {
+        Ok(self.settings.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.settings.timeout
+    }
+
+    fn inter_byte_write_delay(&self) -> Duration {
+        self.inter_byte_write_delay
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.inter_byte_timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        if baud_rate == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate"));
+        }
+        self.settings.baud_rate = baud_rate;
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
+        self.settings.data_bits = data_bits;
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
+        self.settings.flow_control = flow_control;
+        Ok(())
+    }
+
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        crate::check_xon_xoff_chars(xon, xoff)?;
+        self.xon_xoff_chars = (xon, xoff);
+        Ok(())
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> Result<()> {
+        self.settings.parity = parity;
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
+        self.settings.stop_bits = stop_bits;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        Ok(mem::replace(&mut self.settings.timeout, timeout))
+    }
+
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        self.inter_byte_write_delay = delay;
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inter_byte_timeout = timeout;
+        Ok(())
+    }
+
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        self.rx_watchdog = Some(RxWatchdog::start(timeout, on_stall)?);
+        Ok(())
+    }
+
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        if settings.baud_rate == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate"));
+        }
+        self.settings = *settings;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> Result<()> {
+        self.state().request_to_send = level;
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> Result<()> {
+        self.state().data_terminal_ready = level;
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.clear_to_send)
+    }
+
+    fn read_data_set_ready(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.data_set_ready)
+    }
+
+    fn read_ring_indicator(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.ring_indicator)
+    }
+
+    fn read_carrier_detect(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.carrier_detect)
+    }
+
+    fn signals(&self) -> Result<SignalState> {
+        Ok(self.state().signals)
+    }
+
+    fn bytes_to_read(&self) -> Result<u32> {
+        let mut state = self.state();
+        state.apply_signals();
+        match state.steps.front() {
+            Some(Step::Read(data)) => Ok(data.len() as u32),
+            _ => Ok(0),
+        }
+    }
+
+    fn bytes_to_write(&self) -> Result<u32> {
+        Ok(self.state().bytes_to_write)
+    }
+
+    fn clear(&self, _buffer_to_clear: ClearBuffer) -> Result<()> {
+        // The responses are part of the script, so they aren't discarded
+        Ok(())
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_break(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.nonblocking.store(nonblocking, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        let result = self.write_step(buf);
+        if let Ok(n) = result {
+            self.stats.bytes_written += n as u64;
+        }
+        result
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let unavailable = Error::new(
+            ErrorKind::Io(io::ErrorKind::WouldBlock),
+            "No data available",
+        );
+        let result = self.read_step(buf, unavailable);
+        if let Ok(n) = result {
+            self.stats.bytes_read += n as u64;
+        }
+        result
+    }
+
+    fn read_is_eof_possible(&self) -> bool {
+        false
+    }
+
+    fn stats(&self) -> PortStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = PortStats::default();
+    }
+
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        Ok(PortErrors::default())
+    }
+
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.cancel.get_or_create()
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
+        match self.try_clone_native() {
+            Ok(p) => Ok(Box::new(p)),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::posix::poll::WakePipe;
+use crate::Result;
+
+/// A time limit for a blocking read or write that can be extended while the operation waits
+///
+/// A `Deadline` is passed to `TTYPort::read_deadline()` or `TTYPort::write_deadline()` in place of
+/// the port's timeout. Clones share the same end time, so a clone held by another thread can call
+/// `extend()` to give an operation that is already blocked more time to complete, or `cancel()` to
+/// abort it.
+#[derive(Clone, Debug)]
+pub struct Deadline {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    end: Mutex<Instant>,
+    cancelled: AtomicBool,
+    wake: WakePipe,
+}
+
+impl Deadline {
+    /// Creates a deadline that expires `timeout` from now
+    ///
+    /// ## Errors
+    ///
+    /// * `Io` if the pipe used to wake up blocked operations could not be created.
+    pub fn new(timeout: Duration) -> Result<Deadline> {
+        Ok(Deadline {
+            inner: Arc::new(Inner {
+                end: Mutex::new(Instant::now() + timeout),
+                cancelled: AtomicBool::new(false),
+                wake: WakePipe::new()?,
+            }),
+        })
+    }
+
+    /// Pushes the deadline back by `duration`
+    ///
+    /// Any operation currently blocked on this deadline is woken up and resumes waiting until the
+    /// new end time. Operations that already timed out are not affected.
+    pub fn extend(&self, duration: Duration) {
+        *self.end() += duration;
+        self.inner.wake.wake();
+    }
+
+    /// Cancels any operations using this deadline
+    ///
+    /// Operations currently blocked on this deadline, as well as any started with it later, fail
+    /// with an `Interrupted` error. Through `io::Error` this shows up as
+    /// `io::ErrorKind::Interrupted`, with the original `serialport::Error` available from
+    /// `io::Error::get_ref()`. Note that helpers such as `io::Read::read_exact()` retry
+    /// operations that are interrupted.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.wake.wake();
+    }
+
+    /// Returns `true` if `cancel()` was called on this deadline or any of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the time left before the deadline expires
+    pub fn remaining(&self) -> Duration {
+        let end = *self.end();
+        let now = Instant::now();
+        if end > now {
+            end - now
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+
+    pub(crate) fn wake_pipe(&self) -> &WakePipe {
+        &self.inner.wake
+    }
+
+    fn end(&self) -> MutexGuard<'_, Instant> {
+        // The guarded value is a plain `Instant`, so a panic while holding the lock can't leave it
+        // in an inconsistent state.
+        self.inner
+            .end
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
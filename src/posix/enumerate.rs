@@ -1,11 +1,13 @@
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 use nix::libc::{c_char, c_void};
-#[cfg(all(target_os = "linux", not(target_env = "musl"), feature = "libudev"))]
+#[cfg(any(target_os = "android", target_os = "linux"))]
 use std::ffi::OsStr;
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 use std::ffi::{CStr, CString};
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 use std::mem::MaybeUninit;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use std::path::Path;
 
 use cfg_if::cfg_if;
 #[cfg(any(target_os = "ios", target_os = "macos"))]
@@ -13,7 +15,10 @@ use CoreFoundation_sys::*;
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 use IOKit_sys::*;
 
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use crate::posix::ioctl;
 #[cfg(any(
+    target_os = "android",
     target_os = "freebsd",
     target_os = "ios",
     target_os = "linux",
@@ -21,13 +26,13 @@ use IOKit_sys::*;
 ))]
 use crate::SerialPortType;
 #[cfg(any(
+    target_os = "android",
     target_os = "ios",
-    all(target_os = "linux", not(target_env = "musl"), feature = "libudev"),
+    target_os = "linux",
     target_os = "macos"
 ))]
 use crate::UsbPortInfo;
 #[cfg(any(
-    target_os = "android",
     target_os = "ios",
     all(target_os = "linux", not(target_env = "musl"), feature = "libudev"),
     target_os = "macos",
@@ -36,6 +41,42 @@ use crate::UsbPortInfo;
 use crate::{Error, ErrorKind};
 use crate::{Result, SerialPortInfo};
 
+/// Checks whether a tty is a serial core port without a UART behind it
+///
+/// Drivers such as 8250 register a fixed number of `ttyS` ports whether or not the hardware
+/// exists, and those ports can even be opened. Their port type is `PORT_UNKNOWN` though, which is
+/// read from the tty's `type` attribute in sysfs at `tty_path`, or with `TIOCGSERIAL` on the
+/// device node at `dev_path` for kernels that don't have that attribute.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn is_phantom_uart(tty_path: &Path, dev_path: &Path) -> bool {
+    let port_type = match std::fs::read_to_string(tty_path.join("type")) {
+        Ok(port_type) => port_type.trim().parse().ok(),
+        Err(_) => serial_port_type(dev_path),
+    };
+    port_type == Some(ioctl::PORT_UNKNOWN)
+}
+
+/// Returns whether the tty named `name` is a Bluetooth RFCOMM port
+///
+/// RFCOMM ttys have no bus of their own. While the link is down they don't have a parent device
+/// either, so they're recognized by name.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn is_rfcomm(name: &str) -> bool {
+    name.starts_with("rfcomm")
+}
+
+/// Reads the port type of the tty at `dev_path` with `TIOCGSERIAL`
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn serial_port_type(dev_path: &Path) -> Option<nix::libc::c_int> {
+    use nix::fcntl::OFlag;
+
+    let flags = OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC;
+    let fd = nix::fcntl::open(dev_path, flags, nix::sys::stat::Mode::empty()).ok()?;
+    let serial = ioctl::tiocgserial(fd);
+    let _ = nix::unistd::close(fd);
+    serial.ok().map(|serial| serial.type_)
+}
+
 /// Retrieves the udev property value named by `key`. If the value exists, then it will be
 /// converted to a String, otherwise None will be returned.
 #[cfg(all(target_os = "linux", not(target_env = "musl"), feature = "libudev"))]
@@ -64,6 +105,15 @@ fn udev_hex_property_as_u16(d: &libudev::Device, key: &str) -> Result<u16> {
     }
 }
 
+/// Retrieves the symlinks udev created for the device, which are listed in its `DEVLINKS` property
+#[cfg(all(target_os = "linux", not(target_env = "musl"), feature = "libudev"))]
+fn udev_aliases(d: &libudev::Device) -> Vec<String> {
+    match udev_property_as_string(d, "DEVLINKS") {
+        Some(links) => links.split_whitespace().map(String::from).collect(),
+        None => Vec::new(),
+    }
+}
+
 #[cfg(all(target_os = "linux", not(target_env = "musl"), feature = "libudev"))]
 fn port_type(d: &libudev::Device) -> Result<SerialPortType> {
     match d.property_value("ID_BUS").and_then(OsStr::to_str) {
@@ -80,6 +130,7 @@ fn port_type(d: &libudev::Device) -> Result<SerialPortType> {
             }))
         }
         Some("pci") => Ok(SerialPortType::PciPort),
+        _ if is_rfcomm(&d.sysname().to_string_lossy()) => Ok(SerialPortType::BluetoothPort),
         _ => Ok(SerialPortType::Unknown),
     }
 }
@@ -205,6 +256,10 @@ cfg_if! {
     if #[cfg(any(target_os = "ios", target_os = "macos"))] {
         /// Scans the system for serial ports and returns a list of them.
         /// The `SerialPortInfo` struct contains the name of the port which can be used for opening it.
+        ///
+        /// Every port is listed twice: once as its callout device (`/dev/cu.*`) followed by its
+        /// dial-in device (`/dev/tty.*`). Opening the dial-in device blocks until the DCD (Data
+        /// Carrier Detect) signal is asserted, so the callout device is usually the one to use.
         pub fn available_ports() -> Result<Vec<SerialPortInfo>> {
             use mach::kern_return::KERN_SUCCESS;
             use mach::port::{mach_port_t, MACH_PORT_NULL};
@@ -289,35 +344,45 @@ cfg_if! {
                         0,
                     );
                     if result == KERN_SUCCESS {
-                        // We only care about the IODialinDevice, which is the device path for this port.
-                        let key = CString::new("IODialinDevice").unwrap();
-                        let key_cfstring = CFStringCreateWithCString(
-                            kCFAllocatorDefault,
-                            key.as_ptr(),
-                            kCFStringEncodingUTF8,
-                        );
-                        let value = CFDictionaryGetValue(props.assume_init(), key_cfstring as *const c_void);
-
-                        let type_id = CFGetTypeID(value);
-                        if type_id == CFStringGetTypeID() {
-                            let mut buf = Vec::with_capacity(256);
-
-                            CFStringGetCString(
-                                value as CFStringRef,
-                                buf.as_mut_ptr(),
-                                256,
+                        // Each port is exposed both as a callout device (`/dev/cu.*`) and a dial-in
+                        // device (`/dev/tty.*`). List the callout device first as it's usually the one
+                        // to use.
+                        let props = props.assume_init();
+                        for device_key in &["IOCalloutDevice", "IODialinDevice"] {
+                            let key = CString::new(*device_key).unwrap();
+                            let key_cfstring = CFStringCreateWithCString(
+                                kCFAllocatorDefault,
+                                key.as_ptr(),
                                 kCFStringEncodingUTF8,
                             );
-                            let path = CStr::from_ptr(buf.as_ptr()).to_string_lossy();
-                            vec.push(SerialPortInfo {
-                                port_name: path.to_string(),
-                                port_type: port_type(modem_service),
-                            });
-                        } else {
-                            return Err(Error::new(
-                                ErrorKind::Unknown,
-                                "Found invalid type for TypeID",
-                            ));
+                            let value = CFDictionaryGetValue(props, key_cfstring as *const c_void);
+
+                            let type_id = CFGetTypeID(value);
+                            if type_id == CFStringGetTypeID() {
+                                let mut buf = Vec::with_capacity(256);
+
+                                CFStringGetCString(
+                                    value as CFStringRef,
+                                    buf.as_mut_ptr(),
+                                    256,
+                                    kCFStringEncodingUTF8,
+                                );
+                                let path = CStr::from_ptr(buf.as_ptr()).to_string_lossy();
+                                vec.push(SerialPortInfo {
+                                    port_name: path.to_string(),
+                                    port_type: port_type(modem_service),
+                                    location: None,
+                                    aliases: Vec::new(),
+                                    driver: None,
+                                    friendly_name: None,
+                                    hardware_ids: Vec::new(),
+                                });
+                            } else {
+                                return Err(Error::new(
+                                    ErrorKind::Unknown,
+                                    "Found invalid type for TypeID",
+                                ));
+                            }
                         }
                     } else {
                         return Err(Error::new(ErrorKind::Unknown, format!("ERROR: {}", result)));
@@ -340,13 +405,18 @@ cfg_if! {
                 enumerator.match_subsystem("tty")?;
                 let devices = enumerator.scan_devices()?;
                 for d in devices {
-                    if let Some(p) = d.parent() {
+                    let parent = d.parent();
+                    if parent.is_some() || is_rfcomm(&d.sysname().to_string_lossy()) {
                         if let Some(devnode) = d.devnode() {
                             if let Some(path) = devnode.to_str() {
-                                if let Some(driver) = p.driver() {
-                                    if driver == "serial8250" && crate::new(path, 9600).open().is_err() {
-                                        continue;
-                                    }
+                                let driver = parent
+                                    .as_ref()
+                                    .and_then(|p| p.driver())
+                                    .and_then(OsStr::to_str)
+                                    .map(String::from);
+                                let is_uart = d.sysname().to_string_lossy().starts_with("ttyS");
+                                if is_uart && is_phantom_uart(d.syspath(), devnode) {
+                                    continue;
                                 }
                                 // Stop bubbling up port_type errors here so problematic ports are just
                                 // skipped instead of causing no ports to be returned.
@@ -354,6 +424,11 @@ cfg_if! {
                                     vec.push(SerialPortInfo {
                                         port_name: String::from(path),
                                         port_type: pt,
+                                        location: udev_property_as_string(&d, "ID_PATH"),
+                                        aliases: udev_aliases(&d),
+                                        driver,
+                                        friendly_name: None,
+                                        hardware_ids: Vec::new(),
                                     });
                                 }
                             }
@@ -363,42 +438,154 @@ cfg_if! {
             }
             Ok(vec)
         }
-    } else if #[cfg(target_os = "linux")] {
-        use std::fs::File;
-        use std::io::Read;
-        use std::path::Path;
+    } else if #[cfg(any(target_os = "android", target_os = "linux"))] {
+        /// Returns the path of the device behind the tty at `tty_path` relative to `/sys/devices/`
+        fn sysfs_location(tty_path: &Path) -> Option<String> {
+            let device = std::fs::canonicalize(tty_path.join("device")).ok()?;
+            device
+                .strip_prefix("/sys/devices/")
+                .ok()
+                .map(|location| location.to_string_lossy().into_owned())
+        }
 
-        /// Enumerating serial ports on non-Linux POSIX platforms is disabled by disabled the "libudev"
-        /// default feature.
-        pub fn available_ports() -> Result<Vec<SerialPortInfo>> {
+        /// Returns the name of the driver bound to the device behind the tty at `tty_path`
+        fn sysfs_driver(tty_path: &Path) -> Option<String> {
+            let driver = std::fs::read_link(tty_path.join("device").join("driver")).ok()?;
+            driver
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        }
+
+        /// Reads a sysfs attribute, returning `None` if it's missing or empty
+        fn sysfs_attribute(path: &Path) -> Option<String> {
+            let value = std::fs::read_to_string(path).ok()?;
+            let value = value.trim_end_matches('\n');
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        }
+
+        /// Determines how the device behind the tty at `tty_path` is connected
+        fn sysfs_port_type(tty_path: &Path) -> SerialPortType {
+            if tty_path.file_name().and_then(OsStr::to_str).map(is_rfcomm) == Some(true) {
+                return SerialPortType::BluetoothPort;
+            }
+            let device = match std::fs::canonicalize(tty_path.join("device")) {
+                Ok(device) => device,
+                Err(_) => return SerialPortType::Unknown,
+            };
+
+            // The tty belongs to an interface of a USB device, or to a port of a USB serial
+            // converter below that, so search upwards for the device with the descriptors
+            for dir in device.ancestors() {
+                let id = |name| {
+                    sysfs_attribute(&dir.join(name)).and_then(|id| u16::from_str_radix(&id, 16).ok())
+                };
+                if let (Some(vid), Some(pid)) = (id("idVendor"), id("idProduct")) {
+                    return SerialPortType::UsbPort(UsbPortInfo {
+                        vid,
+                        pid,
+                        serial_number: sysfs_attribute(&dir.join("serial")),
+                        manufacturer: sysfs_attribute(&dir.join("manufacturer")),
+                        product: sysfs_attribute(&dir.join("product")),
+                    });
+                }
+            }
+
+            let subsystem = std::fs::read_link(device.join("subsystem")).ok();
+            match subsystem.as_ref().and_then(|s| s.file_name()).and_then(OsStr::to_str) {
+                Some("pci") => SerialPortType::PciPort,
+                _ => SerialPortType::Unknown,
+            }
+        }
+
+        /// Lists the ports found through `/sys/class/tty/`
+        fn sysfs_ports() -> Result<Vec<SerialPortInfo>> {
             let mut vec = Vec::new();
-            let sys_path = Path::new("/sys/class/tty/");
-            let mut s;
-            for path in sys_path.read_dir().expect("/sys/class/tty/ doesn't exist on this system") {
-                let raw_path = path?.path().clone();
-                let mut path = raw_path.clone();
-
-                path.push("device");
-                if !path.is_dir() {
+            for entry in Path::new("/sys/class/tty/").read_dir()? {
+                let tty_path = entry?.path();
+                let name = match tty_path.file_name().and_then(OsStr::to_str) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if !tty_path.join("device").join("driver").exists() && !is_rfcomm(name) {
+                    continue;
+                }
+                let dev_path = Path::new("/dev").join(name);
+                if name.starts_with("ttyS") && is_phantom_uart(&tty_path, &dev_path) {
                     continue;
                 }
 
-                path.push("driver_override");
-                if path.is_file() {
-                    s = String::new();
-                    File::open(path)?.read_to_string(&mut s)?;
-                    if &s == "(null)\n" {
-                        continue;
-                    }
+                vec.push(SerialPortInfo {
+                    port_name: dev_path.to_string_lossy().into_owned(),
+                    port_type: sysfs_port_type(&tty_path),
+                    location: sysfs_location(&tty_path),
+                    aliases: Vec::new(),
+                    driver: sysfs_driver(&tty_path),
+                    friendly_name: None,
+                    hardware_ids: Vec::new(),
+                });
+            }
+            Ok(vec)
+        }
+
+        /// Lists the USB and UART ttys in `/dev/`, for when sysfs can't be read
+        #[cfg(target_os = "android")]
+        fn dev_ports() -> Result<Vec<SerialPortInfo>> {
+            let mut vec = Vec::new();
+            for entry in Path::new("/dev/").read_dir()? {
+                let dev_path = entry?.path();
+                let name = match dev_path.file_name().and_then(OsStr::to_str) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let is_uart = name.starts_with("ttyS");
+                if !is_uart && !name.starts_with("ttyUSB") && !name.starts_with("ttyACM") {
+                    continue;
+                }
+                let tty_path = Path::new("/sys/class/tty/").join(name);
+                if is_uart && is_phantom_uart(&tty_path, &dev_path) {
+                    continue;
                 }
 
                 vec.push(SerialPortInfo {
-                    port_name: raw_path.to_string_lossy().to_string(),
+                    port_name: dev_path.to_string_lossy().into_owned(),
                     port_type: SerialPortType::Unknown,
+                    location: None,
+                    aliases: Vec::new(),
+                    driver: None,
+                    friendly_name: None,
+                    hardware_ids: Vec::new(),
                 });
             }
             Ok(vec)
         }
+
+        /// Scans the system for serial ports and returns a list of them.
+        /// The `SerialPortInfo` struct contains the name of the port
+        /// which can be used for opening it.
+        ///
+        /// Without `libudev` the ports are found through `/sys/class/tty/`. Only ttys backed by a
+        /// device that's bound to a driver are listed, which leaves out virtual consoles and
+        /// pseudo-terminals, as well as `ttyS` ports without a UART behind them. Bluetooth RFCOMM
+        /// ports are listed as well.
+        ///
+        /// Android usually doesn't allow apps to look into sysfs. If no ports are found there, the
+        /// `ttyUSB`, `ttyACM` and `ttyS` devices in `/dev/` are listed instead, with an `Unknown`
+        /// port type since their USB descriptors can't be read.
+        pub fn available_ports() -> Result<Vec<SerialPortInfo>> {
+            let ports = sysfs_ports();
+            #[cfg(target_os = "android")]
+            {
+                match ports {
+                    Ok(ref ports) if !ports.is_empty() => (),
+                    _ => return dev_ports(),
+                }
+            }
+            ports
+        }
     } else if #[cfg(target_os = "freebsd")] {
         use std::path::Path;
 
@@ -417,6 +604,11 @@ cfg_if! {
                         vec.push(SerialPortInfo {
                             port_name: path.path().to_string_lossy().to_string(),
                             port_type: SerialPortType::Unknown,
+                            location: None,
+                            aliases: Vec::new(),
+                            driver: None,
+                            friendly_name: None,
+                            hardware_ids: Vec::new(),
                         });
                     }
                 }
@@ -16,45 +16,70 @@ impl From<libudev::Error> for Error {
 
 impl From<nix::Error> for Error {
     fn from(e: nix::Error) -> Error {
+        let error = from_nix_error(e);
         match e {
-            nix::Error::InvalidPath | nix::Error::InvalidUtf8 => {
-                Error::new(ErrorKind::InvalidInput, "Invalid input")
-            }
-            nix::Error::UnsupportedOperation => Error::new(ErrorKind::Unknown, "Unknown error"),
-            nix::Error::Sys(e @ nix::errno::Errno::ETIMEDOUT) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::TimedOut), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::ECONNABORTED) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::ConnectionAborted), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::ECONNRESET) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::ConnectionReset), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::ECONNREFUSED) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::ConnectionRefused), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::ENOTCONN) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::NotConnected), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::EADDRINUSE) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::AddrInUse), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::EADDRNOTAVAIL) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::AddrNotAvailable), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::EAGAIN) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::WouldBlock), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::EINTR) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::Interrupted), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::EACCES) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::PermissionDenied), e.desc())
-            }
-            nix::Error::Sys(e @ nix::errno::Errno::ENOENT) => {
-                Error::new(ErrorKind::Io(io::ErrorKind::NotFound), e.desc())
-            }
-            nix::Error::Sys(e) => Error::new(ErrorKind::Unknown, e.desc()),
+            nix::Error::Sys(errno) => error.with_os_error(errno as i32),
+            _ => error,
         }
     }
 }
+
+/// Maps `e` to an error of the closest kind
+fn from_nix_error(e: nix::Error) -> Error {
+    match e {
+        nix::Error::InvalidPath | nix::Error::InvalidUtf8 => {
+            Error::new(ErrorKind::InvalidInput, "Invalid input")
+        }
+        nix::Error::UnsupportedOperation => Error::new(ErrorKind::Unknown, "Unknown error"),
+        nix::Error::Sys(e @ nix::errno::Errno::ETIMEDOUT) => {
+            Error::new(ErrorKind::TimedOut, e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::ECONNABORTED) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::ConnectionAborted), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::ECONNRESET) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::ConnectionReset), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::ECONNREFUSED) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::ConnectionRefused), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::ENOTCONN) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::NotConnected), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EADDRINUSE) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::AddrInUse), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EADDRNOTAVAIL) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::AddrNotAvailable), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EAGAIN) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::WouldBlock), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EINTR) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::Interrupted), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EACCES) => {
+            Error::new(ErrorKind::PermissionDenied, e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::ENOENT) => {
+            Error::new(ErrorKind::Io(io::ErrorKind::NotFound), e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EPERM) => {
+            Error::new(ErrorKind::PermissionDenied, e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EPIPE) => {
+            Error::new(ErrorKind::BrokenPipe, e.desc())
+        }
+        // Opening a port that another process holds in exclusive mode fails with this
+        nix::Error::Sys(e @ nix::errno::Errno::EBUSY) => Error::new(ErrorKind::Busy, e.desc()),
+        // USB serial adapters that are unplugged while open report these
+        nix::Error::Sys(e @ nix::errno::Errno::ENXIO)
+        | nix::Error::Sys(e @ nix::errno::Errno::ENODEV) => {
+            Error::new(ErrorKind::NoDevice, e.desc())
+        }
+        nix::Error::Sys(e @ nix::errno::Errno::EINVAL) => {
+            Error::new(ErrorKind::InvalidInput, e.desc())
+        }
+        nix::Error::Sys(e) => Error::new(ErrorKind::Unknown, e.desc()),
+    }
+}
@@ -0,0 +1,97 @@
+//! Detection of serial devices being added or removed through udev
+
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::posix::poll::{self, WakePipe, Wakeup};
+use crate::{Error, ErrorKind, Result};
+
+/// A background thread that calls back whenever a tty device is added or removed
+///
+/// The thread is stopped when this is dropped.
+#[derive(Debug)]
+pub(crate) struct ChangeMonitor {
+    wake: Arc<WakePipe>,
+}
+
+impl ChangeMonitor {
+    /// Starts calling `on_change` for every change until it returns `false`
+    pub(crate) fn start(mut on_change: Box<dyn FnMut() -> bool + Send>) -> Result<Self> {
+        let wake = Arc::new(WakePipe::new()?);
+        let thread_wake = wake.clone();
+        let (ready, started) = mpsc::sync_channel(1);
+        thread::Builder::new()
+            .name("serialport-hotplug".into())
+            .spawn(move || {
+                // The udev handles can't be sent between threads, so they're set up on this one
+                let context = match libudev::Context::new() {
+                    Ok(context) => context,
+                    Err(e) => {
+                        let _ = ready.send(Err(Error::from(e)));
+                        return;
+                    }
+                };
+                let mut socket = match listen(&context) {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready.send(Ok(()));
+
+                loop {
+                    let wait = poll::wait_read_fd_or_wake(
+                        socket.as_raw_fd(),
+                        &thread_wake,
+                        Duration::from_secs(60),
+                    );
+                    match wait {
+                        Ok(Wakeup::Ready) => (),
+                        Ok(Wakeup::Woken) => return,
+                        Err(ref e)
+                            if e.kind() == io::ErrorKind::TimedOut
+                                || e.kind() == io::ErrorKind::Interrupted =>
+                        {
+                            continue
+                        }
+                        Err(_) => return,
+                    }
+
+                    let mut changed = false;
+                    while let Some(event) = socket.receive_event() {
+                        match event.event_type() {
+                            libudev::EventType::Add | libudev::EventType::Remove => changed = true,
+                            _ => (),
+                        }
+                    }
+                    if changed && !on_change() {
+                        return;
+                    }
+                }
+            })?;
+
+        match started.recv() {
+            Ok(result) => result.map(|()| ChangeMonitor { wake }),
+            Err(_) => Err(Error::new(
+                ErrorKind::Unknown,
+                "The port watcher thread exited unexpectedly",
+            )),
+        }
+    }
+}
+
+impl Drop for ChangeMonitor {
+    fn drop(&mut self) {
+        self.wake.wake();
+    }
+}
+
+fn listen(context: &libudev::Context) -> Result<libudev::MonitorSocket<'_>> {
+    let mut monitor = libudev::Monitor::new(context)?;
+    monitor.match_subsystem("tty")?;
+    Ok(monitor.listen()?)
+}
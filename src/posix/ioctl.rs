@@ -8,10 +8,15 @@ use crate::Result;
 // These are wrapped in a module because they're `pub` by default
 mod raw {
     use nix::libc;
-    use nix::{ioctl_none_bad, ioctl_read, ioctl_read_bad, ioctl_write_ptr, ioctl_write_ptr_bad};
+    use nix::{
+        ioctl_none_bad, ioctl_read, ioctl_read_bad, ioctl_readwrite_bad, ioctl_write_ptr,
+        ioctl_write_ptr_bad,
+    };
 
     ioctl_none_bad!(tiocexcl, libc::TIOCEXCL);
     ioctl_none_bad!(tiocnxcl, libc::TIOCNXCL);
+    ioctl_none_bad!(tiocsbrk, libc::TIOCSBRK);
+    ioctl_none_bad!(tioccbrk, libc::TIOCCBRK);
     ioctl_read_bad!(tiocmget, libc::TIOCMGET, libc::c_int);
 
     #[cfg(any(target_os = "android", target_os = "linux"))]
@@ -78,6 +83,87 @@ mod raw {
         0x2B,
         libc::termios2
     );
+    // Not exposed by libc. See: /usr/include/asm-generic/ioctls.h
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        not(any(target_arch = "mips", target_arch = "mips64"))
+    ))]
+    const TIOCGICOUNT: libc::c_ulong = 0x545D;
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        any(target_arch = "mips", target_arch = "mips64")
+    ))]
+    const TIOCGICOUNT: libc::c_ulong = 0x5492;
+    ioctl_read_bad!(
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        tiocgicount,
+        TIOCGICOUNT,
+        super::SerialIcounter
+    );
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        not(any(target_arch = "mips", target_arch = "mips64"))
+    ))]
+    const TIOCGSERIAL: libc::c_ulong = 0x541E;
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        any(target_arch = "mips", target_arch = "mips64")
+    ))]
+    const TIOCGSERIAL: libc::c_ulong = 0x5484;
+    ioctl_read_bad!(
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        tiocgserial,
+        TIOCGSERIAL,
+        super::SerialStruct
+    );
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        not(any(target_arch = "mips", target_arch = "mips64"))
+    ))]
+    const TIOCSSERIAL: libc::c_ulong = 0x541F;
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        any(target_arch = "mips", target_arch = "mips64")
+    ))]
+    const TIOCSSERIAL: libc::c_ulong = 0x5485;
+    ioctl_write_ptr_bad!(
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        tiocsserial,
+        TIOCSSERIAL,
+        super::SerialStruct
+    );
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        not(any(target_arch = "mips", target_arch = "mips64"))
+    ))]
+    const TIOCGRS485: libc::c_ulong = 0x542E;
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        any(target_arch = "mips", target_arch = "mips64")
+    ))]
+    const TIOCGRS485: libc::c_ulong = 0x4020542E;
+    ioctl_read_bad!(
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        tiocgrs485,
+        TIOCGRS485,
+        super::SerialRs485
+    );
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        not(any(target_arch = "mips", target_arch = "mips64"))
+    ))]
+    const TIOCSRS485: libc::c_ulong = 0x542F;
+    #[cfg(all(
+        any(target_os = "android", target_os = "linux"),
+        any(target_arch = "mips", target_arch = "mips64")
+    ))]
+    const TIOCSRS485: libc::c_ulong = 0xC020542F;
+    ioctl_readwrite_bad!(
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        tiocsrs485,
+        TIOCSRS485,
+        super::SerialRs485
+    );
     #[cfg(any(target_os = "ios", target_os = "macos"))]
     const IOSSIOSPEED: libc::c_ulong = 0x80045402;
     ioctl_write_ptr_bad!(
@@ -88,6 +174,84 @@ mod raw {
     );
 }
 
+/// The interrupt counters of a serial driver, as returned by `TIOCGICOUNT`
+///
+/// This mirrors `struct serial_icounter_struct` from `<linux/serial.h>`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerialIcounter {
+    pub cts: libc::c_int,
+    pub dsr: libc::c_int,
+    pub rng: libc::c_int,
+    pub dcd: libc::c_int,
+    pub rx: libc::c_int,
+    pub tx: libc::c_int,
+    pub frame: libc::c_int,
+    pub overrun: libc::c_int,
+    pub parity: libc::c_int,
+    pub brk: libc::c_int,
+    pub buf_overrun: libc::c_int,
+    reserved: [libc::c_int; 9],
+}
+
+/// The configuration of a serial driver, as used by `TIOCGSERIAL` and `TIOCSSERIAL`
+///
+/// This mirrors `struct serial_struct` from `<linux/serial.h>`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SerialStruct {
+    pub type_: libc::c_int,
+    pub line: libc::c_int,
+    pub port: libc::c_uint,
+    pub irq: libc::c_int,
+    pub flags: libc::c_int,
+    pub xmit_fifo_size: libc::c_int,
+    pub custom_divisor: libc::c_int,
+    pub baud_base: libc::c_int,
+    pub close_delay: libc::c_ushort,
+    pub io_type: libc::c_char,
+    pub reserved_char: [libc::c_char; 1],
+    pub hub6: libc::c_int,
+    pub closing_wait: libc::c_ushort,
+    pub closing_wait2: libc::c_ushort,
+    pub iomem_base: *mut libc::c_uchar,
+    pub iomem_reg_shift: libc::c_ushort,
+    pub port_high: libc::c_uint,
+    pub iomap_base: libc::c_ulong,
+}
+
+/// The port type of a serial core port without a UART behind it
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub const PORT_UNKNOWN: libc::c_int = 0;
+
+/// Asks the driver to minimize the latency of received data rather than its overhead
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub const ASYNC_LOW_LATENCY: libc::c_int = 1 << 13;
+
+/// The RS-485 settings of a serial driver, as used by `TIOCGRS485` and `TIOCSRS485`
+///
+/// This mirrors `struct serial_rs485` from `<linux/serial.h>`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerialRs485 {
+    pub flags: u32,
+    pub delay_rts_before_send: u32,
+    pub delay_rts_after_send: u32,
+    padding: [u32; 5],
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub const SER_RS485_ENABLED: u32 = 1 << 0;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub const SER_RS485_RTS_AFTER_SEND: u32 = 1 << 2;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub const SER_RS485_RX_DURING_TX: u32 = 1 << 4;
+
 bitflags! {
     /// Flags to indicate which wires in a serial connection to use
     pub struct SerialLines: libc::c_int {
@@ -114,6 +278,18 @@ pub fn tiocnxcl(fd: RawFd) -> Result<()> {
         .map_err(|e| e.into())
 }
 
+pub fn tiocsbrk(fd: RawFd) -> Result<()> {
+    unsafe { raw::tiocsbrk(fd) }
+        .map(|_| ())
+        .map_err(|e| e.into())
+}
+
+pub fn tioccbrk(fd: RawFd) -> Result<()> {
+    unsafe { raw::tioccbrk(fd) }
+        .map(|_| ())
+        .map_err(|e| e.into())
+}
+
 pub fn tiocmget(fd: RawFd) -> Result<SerialLines> {
     let mut status: libc::c_int = 0;
     let x = unsafe { raw::tiocmget(fd, &mut status) };
@@ -184,6 +360,44 @@ pub fn tcsets2(fd: RawFd, options: &libc::termios2) -> Result<()> {
         .map_err(|e| e.into())
 }
 
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn tiocgicount(fd: RawFd) -> Result<SerialIcounter> {
+    let mut counters = SerialIcounter::default();
+    unsafe { raw::tiocgicount(fd, &mut counters) }
+        .map(|_| counters)
+        .map_err(|e| e.into())
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn tiocgserial(fd: RawFd) -> Result<SerialStruct> {
+    let mut serial = std::mem::MaybeUninit::uninit();
+    match unsafe { raw::tiocgserial(fd, serial.as_mut_ptr()) } {
+        Ok(_) => unsafe { Ok(serial.assume_init()) },
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn tiocsserial(fd: RawFd, serial: &SerialStruct) -> Result<()> {
+    unsafe { raw::tiocsserial(fd, serial) }
+        .map(|_| ())
+        .map_err(|e| e.into())
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn tiocgrs485(fd: RawFd) -> Result<SerialRs485> {
+    let mut rs485 = SerialRs485::default();
+    unsafe { raw::tiocgrs485(fd, &mut rs485) }?;
+    Ok(rs485)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn tiocsrs485(fd: RawFd, rs485: &mut SerialRs485) -> Result<()> {
+    unsafe { raw::tiocsrs485(fd, rs485) }
+        .map(|_| ())
+        .map_err(|e| e.into())
+}
+
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 pub fn iossiospeed(fd: RawFd, baud_rate: &libc::speed_t) -> Result<()> {
     unsafe { raw::iossiospeed(fd, baud_rate) }
@@ -0,0 +1,180 @@
+//! UUCP-style lock files for serial devices
+//!
+//! A lock file named `LCK..<device>` holding the PID of its owner marks a device as in use. This
+//! is the protocol used by `minicom`, `cu`, and friends, so honoring it keeps those from
+//! interfering with ports opened by this crate and vice versa.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::{getpid, Pid};
+
+use crate::{Error, ErrorKind, Result};
+
+/// The directory lock files are created in by default
+pub const LOCK_DIR: &str = "/var/lock";
+
+/// A held lock on a serial device, removed when dropped
+#[derive(Debug)]
+pub struct LockFile {
+    path: PathBuf,
+}
+
+impl LockFile {
+    /// Locks `device` by creating its lock file in `dir`
+    ///
+    /// The lock file is created atomically, so only one process can succeed even if several try
+    /// at once. A lock left behind by a process that no longer exists is considered stale and is
+    /// replaced.
+    ///
+    /// ## Errors
+    ///
+    /// * `Busy` if the device is locked by a process that is still running.
+    /// * `InvalidInput` if `device` doesn't have a file name.
+    /// * `Io` if the lock file could not be created or read.
+    pub fn acquire(dir: &Path, device: &Path) -> Result<LockFile> {
+        let name = match device.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Device path has no file name",
+                ))
+            }
+        };
+        let path = dir.join(format!("LCK..{}", name));
+
+        // The lock is written to a temporary file first and then hard-linked into place. Linking
+        // fails if the lock file already exists, so it's created atomically and is never seen
+        // without the PID in it.
+        let temp = dir.join(format!("LTMP.{}.{}", getpid(), name));
+        // HDB UUCP format: the PID as 10 right-aligned ASCII digits and a newline
+        fs::write(&temp, format!("{:>10}\n", getpid()))?;
+        let result = link_lock(&temp, &path);
+        let _ = fs::remove_file(&temp);
+        result.map(|()| LockFile { path })
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Creates the lock file at `path` as a hard link to `temp`, replacing it if it's stale
+fn link_lock(temp: &Path, path: &Path) -> Result<()> {
+    // A stale lock is removed at most once so two processes can't keep stealing it from each
+    // other.
+    let mut removed_stale = false;
+    loop {
+        match fs::hard_link(temp, path) {
+            Ok(()) => return Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && !removed_stale => {
+                let owner = read_owner(path)?;
+                if let Some(pid) = owner {
+                    if is_running(pid) {
+                        return Err(Error::new(
+                            ErrorKind::Busy,
+                            format!("Device is locked by process {}", pid),
+                        ));
+                    }
+                }
+
+                // Make sure the lock wasn't replaced by another process since it was read
+                if read_owner(path)? == owner {
+                    match fs::remove_file(path) {
+                        Ok(()) => (),
+                        Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                removed_stale = true;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(Error::new(
+                    ErrorKind::Busy,
+                    "Device was locked by another process",
+                ));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Reads the PID of the owner of a lock file
+///
+/// Returns `None` if the file contents aren't a valid PID, which makes the lock stale. A lock file
+/// that has disappeared in the meantime is reported the same way.
+fn read_owner(path: &Path) -> Result<Option<Pid>> {
+    let mut contents = String::new();
+    match fs::File::open(path).and_then(|mut file| file.read_to_string(&mut contents)) {
+        Ok(_) => (),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    match contents.trim().parse() {
+        Ok(pid) if pid > 0 => Ok(Some(Pid::from_raw(pid))),
+        _ => Ok(None),
+    }
+}
+
+/// Checks whether the process `pid` exists
+fn is_running(pid: Pid) -> bool {
+    match kill(pid, None) {
+        Ok(()) => true,
+        // The process exists but belongs to another user
+        Err(nix::Error::Sys(Errno::EPERM)) => true,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+fn lock_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("serialport-lock-{}-{}", name, getpid()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_lock_exclusive() {
+    let dir = lock_dir("exclusive");
+    let device = Path::new("/dev/ttyS0");
+
+    let lock = LockFile::acquire(&dir, device).unwrap();
+    assert_eq!(&lock.path, dir.join("LCK..ttyS0").as_path());
+    let contents = fs::read_to_string(&lock.path).unwrap();
+    assert_eq!(contents, format!("{:>10}\n", getpid()));
+
+    let err = LockFile::acquire(&dir, device).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Busy);
+
+    drop(lock);
+    assert!(!dir.join("LCK..ttyS0").exists());
+    LockFile::acquire(&dir, device).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lock_stale() {
+    let dir = lock_dir("stale");
+    let device = Path::new("/dev/ttyS0");
+
+    // The PID of a process that has exited is a stale lock
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    fs::write(dir.join("LCK..ttyS0"), format!("{:>10}\n", pid)).unwrap();
+    let lock = LockFile::acquire(&dir, device).unwrap();
+    drop(lock);
+
+    // So is a lock file with garbage in it
+    fs::write(dir.join("LCK..ttyS0"), "garbage").unwrap();
+    LockFile::acquire(&dir, device).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
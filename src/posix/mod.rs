@@ -1,8 +1,31 @@
+pub use self::deadline::*;
 pub use self::enumerate::*;
+#[cfg(all(
+    feature = "hotplug",
+    target_os = "linux",
+    not(target_env = "musl"),
+    feature = "libudev"
+))]
+pub(crate) use self::hotplug::ChangeMonitor;
+pub use self::poll::poll;
+pub(crate) use self::poll::WakePipe;
+#[cfg(feature = "tokio")]
+pub use self::stream::SerialStream;
 pub use self::tty::*;
 
+mod deadline;
 mod enumerate;
 mod error;
+#[cfg(all(
+    feature = "hotplug",
+    target_os = "linux",
+    not(target_env = "musl"),
+    feature = "libudev"
+))]
+mod hotplug;
 mod ioctl;
+mod lock;
 mod poll;
+#[cfg(feature = "tokio")]
+mod stream;
 mod tty;
@@ -1,37 +1,169 @@
 #![allow(non_camel_case_types, dead_code)]
 
 use std::io;
-use std::os::unix::io::RawFd;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::time::Duration;
 
+use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
 use nix::poll::{PollFd, PollFlags};
 #[cfg(target_os = "linux")]
 use nix::sys::signal::SigSet;
 #[cfg(target_os = "linux")]
 use nix::sys::time::{TimeSpec, TimeValLike};
+use nix::unistd;
+
+use crate::posix::TTYPort;
+
+/// The reason a wait on a file descriptor completed
+#[derive(Debug, PartialEq, Eq)]
+pub enum Wakeup {
+    /// The file descriptor is ready for the requested operation
+    Ready,
+    /// The wait was interrupted through a `WakePipe`
+    Woken,
+}
+
+/// A self-pipe used to wake up a thread blocked waiting on a file descriptor
+#[derive(Debug)]
+pub struct WakePipe {
+    read_fd: RawFd,
+    write_fd: RawFd,
+}
+
+impl WakePipe {
+    pub fn new() -> crate::Result<WakePipe> {
+        // `pipe2()` isn't available everywhere, so set the flags separately
+        let (read_fd, write_fd) = unistd::pipe()?;
+        let pipe = WakePipe { read_fd, write_fd };
+        for &fd in &[read_fd, write_fd] {
+            fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+            fcntl(fd, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+        }
+        Ok(pipe)
+    }
+
+    /// Wakes up any thread waiting on this pipe
+    pub fn wake(&self) {
+        // If the pipe is full there's already a wakeup pending, so errors are safe to ignore
+        let _ = unistd::write(self.write_fd, &[0]);
+    }
+
+    /// Consumes all pending wakeups
+    pub fn clear(&self) {
+        let mut buf = [0u8; 16];
+        while let Ok(n) = unistd::read(self.read_fd, &mut buf) {
+            if n == 0 {
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for WakePipe {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.read_fd);
+        let _ = unistd::close(self.write_fd);
+    }
+}
+
+/// The longest wait given to `ppoll()`, about 68 years, which is as good as no timeout at all
+///
+/// These are the most seconds `TimeSpec` holds on 32-bit platforms.
+#[cfg(target_os = "linux")]
+const MAX_PPOLL_MILLISECONDS: u64 = 0x7fff_ffff * 1000;
+
+/// Converts `timeout` to whole milliseconds, saturating instead of overflowing
+fn milliseconds(timeout: Duration) -> u64 {
+    timeout
+        .as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(timeout.subsec_millis()))
+}
+
+/// Converts `timeout` to the argument of `ppoll()`
+#[cfg(target_os = "linux")]
+fn timespec(timeout: Duration) -> TimeSpec {
+    TimeSpec::milliseconds(milliseconds(timeout).min(MAX_PPOLL_MILLISECONDS) as i64)
+}
+
+/// Converts `timeout` to the argument of `poll()`, waiting forever if it doesn't fit
+#[cfg(not(target_os = "linux"))]
+fn poll_timeout(timeout: Duration) -> nix::libc::c_int {
+    std::convert::TryFrom::try_from(milliseconds(timeout)).unwrap_or(-1)
+}
+
+/// Waits until at least one of `ports` has data to read
+///
+/// Returns the indices into `ports` of all ports that can be read from without blocking, in
+/// ascending order, or an empty list if `timeout` elapsed first. A port that was disconnected or
+/// failed is included too, so the error is reported by the next read. This allows a single thread
+/// to serve many ports, using `poll()` on all of them at once.
+///
+/// ## Errors
+///
+/// * `InvalidInput` if `ports` is empty.
+/// * `Io` for any other type of I/O error, including `Interrupted` for signals.
+pub fn poll(ports: &[&TTYPort], timeout: Duration) -> crate::Result<Vec<usize>> {
+    if ports.is_empty() {
+        return Err(crate::Error::new(
+            crate::ErrorKind::InvalidInput,
+            "No ports to wait for",
+        ));
+    }
+    let mut fds: Vec<PollFd> = ports
+        .iter()
+        .map(|port| PollFd::new(port.as_raw_fd(), PollFlags::POLLIN))
+        .collect();
+
+    #[cfg(target_os = "linux")]
+    nix::poll::ppoll(fds.as_mut_slice(), timespec(timeout), SigSet::empty())?;
+    #[cfg(not(target_os = "linux"))]
+    nix::poll::poll(fds.as_mut_slice(), poll_timeout(timeout))?;
+
+    Ok(fds
+        .iter()
+        .enumerate()
+        .filter(|&(_, fd)| match fd.revents() {
+            Some(events) => !events.is_empty(),
+            None => false,
+        })
+        .map(|(i, _)| i)
+        .collect())
+}
 
 pub fn wait_read_fd(fd: RawFd, timeout: Duration) -> io::Result<()> {
-    wait_fd(fd, PollFlags::POLLIN, timeout)
+    wait_fd(fd, PollFlags::POLLIN, None, timeout).map(|_| ())
 }
 
 pub fn wait_write_fd(fd: RawFd, timeout: Duration) -> io::Result<()> {
-    wait_fd(fd, PollFlags::POLLOUT, timeout)
+    wait_fd(fd, PollFlags::POLLOUT, None, timeout).map(|_| ())
+}
+
+pub fn wait_read_fd_or_wake(fd: RawFd, wake: &WakePipe, timeout: Duration) -> io::Result<Wakeup> {
+    wait_fd(fd, PollFlags::POLLIN, Some(wake.read_fd), timeout)
+}
+
+pub fn wait_write_fd_or_wake(fd: RawFd, wake: &WakePipe, timeout: Duration) -> io::Result<Wakeup> {
+    wait_fd(fd, PollFlags::POLLOUT, Some(wake.read_fd), timeout)
 }
 
-fn wait_fd(fd: RawFd, events: PollFlags, timeout: Duration) -> io::Result<()> {
+fn wait_fd(
+    fd: RawFd,
+    events: PollFlags,
+    wake_fd: Option<RawFd>,
+    timeout: Duration,
+) -> io::Result<Wakeup> {
     use nix::errno::Errno::{EIO, EPIPE};
 
     let mut fds = vec![PollFd::new(fd, events)];
+    if let Some(wake_fd) = wake_fd {
+        fds.push(PollFd::new(wake_fd, PollFlags::POLLIN));
+    }
 
-    let milliseconds =
-        timeout.as_secs() as i64 * 1000 + i64::from(timeout.subsec_nanos()) / 1_000_000;
     #[cfg(target_os = "linux")]
-    let wait_res = {
-        let timespec = TimeSpec::milliseconds(milliseconds);
-        nix::poll::ppoll(fds.as_mut_slice(), timespec, SigSet::empty())
-    };
+    let wait_res = nix::poll::ppoll(fds.as_mut_slice(), timespec(timeout), SigSet::empty());
     #[cfg(not(target_os = "linux"))]
-    let wait_res = nix::poll::poll(fds.as_mut_slice(), milliseconds as nix::libc::c_int);
+    let wait_res = nix::poll::poll(fds.as_mut_slice(), poll_timeout(timeout));
 
     let wait = match wait_res {
         Ok(r) => r,
@@ -39,16 +171,23 @@ fn wait_fd(fd: RawFd, events: PollFlags, timeout: Duration) -> io::Result<()> {
     };
     // All errors generated by poll or ppoll are already caught by the nix wrapper around libc, so
     // here we only need to check if there's at least 1 event
-    if wait != 1 {
+    if wait == 0 {
         return Err(io::Error::new(
             io::ErrorKind::TimedOut,
             "Operation timed out",
         ));
     }
 
+    // A wakeup takes priority over the port so callers always get a chance to react to it
+    if let Some(Some(e)) = fds.get(1).map(|wake| wake.revents()) {
+        if e.contains(PollFlags::POLLIN) {
+            return Ok(Wakeup::Woken);
+        }
+    }
+
     // Check the result of ppoll() by looking at the revents field
     match fds[0].revents() {
-        Some(e) if e == events => return Ok(()),
+        Some(e) if e == events => return Ok(Wakeup::Ready),
         // If there was a hangout or invalid request
         Some(e) if e.contains(PollFlags::POLLHUP) || e.contains(PollFlags::POLLNVAL) => {
             return Err(io::Error::new(io::ErrorKind::BrokenPipe, EPIPE.desc()));
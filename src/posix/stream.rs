@@ -0,0 +1,110 @@
+//! Asynchronous I/O on TTY ports with Tokio
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio_crate::io::{AsyncRead, AsyncWrite, PollEvented};
+
+use crate::posix::TTYPort;
+use crate::{Result, SerialPort, SerialPortBuilder};
+
+/// A TTY port registered with the Tokio reactor
+///
+/// This implements Tokio's `AsyncRead` and `AsyncWrite` and is available with the `tokio` feature.
+/// The port is switched to non-blocking mode while it's registered, so the port's timeout doesn't
+/// apply to reads and writes made through this type. Settings can still be changed through
+/// `get_mut()`, which doesn't block as `TTYPort` applies them right away with `TCSANOW`.
+///
+/// A `SerialStream` has to be created from within a Tokio runtime that has I/O enabled.
+#[derive(Debug)]
+pub struct SerialStream {
+    io: PollEvented<TTYPort>,
+}
+
+impl SerialStream {
+    /// Opens the port described by `builder` and registers it with the current Tokio reactor
+    ///
+    /// ## Errors
+    ///
+    /// * Any error that `TTYPort::open()` can return.
+    /// * `Io` if the port could not be registered, e.g. because there's no runtime.
+    pub fn open(builder: &SerialPortBuilder) -> Result<SerialStream> {
+        SerialStream::from_port(TTYPort::open(builder)?)
+    }
+
+    /// Registers an already open port with the current Tokio reactor
+    ///
+    /// ## Errors
+    ///
+    /// * `Io` if the port could not be registered, e.g. because there's no runtime.
+    pub fn from_port(port: TTYPort) -> Result<SerialStream> {
+        Ok(SerialStream {
+            io: PollEvented::new(port)?,
+        })
+    }
+
+    /// Returns a reference to the underlying port
+    pub fn get_ref(&self) -> &TTYPort {
+        self.io.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying port
+    ///
+    /// Reads and writes made directly on the port fail with `WouldBlock` instead of waiting.
+    pub fn get_mut(&mut self) -> &mut TTYPort {
+        self.io.get_mut()
+    }
+
+    /// Deregisters the port from the reactor and returns it in blocking mode again
+    ///
+    /// ## Errors
+    ///
+    /// * `Io` if the port could not be deregistered.
+    pub fn into_inner(self) -> Result<TTYPort> {
+        Ok(self.io.into_inner()?)
+    }
+
+    /// Sets the baud rate, see `SerialPort::set_baud_rate()`
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        self.get_mut().set_baud_rate(baud_rate)
+    }
+
+    /// Sets the state of the RTS line, see `SerialPort::write_request_to_send()`
+    pub fn write_request_to_send(&mut self, level: bool) -> Result<()> {
+        self.get_mut().write_request_to_send(level)
+    }
+
+    /// Sets the state of the DTR line, see `SerialPort::write_data_terminal_ready()`
+    pub fn write_data_terminal_ready(&mut self, level: bool) -> Result<()> {
+        self.get_mut().write_data_terminal_ready(level)
+    }
+}
+
+impl AsyncRead for SerialStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SerialStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
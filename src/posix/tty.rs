@@ -1,18 +1,29 @@
 use std::mem::MaybeUninit;
 use std::os::unix::prelude::*;
 use std::path::Path;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use std::{io, mem};
 
 use nix::fcntl::fcntl;
 use nix::{self, libc, unistd};
 
+use crate::cancel::SharedToken;
 use crate::posix::ioctl::{self, SerialLines};
+use crate::posix::lock::{self, LockFile};
+use crate::posix::poll::{self, WakePipe, Wakeup};
+use crate::posix::Deadline;
+use crate::watchdog::RxWatchdog;
 use crate::{
-    ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity, Result, SerialPort,
-    SerialPortBuilder, StopBits,
+    AccessMode, CancellationToken, ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity,
+    PortErrors, PortStats, ReadHalf, Result, SerialPort, SerialPortBuilder, SerialPortSettings,
+    SignalMask, SignalState, StopBits, WriteHalf,
 };
 
+/// How often `TTYPort::wait_for_signal_change()` checks the driver's counters for line changes
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Convenience method for removing exclusive access from
 /// a fd and closing it.
 fn close(fd: RawFd) {
@@ -50,9 +61,127 @@ pub struct TTYPort {
     fd: RawFd,
     timeout: Duration,
     exclusive: bool,
+    access_mode: AccessMode,
     port_name: Option<String>,
     #[cfg(any(target_os = "ios", target_os = "macos"))]
     baud_rate: u32,
+    stats: PortStats,
+    /// The most bytes a single `read()` asks the kernel for, or 0 for no limit
+    max_read_chunk: usize,
+    inter_byte_write_delay: Duration,
+    inter_byte_timeout: Option<Duration>,
+    /// Whether this port has asserted a break that still needs to be cleared
+    break_set: AtomicBool,
+    /// Whether reads and writes return `WouldBlock` rather than wait, as set by
+    /// `set_nonblocking()` or registering with a `mio::Poll`. Shared with all clones.
+    nonblocking: Arc<AtomicBool>,
+    /// How received bytes with parity errors are handled while parity is enabled
+    parity_check: ParityCheck,
+    rx_watchdog: Option<RxWatchdog>,
+    /// The modem line transition counters as of the last `modem_line_changes_since()` call
+    modem_counts: Option<ModemSignals>,
+    /// The receive error counters as of opening the port or the last `take_errors()` call
+    error_counts: Option<PortErrors>,
+    null_modem: Option<NullModem>,
+    lock: Option<Arc<LockFile>>,
+    /// Cancels blocking reads and writes once `SerialPort::cancellation_token()` was called.
+    /// Shared with all clones.
+    cancel: SharedToken,
+}
+
+/// The modem control lines of one end of an emulated null-modem cable
+#[derive(Clone, Debug)]
+struct NullModem {
+    /// The lines driven by each end of the cable
+    driven: Arc<Mutex<[SerialLines; 2]>>,
+    /// Notified whenever either end changes the lines it drives
+    changed: Arc<Condvar>,
+    /// Which end of the cable this is
+    end: usize,
+}
+
+impl NullModem {
+    fn driven(&self) -> MutexGuard<'_, [SerialLines; 2]> {
+        self.driven
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Drives an output line of this end
+    fn set(&self, pin: SerialLines, level: bool) {
+        let mut driven = self.driven();
+        if level {
+            driven[self.end].insert(pin);
+        } else {
+            driven[self.end].remove(pin);
+        }
+        self.changed.notify_all();
+    }
+
+    /// Returns the state of all lines as seen by this end
+    fn lines(&self) -> SerialLines {
+        self.lines_of(&self.driven())
+    }
+
+    /// Blocks until any of the `mask` lines as seen by this end changes, or `timeout` elapses
+    ///
+    /// Returns `false` if the wait timed out.
+    fn wait_for_change(&self, mask: SerialLines, timeout: Duration) -> bool {
+        let start = Instant::now();
+        let mut driven = self.driven();
+        let before = self.lines_of(&driven) & mask;
+        while self.lines_of(&driven) & mask == before {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return false;
+            }
+            driven = match self.changed.wait_timeout(driven, timeout - elapsed) {
+                Ok((driven, _)) => driven,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+        true
+    }
+
+    fn lines_of(&self, driven: &[SerialLines; 2]) -> SerialLines {
+        let remote = driven[1 - self.end];
+
+        let mut lines =
+            driven[self.end] & (SerialLines::REQUEST_TO_SEND | SerialLines::DATA_TERMINAL_READY);
+        if remote.contains(SerialLines::REQUEST_TO_SEND) {
+            lines |= SerialLines::CLEAR_TO_SEND;
+        }
+        if remote.contains(SerialLines::DATA_TERMINAL_READY) {
+            lines |= SerialLines::DATA_SET_READY | SerialLines::DATA_CARRIER_DETECT;
+        }
+        lines
+    }
+}
+
+/// How received bytes with parity errors are handled
+///
+/// Set with `TTYPort::set_parity_check()`. This only has an effect while parity is enabled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParityCheck {
+    /// Bytes with parity errors are passed on without being marked.
+    ///
+    /// The driver replaces each of them with a 0 byte. This is the default.
+    Ignore,
+    /// Bytes with parity errors are discarded (`IGNPAR`).
+    Drop,
+    /// Bytes with parity errors are marked in the received data (`PARMRK`).
+    ///
+    /// Each of them is delivered as the three bytes `0xFF 0x00 <byte>`. To keep that unambiguous,
+    /// a valid `0xFF` byte is delivered as the two bytes `0xFF 0xFF`, so data read in this mode
+    /// has to be unescaped.
+    Mark,
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for ParityCheck {
+    fn default() -> Self {
+        ParityCheck::Ignore
+    }
 }
 
 /// Specifies the duration of a transmission break
@@ -64,6 +193,135 @@ pub enum BreakDuration {
     Arbitrary(std::num::NonZeroI32),
 }
 
+impl SignalMask {
+    fn lines(self) -> SerialLines {
+        let mut lines = SerialLines::empty();
+        lines.set(
+            SerialLines::CLEAR_TO_SEND,
+            self.contains(SignalMask::CLEAR_TO_SEND),
+        );
+        lines.set(
+            SerialLines::DATA_SET_READY,
+            self.contains(SignalMask::DATA_SET_READY),
+        );
+        lines.set(SerialLines::RING, self.contains(SignalMask::RING_INDICATOR));
+        lines.set(
+            SerialLines::DATA_CARRIER_DETECT,
+            self.contains(SignalMask::CARRIER_DETECT),
+        );
+        lines
+    }
+}
+
+/// Numbers of transitions of the modem status lines
+///
+/// Returned by `TTYPort::modem_line_changes_since()`. Each count includes both rising and falling
+/// edges, so an odd count means the line's level is now the opposite of what it was before.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ModemSignals {
+    /// Transitions of CTS (Clear To Send)
+    pub clear_to_send: u32,
+    /// Transitions of DSR (Data Set Ready)
+    pub data_set_ready: u32,
+    /// Transitions of RI (Ring Indicator)
+    pub ring_indicator: u32,
+    /// Transitions of CD (Carrier Detect)
+    pub carrier_detect: u32,
+}
+
+impl ModemSignals {
+    /// Returns the transitions counted in `self` that happened after those in `earlier`
+    fn since(&self, earlier: &ModemSignals) -> ModemSignals {
+        // The kernel's counters are allowed to wrap around
+        ModemSignals {
+            clear_to_send: self.clear_to_send.wrapping_sub(earlier.clear_to_send),
+            data_set_ready: self.data_set_ready.wrapping_sub(earlier.data_set_ready),
+            ring_indicator: self.ring_indicator.wrapping_sub(earlier.ring_indicator),
+            carrier_detect: self.carrier_detect.wrapping_sub(earlier.carrier_detect),
+        }
+    }
+
+    /// Returns `true` if any of the lines in `mask` changed
+    fn any_of(&self, mask: SignalMask) -> bool {
+        (mask.contains(SignalMask::CLEAR_TO_SEND) && self.clear_to_send > 0)
+            || (mask.contains(SignalMask::DATA_SET_READY) && self.data_set_ready > 0)
+            || (mask.contains(SignalMask::RING_INDICATOR) && self.ring_indicator > 0)
+            || (mask.contains(SignalMask::CARRIER_DETECT) && self.carrier_detect > 0)
+    }
+}
+
+/// Settings for the RS-485 half-duplex mode of the Linux serial drivers
+///
+/// In RS-485 mode the driver drives RTS to enable the transceiver while transmitting, which is set
+/// with `TTYPort::set_rs485_mode()`. The delays have a resolution of milliseconds and are rounded
+/// down. The `Default` enables the transmitter with RTS asserted while sending, without any delays.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rs485Config {
+    /// Whether RTS is asserted while sending
+    pub rts_on_send: bool,
+    /// Whether RTS is asserted after sending
+    pub rts_after_send: bool,
+    /// Whether to keep receiving while sending, which shows the transmitted data as an echo
+    pub rx_during_tx: bool,
+    /// How long to wait after changing RTS before sending
+    pub delay_before_send: Duration,
+    /// How long to wait after sending before changing RTS back
+    pub delay_after_send: Duration,
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+impl Default for Rs485Config {
+    fn default() -> Self {
+        Rs485Config {
+            rts_on_send: true,
+            rts_after_send: false,
+            rx_during_tx: false,
+            delay_before_send: Duration::from_secs(0),
+            delay_after_send: Duration::from_secs(0),
+        }
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+impl Rs485Config {
+    fn to_raw(self) -> ioctl::SerialRs485 {
+        // Saturate delays too long for the driver rather than wrapping them around
+        fn millis(delay: Duration) -> u32 {
+            std::convert::TryFrom::try_from(delay.as_millis()).unwrap_or(!0)
+        }
+
+        let mut raw = ioctl::SerialRs485::default();
+        raw.flags = ioctl::SER_RS485_ENABLED;
+        if self.rts_on_send {
+            raw.flags |= ioctl::SER_RS485_RTS_ON_SEND;
+        }
+        if self.rts_after_send {
+            raw.flags |= ioctl::SER_RS485_RTS_AFTER_SEND;
+        }
+        if self.rx_during_tx {
+            raw.flags |= ioctl::SER_RS485_RX_DURING_TX;
+        }
+        raw.delay_rts_before_send = millis(self.delay_before_send);
+        raw.delay_rts_after_send = millis(self.delay_after_send);
+        raw
+    }
+
+    /// Returns the settings in `raw`, or `None` if RS-485 mode isn't enabled
+    fn from_raw(raw: ioctl::SerialRs485) -> Option<Self> {
+        if raw.flags & ioctl::SER_RS485_ENABLED == 0 {
+            return None;
+        }
+        Some(Rs485Config {
+            rts_on_send: raw.flags & ioctl::SER_RS485_RTS_ON_SEND != 0,
+            rts_after_send: raw.flags & ioctl::SER_RS485_RTS_AFTER_SEND != 0,
+            rx_during_tx: raw.flags & ioctl::SER_RS485_RX_DURING_TX != 0,
+            delay_before_send: Duration::from_millis(raw.delay_rts_before_send.into()),
+            delay_after_send: Duration::from_millis(raw.delay_rts_after_send.into()),
+        })
+    }
+}
+
 impl TTYPort {
     /// Opens a TTY device as a serial port.
     ///
@@ -72,21 +330,30 @@ impl TTYPort {
     /// Ports are opened in exclusive mode by default. If this is undesireable
     /// behavior, use `TTYPort::set_exclusive(false)`.
     ///
+    /// Ports are always opened with `O_NOCTTY`, so they never become the controlling terminal of
+    /// the process. Otherwise a session leader without a controlling terminal would acquire the
+    /// port as one, and characters like `Ctrl-C` on the line could deliver signals to it.
+    ///
     /// ## Errors
     ///
-    /// * `NoDevice` if the device could not be opened. This could indicate that
-    ///    the device is already in use.
+    /// * `Busy` if another port holds the device in exclusive mode.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `NoDevice` if the device could not be opened.
     /// * `InvalidInput` if `path` is not a valid device name.
     /// * `Io` for any other error while opening or initializing the device.
     pub fn open(builder: &SerialPortBuilder) -> Result<TTYPort> {
-        use nix::fcntl::FcntlArg::F_SETFL;
         use nix::fcntl::OFlag;
         use nix::libc::{cfmakeraw, tcflush, tcgetattr, tcsetattr};
 
         let path = Path::new(&builder.path);
+        let access = match builder.access_mode {
+            AccessMode::ReadOnly => OFlag::O_RDONLY,
+            AccessMode::WriteOnly => OFlag::O_WRONLY,
+            AccessMode::ReadWrite => OFlag::O_RDWR,
+        };
         let fd = nix::fcntl::open(
             path,
-            OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_NONBLOCK,
+            access | OFlag::O_NOCTTY | OFlag::O_NONBLOCK,
             nix::sys::stat::Mode::empty(),
         )?;
 
@@ -134,9 +401,7 @@ impl TTYPort {
             // get exclusive access to device
             ioctl::tiocexcl(fd)?;
 
-            // clear O_NONBLOCK flag
-            fcntl(fd, F_SETFL(nix::fcntl::OFlag::empty()))?;
-
+            // `O_NONBLOCK` stays set, see `set_o_nonblock()`
             Ok(())
         }
         .map_err(|e: Error| {
@@ -144,15 +409,62 @@ impl TTYPort {
             e
         })?;
 
-        let port = TTYPort {
+        let mut port = TTYPort {
             fd,
-            timeout: builder.timeout, // This is overwritten by the subsequent call to `set_all()`
-            exclusive: true,          // This is guaranteed by the above `ioctl::tiocexcl()` call
+            timeout: builder.timeout,
+            exclusive: true, // This is guaranteed by the above `ioctl::tiocexcl()` call
+            access_mode: builder.access_mode,
             port_name: Some(builder.path.clone()),
             #[cfg(any(target_os = "ios", target_os = "macos"))]
             baud_rate: builder.baud_rate,
+            stats: PortStats::default(),
+            max_read_chunk: 0,
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            break_set: AtomicBool::new(false),
+            nonblocking: Arc::new(AtomicBool::new(false)),
+            parity_check: ParityCheck::Ignore,
+            rx_watchdog: None,
+            modem_counts: None,
+            error_counts: None,
+            null_modem: None,
+            lock: None,
+            cancel: SharedToken::default(),
         };
 
+        // If any setting fails the port is dropped here, which closes the file descriptor
+        if let Some(dtr) = builder.dtr_on_open {
+            port.write_data_terminal_ready(dtr)?;
+        }
+        if let Some(rts) = builder.rts_on_open {
+            port.write_request_to_send(rts)?;
+        }
+        // Apply everything with a single `tcsetattr()` so a failure can't leave the port with only
+        // some of the settings
+        port.set_all(&builder.settings())?;
+        // Count receive errors from here on, if the driver counts them at all
+        port.error_counts = port.error_counts().ok();
+
+        Ok(port)
+    }
+
+    /// Opens a TTY device as a serial port after locking it with a UUCP lock file
+    ///
+    /// This is the same as `open()` but first creates the lock file `/var/lock/LCK..<device>`
+    /// holding the PID of this process, as done by `minicom`, `cu`, and other serial programs. The
+    /// lock file is created atomically, and a lock left behind by a process that no longer exists
+    /// is replaced. It is removed once the port and all of its clones are dropped, or turned into
+    /// raw file descriptors with `into_raw_fd()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `Busy` if the device is locked by another running process.
+    /// * `Io` if the lock file could not be created.
+    /// * Any error that `open()` can return.
+    pub fn open_locked(builder: &SerialPortBuilder) -> Result<TTYPort> {
+        let lock = LockFile::acquire(Path::new(lock::LOCK_DIR), Path::new(&builder.path))?;
+        let mut port = TTYPort::open(builder)?;
+        port.lock = Some(Arc::new(lock));
         Ok(port)
     }
 
@@ -164,12 +476,20 @@ impl TTYPort {
         self.exclusive
     }
 
+    /// Returns the directions the port was opened for
+    pub fn access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
     /// Sets the exclusivity of the port
     ///
     /// If a port is exclusive, then trying to open the same device path again
     /// will fail.
     ///
-    /// See the man pages for the tiocexcl and tiocnxcl ioctl's for more details.
+    /// See the man pages for the tiocexcl and tiocnxcl ioctl's for more details. On Linux,
+    /// processes with `CAP_SYS_ADMIN`, which includes those running as root, can open an exclusive
+    /// port anyway. `open_locked()` additionally honors the UUCP lock files used by other serial
+    /// programs.
     ///
     /// ## Errors
     ///
@@ -190,6 +510,11 @@ impl TTYPort {
     }
 
     fn set_pin(&mut self, pin: ioctl::SerialLines, level: bool) -> Result<()> {
+        if let Some(ref null_modem) = self.null_modem {
+            null_modem.set(pin, level);
+            return Ok(());
+        }
+
         let retval = if level {
             ioctl::tiocmbis(self.fd, pin)
         } else {
@@ -203,10 +528,14 @@ impl TTYPort {
     }
 
     fn read_pin(&mut self, pin: ioctl::SerialLines) -> Result<bool> {
-        match ioctl::tiocmget(self.fd) {
-            Ok(pins) => Ok(pins.contains(pin)),
-            Err(err) => Err(err),
+        self.read_lines().map(|lines| lines.contains(pin))
+    }
+
+    fn read_lines(&self) -> Result<ioctl::SerialLines> {
+        if let Some(ref null_modem) = self.null_modem {
+            return Ok(null_modem.lines());
         }
+        ioctl::tiocmget(self.fd)
     }
 
     /// Create a pair of pseudo serial terminals
@@ -230,7 +559,8 @@ impl TTYPort {
     /// ```
     pub fn pair() -> Result<(Self, Self)> {
         // Open the next free pty.
-        let next_pty_fd = nix::pty::posix_openpt(nix::fcntl::OFlag::O_RDWR)?;
+        let next_pty_fd =
+            nix::pty::posix_openpt(nix::fcntl::OFlag::O_RDWR | nix::fcntl::OFlag::O_NOCTTY)?;
 
         // Grant access to the associated slave pty
         nix::pty::grantpt(&next_pty_fd)?;
@@ -263,20 +593,79 @@ impl TTYPort {
         // Manually construct the master port here because the
         // `tcgetattr()` doesn't work on Mac, Solaris, and maybe other
         // BSDs when used on the master port.
+        set_o_nonblock(next_pty_fd.as_raw_fd())?;
         let master_tty = TTYPort {
             fd: next_pty_fd.into_raw_fd(),
             timeout: Duration::from_millis(100),
             exclusive: true,
+            access_mode: AccessMode::ReadWrite,
             port_name: None,
             #[cfg(any(target_os = "ios", target_os = "macos"))]
             baud_rate,
+            stats: PortStats::default(),
+            max_read_chunk: 0,
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            break_set: AtomicBool::new(false),
+            nonblocking: Arc::new(AtomicBool::new(false)),
+            parity_check: ParityCheck::Ignore,
+            rx_watchdog: None,
+            modem_counts: None,
+            error_counts: None,
+            null_modem: None,
+            lock: None,
+            cancel: SharedToken::default(),
         };
 
         Ok((master_tty, slave_tty))
     }
 
+    /// Create a pair of pseudo serial terminals connected by an emulated null-modem cable
+    ///
+    /// This is the same as `pair()` except that the modem control lines are emulated, as pseudo
+    /// terminals don't have any. RTS on one port drives CTS on the other and DTR drives both DSR and
+    /// DCD, so `read_clear_to_send()` on one port reflects `write_request_to_send()` on the other.
+    /// RI is never asserted. All lines start out deasserted.
+    ///
+    /// Ports cloned from either of these share the same emulated lines.
+    ///
+    /// ## Returns
+    /// Two connected `TTYPort` objects: `(master, slave)`
+    ///
+    /// ## Errors
+    /// The same as `pair()`.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use serialport::{SerialPort, TTYPort};
+    ///
+    /// let (mut master, mut slave) = TTYPort::null_modem_pair().unwrap();
+    /// master.write_request_to_send(true).unwrap();
+    /// assert!(slave.read_clear_to_send().unwrap());
+    /// ```
+    pub fn null_modem_pair() -> Result<(Self, Self)> {
+        let (mut master, mut slave) = TTYPort::pair()?;
+        let driven = Arc::new(Mutex::new([SerialLines::empty(); 2]));
+        let changed = Arc::new(Condvar::new());
+        master.null_modem = Some(NullModem {
+            driven: driven.clone(),
+            changed: changed.clone(),
+            end: 0,
+        });
+        slave.null_modem = Some(NullModem {
+            driven,
+            changed,
+            end: 1,
+        });
+        Ok((master, slave))
+    }
+
     /// Sends 0-valued bits over the port for a set duration
-    pub fn send_break(&self, duration: BreakDuration) -> Result<()> {
+    ///
+    /// Unlike `SerialPort::send_break()`, this leaves timing the break to the driver, so it
+    /// doesn't block the thread for longer than the break lasts.
+    pub fn send_break_for(&self, duration: BreakDuration) -> Result<()> {
         match duration {
             BreakDuration::Short => nix::sys::termios::tcsendbreak(self.fd, 0),
             BreakDuration::Arbitrary(n) => nix::sys::termios::tcsendbreak(self.fd, n.get()),
@@ -284,6 +673,354 @@ impl TTYPort {
         .map_err(|e| e.into())
     }
 
+    /// Starts transmitting a break
+    ///
+    /// The line is held in the break state until `clear_break()` is called. A break that is still
+    /// asserted when the port is dropped is cleared then, so an early return or a panic can't
+    /// leave the line stuck. Each clone of a port keeps track of the breaks it set itself.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while setting the break.
+    pub fn set_break(&self) -> Result<()> {
+        ioctl::tiocsbrk(self.fd)?;
+        self.break_set.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stops transmitting a break
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while clearing the break.
+    pub fn clear_break(&self) -> Result<()> {
+        ioctl::tioccbrk(self.fd)?;
+        self.break_set.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns the RS-485 half-duplex settings, or `None` if RS-485 mode is disabled
+    ///
+    /// The driver may have adjusted the settings passed to `set_rs485_mode()`, for example to
+    /// clamp the delays or because the hardware can't use RTS in both directions, so this reports
+    /// what's actually in effect.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the driver doesn't support RS-485 mode.
+    /// * `Io` for any other error while reading the settings.
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    pub fn rs485_mode(&self) -> Result<Option<Rs485Config>> {
+        ioctl::tiocgrs485(self.fd).map(Rs485Config::from_raw)
+    }
+
+    /// Enables RS-485 half-duplex mode with `config`, or disables it for `None`
+    ///
+    /// This is only available on Linux and Android, and only for drivers and hardware that support
+    /// RS-485.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Unknown` if the driver doesn't support RS-485 mode.
+    /// * `Io` for any other error while applying the settings.
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    pub fn set_rs485_mode(&mut self, config: Option<Rs485Config>) -> Result<()> {
+        let mut rs485 = match config {
+            Some(config) => config.to_raw(),
+            None => ioctl::SerialRs485::default(),
+        };
+        ioctl::tiocsrs485(self.fd, &mut rs485)
+    }
+
+    /// Returns how received bytes with parity errors are handled
+    pub fn parity_check(&self) -> ParityCheck {
+        self.parity_check
+    }
+
+    /// Sets how received bytes with parity errors are handled
+    ///
+    /// By default they are replaced by 0 bytes, which can't be told apart from real 0 bytes.
+    /// `ParityCheck::Mark` reports them in the received data instead, see its documentation for
+    /// the format. The setting is kept when the parity is changed, but only has an effect while
+    /// parity is enabled.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_parity_check(&mut self, check: ParityCheck) -> Result<()> {
+        let mut termios = self.get_termios()?;
+        let parity = self.parity()?;
+        set_parity_flags(&mut termios, parity, check)?;
+        self.set_termios(&termios)?;
+        self.parity_check = check;
+        Ok(())
+    }
+
+    /// Enables or disables the driver's low latency mode
+    ///
+    /// USB serial adapters such as those made by FTDI hold on to received data for a while, up to
+    /// 16ms by default, to pass it on in fewer, larger transfers. Low latency mode hands data over
+    /// right away at the cost of more overhead, which speeds up request/response protocols.
+    ///
+    /// This sets `ASYNC_LOW_LATENCY` through `TIOCSSERIAL`, so it's only available on Linux and
+    /// Android, and only for drivers that support it, which pseudo-terminals don't.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` if the platform or driver doesn't support low latency mode.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    pub fn set_low_latency(&mut self, enabled: bool) -> Result<()> {
+        let mut serial = ioctl::tiocgserial(self.fd).map_err(|e| match e.kind() {
+            ErrorKind::InvalidInput | ErrorKind::Unknown => Error::new(
+                ErrorKind::Unknown,
+                format!("Low latency mode is not supported by the driver: {}", e),
+            ),
+            _ => e,
+        })?;
+        if enabled {
+            serial.flags |= ioctl::ASYNC_LOW_LATENCY;
+        } else {
+            serial.flags &= !ioctl::ASYNC_LOW_LATENCY;
+        }
+        ioctl::tiocsserial(self.fd, &serial)
+    }
+
+    /// Enables or disables the driver's low latency mode
+    ///
+    /// Low latency mode is only available on Linux and Android, so this always fails.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` as the platform doesn't support low latency mode.
+    #[cfg(not(any(target_os = "android", target_os = "linux")))]
+    pub fn set_low_latency(&mut self, _enabled: bool) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Low latency mode is not supported on this platform",
+        ))
+    }
+
+    /// Returns how often each modem status line changed since the last call
+    ///
+    /// Unlike reading the lines' levels, this catches pulses that are over before the next poll.
+    /// The first call only records the current counts and reports no changes.
+    ///
+    /// This uses the driver's interrupt counters (`TIOCGICOUNT`), so it's only available on Linux
+    /// and Android, and only for drivers that keep those counters. Most UART and USB serial drivers
+    /// do, but pseudo-terminals don't.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` if the platform or driver doesn't count modem line changes.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn modem_line_changes_since(&mut self) -> Result<ModemSignals> {
+        let counts = self.modem_counts()?;
+        let changes = match self.modem_counts {
+            Some(ref previous) => counts.since(previous),
+            None => ModemSignals::default(),
+        };
+        self.modem_counts = Some(counts);
+        Ok(changes)
+    }
+
+    /// Blocks until one of the modem status lines in `mask` changes, then returns all their levels
+    ///
+    /// This saves callers from polling the lines to catch an incoming call on RI or a carrier loss
+    /// on CD. The wait ends with a `TimedOut` error once `timeout` elapses, or with another error if
+    /// the device is disconnected, but not when a clone of the port is closed.
+    ///
+    /// `TIOCMIWAIT` can't time out, so this checks the driver's interrupt counters (`TIOCGICOUNT`)
+    /// every few milliseconds instead. Like the kernel's own wait, that catches pulses shorter than
+    /// the interval. It's only available on Linux and Android, and only for drivers that keep those
+    /// counters. Most UART and USB serial drivers do, but pseudo-terminals don't, apart from the
+    /// lines emulated for `TTYPort::null_modem_pair()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if `mask` is empty.
+    /// * `Unknown` if the platform or driver doesn't count modem line changes.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `TimedOut` if none of the lines changed within `timeout`.
+    /// * `Io` for any other type of I/O error.
+    pub fn wait_for_signal_change(
+        &self,
+        mask: SignalMask,
+        timeout: Duration,
+    ) -> Result<SignalState> {
+        if mask.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "No modem status lines to wait for",
+            ));
+        }
+        let changed = match self.null_modem {
+            Some(ref null_modem) => null_modem.wait_for_change(mask.lines(), timeout),
+            None => self.wait_for_counts(mask, timeout)?,
+        };
+        if !changed {
+            return Err(Error::new(
+                ErrorKind::TimedOut,
+                "None of the modem status lines changed",
+            ));
+        }
+        self.signals()
+    }
+
+    /// Polls the interrupt counters until one of the `mask` lines changes, or `timeout` elapses
+    ///
+    /// Returns `false` if the wait timed out.
+    fn wait_for_counts(&self, mask: SignalMask, timeout: Duration) -> Result<bool> {
+        let start = Instant::now();
+        let before = self.modem_counts()?;
+        loop {
+            if self.modem_counts()?.since(&before).any_of(mask) {
+                return Ok(true);
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(false);
+            }
+            std::thread::sleep(std::cmp::min(SIGNAL_POLL_INTERVAL, timeout - elapsed));
+        }
+    }
+
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    fn modem_counts(&self) -> Result<ModemSignals> {
+        let counters = ioctl::tiocgicount(self.fd)?;
+        Ok(ModemSignals {
+            clear_to_send: counters.cts as u32,
+            data_set_ready: counters.dsr as u32,
+            ring_indicator: counters.rng as u32,
+            carrier_detect: counters.dcd as u32,
+        })
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "linux")))]
+    fn modem_counts(&self) -> Result<ModemSignals> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Modem line changes are not counted on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    fn error_counts(&self) -> Result<PortErrors> {
+        let counters = ioctl::tiocgicount(self.fd)?;
+        Ok(PortErrors {
+            parity: counters.parity as u32,
+            framing: counters.frame as u32,
+            overrun: counters.overrun as u32,
+            buffer_overrun: counters.buf_overrun as u32,
+            breaks: counters.brk as u32,
+        })
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "linux")))]
+    fn error_counts(&self) -> Result<PortErrors> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Receive errors are not counted on this platform",
+        ))
+    }
+
+    /// Reads from the port, waiting for data until `deadline` expires
+    ///
+    /// This behaves like `io::Read::read()` except that the port's timeout is ignored in favor of
+    /// `deadline`, which may be extended from another thread while this call is blocked.
+    ///
+    /// ## Errors
+    ///
+    /// * `TimedOut` if no data was received before the deadline expired.
+    /// * `Interrupted` if the deadline was cancelled.
+    /// * `InvalidInput` if the port was opened write-only.
+    /// * Any other error that `io::Read::read()` can return.
+    pub fn read_deadline(&mut self, buf: &mut [u8], deadline: &Deadline) -> io::Result<usize> {
+        self.access_mode.check_read()?;
+        let fd = self.fd;
+        let buf = read_chunk(buf, self.max_read_chunk);
+        let result = self.retry_deadline(deadline, poll::wait_read_fd_or_wake, || {
+            nix::unistd::read(fd, buf)
+        });
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
+        }
+        result
+    }
+
+    /// Writes to the port, waiting for it to become writable until `deadline` expires
+    ///
+    /// This behaves like `io::Write::write()` except that the port's timeout is ignored in favor
+    /// of `deadline`, which may be extended from another thread while this call is blocked.
+    ///
+    /// ## Errors
+    ///
+    /// * `TimedOut` if the port did not accept data before the deadline expired.
+    /// * `Interrupted` if the deadline was cancelled.
+    /// * `InvalidInput` if the port was opened read-only.
+    /// * Any other error that `io::Write::write()` can return.
+    pub fn write_deadline(&mut self, buf: &[u8], deadline: &Deadline) -> io::Result<usize> {
+        self.access_mode.check_write()?;
+        let fd = self.fd;
+        let result = self.retry_deadline(deadline, poll::wait_write_fd_or_wake, || {
+            nix::unistd::write(fd, buf)
+        });
+        self.stats.record_write(&result);
+        result
+    }
+
+    /// Runs `op` once the port is ready, waiting again if a clone took the data or the space first
+    fn retry_deadline<O>(
+        &self,
+        deadline: &Deadline,
+        wait: fn(RawFd, &WakePipe, Duration) -> io::Result<Wakeup>,
+        mut op: O,
+    ) -> io::Result<usize>
+    where
+        O: FnMut() -> nix::Result<usize>,
+    {
+        loop {
+            self.wait_deadline(deadline, wait)?;
+            match op() {
+                Err(nix::Error::Sys(nix::errno::Errno::EAGAIN)) => (),
+                result => return result.map_err(|e| io::Error::from(Error::from(e))),
+            }
+        }
+    }
+
+    fn wait_deadline(
+        &self,
+        deadline: &Deadline,
+        wait: fn(RawFd, &WakePipe, Duration) -> io::Result<Wakeup>,
+    ) -> io::Result<()> {
+        loop {
+            if deadline.is_cancelled() {
+                return Err(io::Error::from(Error::new(
+                    ErrorKind::Interrupted,
+                    "Operation was cancelled",
+                )));
+            }
+            match wait(self.fd, deadline.wake_pipe(), deadline.remaining()) {
+                Ok(Wakeup::Ready) => return Ok(()),
+                // The deadline was extended or cancelled, so check it again
+                Ok(Wakeup::Woken) => deadline.wake_pipe().clear(),
+                // The deadline may have been extended right as the previous one expired
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::TimedOut
+                        && deadline.remaining() > Duration::from_secs(0) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// Attempts to clone the `SerialPort`. This allow you to write and read simultaneously from the
     /// same serial connection. Please note that if you want a real asynchronous serial port you
     /// should look at [mio-serial](https://crates.io/crates/mio-serial) or
@@ -303,13 +1040,106 @@ impl TTYPort {
         Ok(TTYPort {
             fd: fd_cloned,
             exclusive: self.exclusive,
+            access_mode: self.access_mode,
             port_name: self.port_name.clone(),
             timeout: self.timeout,
             #[cfg(any(target_os = "ios", target_os = "macos"))]
             baud_rate: self.baud_rate,
+            stats: PortStats::default(),
+            max_read_chunk: self.max_read_chunk,
+            inter_byte_write_delay: self.inter_byte_write_delay,
+            inter_byte_timeout: self.inter_byte_timeout,
+            break_set: AtomicBool::new(false),
+            nonblocking: self.nonblocking.clone(),
+            parity_check: self.parity_check,
+            rx_watchdog: None,
+            modem_counts: None,
+            error_counts: None,
+            null_modem: self.null_modem.clone(),
+            lock: self.lock.clone(),
+            cancel: self.cancel.clone(),
         })
     }
 
+    /// Splits the port into a reading and a writing half
+    ///
+    /// The writing half uses a clone of the port made with `try_clone_native()`, so the halves
+    /// can be used from different threads without sharing the port between them.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the serial port couldn't be cloned.
+    pub fn split(self) -> Result<(ReadHalf<TTYPort>, WriteHalf<TTYPort>)> {
+        crate::split(self, TTYPort::try_clone_native)
+    }
+
+    /// Returns whether the high bit of every received byte is cleared
+    pub fn strip_high_bit(&self) -> Result<bool> {
+        let termios = self.get_termios()?;
+        Ok(termios.c_iflag & libc::ISTRIP == libc::ISTRIP)
+    }
+
+    /// Sets whether the high bit of every received byte is cleared
+    ///
+    /// This controls `ISTRIP` independently of the data bits and parity settings. Ports are opened
+    /// with stripping disabled, so all 8 bits of every byte are passed through by default.
+    pub fn set_strip_high_bit(&mut self, strip: bool) -> Result<()> {
+        let mut termios = self.get_termios()?;
+        if strip {
+            termios.c_iflag |= libc::ISTRIP;
+        } else {
+            termios.c_iflag &= !libc::ISTRIP;
+        }
+        self.set_termios(&termios)
+    }
+
+    /// Sets the sizes of the driver's input and output buffers in bytes
+    ///
+    /// The buffer sizes of TTY devices are fixed by the kernel and can't be changed from user
+    /// space, so this always fails. It exists to mirror `COMPort::set_driver_buffer_sizes()`.
+    /// The `xmit_fifo_size` of Linux's `serial_struct` describes the UART's hardware FIFO rather
+    /// than a buffer, so it isn't used here. To avoid overruns on Linux and Android, try
+    /// `set_low_latency()`, which makes the driver pass received data on sooner.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` as this is not supported on POSIX platforms.
+    pub fn set_driver_buffer_sizes(&mut self, _rx: u32, _tx: u32) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Driver buffer sizes are not settable on this platform",
+        ))
+    }
+
+    /// Sets the byte substituted for characters received with a parity error
+    ///
+    /// POSIX platforms can only mark such characters with `PARMRK` or replace them with `\0`,
+    /// not substitute an arbitrary byte, so this is only available on Windows.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` as this is not supported on POSIX platforms.
+    pub fn set_error_char(&mut self, _ch: Option<u8>) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Error characters are not supported on this platform",
+        ))
+    }
+
+    /// Returns the most bytes a single read asks the kernel for, or 0 if there is no limit
+    pub fn max_read_chunk(&self) -> usize {
+        self.max_read_chunk
+    }
+
+    /// Limits how many bytes a single read asks the kernel for
+    ///
+    /// Reads into a buffer larger than `bytes` return at most `bytes` bytes, so one call never
+    /// issues a huge `read()` syscall. This applies to `io::Read::read()` and `read_deadline()`.
+    /// A limit of 0, the default, passes the whole buffer to the kernel.
+    pub fn set_max_read_chunk(&mut self, bytes: usize) {
+        self.max_read_chunk = bytes;
+    }
+
     #[cfg(any(
         target_os = "dragonflybsd",
         target_os = "freebsd",
@@ -383,7 +1213,16 @@ impl TTYPort {
             let res = unsafe { libc::tcsetattr(self.fd, libc::TCSANOW, &termios) };
             nix::errno::Errno::result(res)?;
 
-            ioctl::iossiospeed(self.fd, &(self.baud_rate as libc::speed_t))
+            // The driver rejects rates the hardware can't generate
+            ioctl::iossiospeed(self.fd, &(self.baud_rate as libc::speed_t)).map_err(|e| {
+                match e.kind() {
+                    ErrorKind::InvalidInput | ErrorKind::Unknown => Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unsupported baud rate {}: {}", self.baud_rate, e),
+                    ),
+                    _ => e,
+                }
+            })
         } else {
             let mut termios = termios.clone();
             termios.c_ispeed = self.baud_rate as libc::speed_t;
@@ -395,6 +1234,27 @@ impl TTYPort {
         }
     }
 
+    /// Applies `termios` at `baud_rate`, cached as the port's rate if that succeeds
+    ///
+    /// A non-standard rate is programmed after first applying the settings at a placeholder rate,
+    /// so on failure the previous settings and rate are restored rather than leaving the port at
+    /// the placeholder.
+    #[cfg(any(target_os = "ios", target_os = "macos"))]
+    fn set_termios_and_baud_rate(&mut self, termios: &libc::termios, baud_rate: u32) -> Result<()> {
+        let previous_termios = self.get_termios()?;
+        let previous_baud_rate = self.baud_rate;
+        self.baud_rate = baud_rate;
+        let result = self.set_termios(termios);
+        if result.is_err() {
+            self.baud_rate = previous_baud_rate;
+            if self.set_termios(&previous_termios).is_err() {
+                // Don't report a rate that isn't applied, go with whatever the driver says instead
+                self.baud_rate = get_termios_speed(self.fd);
+            }
+        }
+        result
+    }
+
     #[cfg(any(
         target_os = "android",
         all(
@@ -411,86 +1271,853 @@ impl TTYPort {
     }
 }
 
-impl Drop for TTYPort {
-    fn drop(&mut self) {
-        close(self.fd);
+/// Checks the output and input speeds read back from the driver, returning the baud rate
+///
+/// An input speed of 0 means it's the same as the output speed. A port that reports different
+/// speeds, or an output speed of 0 (which hangs up the line), is misconfigured, so that's an
+/// error rather than a baud rate the caller would take at face value.
+fn check_baud_rate(ospeed: u32, ispeed: u32) -> Result<u32> {
+    if ospeed == 0 {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Port reported a baud rate of 0",
+        ))
+    } else if ispeed != 0 && ispeed != ospeed {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Port reported different input and output baud rates",
+        ))
+    } else {
+        Ok(ospeed)
     }
 }
 
-impl AsRawFd for TTYPort {
-    fn as_raw_fd(&self) -> RawFd {
-        self.fd
+/// Rejects frame formats that the hardware can't produce as requested
+///
+/// With 5 data bits `CSTOPB` selects 1.5 stop bits rather than 2, and there's no other way to get
+/// 1.5 stop bits, so any other combination would silently transmit different framing than was
+/// asked for.
+fn check_framing(data_bits: DataBits, stop_bits: StopBits) -> Result<()> {
+    match (data_bits, stop_bits) {
+        (DataBits::Five, StopBits::Two) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Two stop bits are not supported with five data bits",
+        )),
+        (DataBits::Five, _) | (_, StopBits::One) | (_, StopBits::Two) => Ok(()),
+        (_, StopBits::OnePointFive) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "1.5 stop bits are only supported with five data bits",
+        )),
+    }
+}
+
+/// The terminal settings structure used on this platform
+#[cfg(any(
+    target_os = "android",
+    all(
+        target_os = "linux",
+        not(any(
+            target_env = "musl",
+            target_arch = "powerpc",
+            target_arch = "powerpc64"
+        ))
+    )
+))]
+type Termios = libc::termios2;
+#[cfg(not(any(
+    target_os = "android",
+    all(
+        target_os = "linux",
+        not(any(
+            target_env = "musl",
+            target_arch = "powerpc",
+            target_arch = "powerpc64"
+        ))
+    )
+)))]
+type Termios = libc::termios;
+
+fn set_data_bits_flags(termios: &mut Termios, data_bits: DataBits) {
+    termios.c_cflag &= !libc::CSIZE;
+    termios.c_cflag |= match data_bits {
+        DataBits::Five => libc::CS5,
+        DataBits::Six => libc::CS6,
+        DataBits::Seven => libc::CS7,
+        DataBits::Eight => libc::CS8,
+    };
+}
+
+fn set_stop_bits_flags(termios: &mut Termios, stop_bits: StopBits) {
+    match stop_bits {
+        StopBits::One => termios.c_cflag &= !libc::CSTOPB,
+        StopBits::OnePointFive | StopBits::Two => termios.c_cflag |= libc::CSTOPB,
+    };
+}
+
+fn termios_data_bits(termios: &Termios) -> Result<DataBits> {
+    match termios.c_cflag & libc::CSIZE {
+        libc::CS8 => Ok(DataBits::Eight),
+        libc::CS7 => Ok(DataBits::Seven),
+        libc::CS6 => Ok(DataBits::Six),
+        libc::CS5 => Ok(DataBits::Five),
+        _ => Err(Error::new(
+            ErrorKind::Unknown,
+            "Invalid data bits setting encountered",
+        )),
+    }
+}
+
+fn termios_stop_bits(termios: &Termios) -> StopBits {
+    if termios.c_cflag & libc::CSTOPB != libc::CSTOPB {
+        StopBits::One
+    } else if termios.c_cflag & libc::CSIZE == libc::CS5 {
+        StopBits::OnePointFive
+    } else {
+        StopBits::Two
+    }
+}
+
+fn set_parity_flags(termios: &mut Termios, parity: Parity, check: ParityCheck) -> Result<()> {
+    let mark_space = match (parity, MARK_SPACE_PARITY) {
+        (Parity::Mark, None) | (Parity::Space, None) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Mark and space parity are not supported on this platform",
+            ))
+        }
+        (_, flag) => flag.unwrap_or(0),
+    };
+    termios.c_cflag &= !mark_space;
+    match parity {
+        Parity::None => {
+            termios.c_cflag &= !(libc::PARENB | libc::PARODD);
+            termios.c_iflag &= !(libc::INPCK | libc::PARMRK);
+            termios.c_iflag |= libc::IGNPAR;
+            return Ok(());
+        }
+        Parity::Odd => {
+            termios.c_cflag |= libc::PARENB | libc::PARODD;
+        }
+        Parity::Even => {
+            termios.c_cflag &= !libc::PARODD;
+            termios.c_cflag |= libc::PARENB;
+        }
+        // With `CMSPAR` the parity bit is a constant set by `PARODD`
+        Parity::Mark => {
+            termios.c_cflag |= libc::PARENB | libc::PARODD | mark_space;
+        }
+        Parity::Space => {
+            termios.c_cflag &= !libc::PARODD;
+            termios.c_cflag |= libc::PARENB | mark_space;
+        }
+    };
+
+    termios.c_iflag |= libc::INPCK;
+    match check {
+        ParityCheck::Ignore => termios.c_iflag &= !(libc::IGNPAR | libc::PARMRK),
+        ParityCheck::Drop => {
+            termios.c_iflag &= !libc::PARMRK;
+            termios.c_iflag |= libc::IGNPAR;
+        }
+        ParityCheck::Mark => {
+            termios.c_iflag &= !libc::IGNPAR;
+            termios.c_iflag |= libc::PARMRK;
+        }
+    }
+    Ok(())
+}
+
+/// The `c_cflag` bit for mark and space parity, where termios supports it
+#[cfg(any(target_os = "android", target_os = "linux"))]
+const MARK_SPACE_PARITY: Option<libc::tcflag_t> = Some(libc::CMSPAR);
+/// The `c_cflag` bit for mark and space parity, where termios supports it
+#[cfg(not(any(target_os = "android", target_os = "linux")))]
+const MARK_SPACE_PARITY: Option<libc::tcflag_t> = None;
+
+/// The `c_cflag` bits for DTR/DSR flow control, where termios supports it
+#[cfg(any(target_os = "dragonfly", target_os = "freebsd"))]
+const DTR_DSR_FLOW: Option<libc::tcflag_t> = Some(libc::CDTR_IFLOW | libc::CDSR_OFLOW);
+/// The `c_cflag` bits for DTR/DSR flow control, where termios supports it
+///
+/// These are `CDTR_IFLOW` and `CDSR_OFLOW`, which `libc` doesn't provide for these platforms.
+#[cfg(any(target_os = "ios", target_os = "macos"))]
+const DTR_DSR_FLOW: Option<libc::tcflag_t> = Some(0x0004_0000 | 0x0008_0000);
+/// The `c_cflag` bits for DTR/DSR flow control, where termios supports it
+#[cfg(not(any(
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "ios",
+    target_os = "macos"
+)))]
+const DTR_DSR_FLOW: Option<libc::tcflag_t> = None;
+
+fn set_flow_control_flags(termios: &mut Termios, flow_control: FlowControl) -> Result<()> {
+    let dtr_dsr = match (flow_control, DTR_DSR_FLOW) {
+        (FlowControl::DtrDsr, None) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "DTR/DSR flow control is not supported on this platform",
+            ))
+        }
+        (_, flags) => flags.unwrap_or(0),
+    };
+    termios.c_cflag &= !dtr_dsr;
+    match flow_control {
+        FlowControl::None => {
+            termios.c_iflag &= !(libc::IXON | libc::IXOFF);
+            termios.c_cflag &= !libc::CRTSCTS;
+        }
+        FlowControl::Software => {
+            termios.c_iflag |= libc::IXON | libc::IXOFF;
+            termios.c_cflag &= !libc::CRTSCTS;
+        }
+        FlowControl::Hardware => {
+            termios.c_iflag &= !(libc::IXON | libc::IXOFF);
+            termios.c_cflag |= libc::CRTSCTS;
+        }
+        FlowControl::DtrDsr => {
+            termios.c_iflag &= !(libc::IXON | libc::IXOFF);
+            termios.c_cflag &= !libc::CRTSCTS;
+            termios.c_cflag |= dtr_dsr;
+        }
+    };
+    Ok(())
+}
+
+/// Sets the baud rate in `termios`
+#[cfg(any(
+    target_os = "android",
+    all(
+        target_os = "linux",
+        not(any(
+            target_env = "musl",
+            target_arch = "powerpc",
+            target_arch = "powerpc64"
+        ))
+    )
+))]
+fn set_termios_baud_rate(termios: &mut Termios, baud_rate: u32) -> Result<()> {
+    termios.c_cflag &= !nix::libc::CBAUD;
+    termios.c_cflag |= nix::libc::BOTHER;
+    termios.c_ispeed = baud_rate;
+    termios.c_ospeed = baud_rate;
+    Ok(())
+}
+
+// BSDs use the baud rate as the constant value so there's no translation necessary
+#[cfg(any(
+    target_os = "dragonflybsd",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn set_termios_baud_rate(termios: &mut Termios, baud_rate: u32) -> Result<()> {
+    let res = unsafe { libc::cfsetspeed(termios, baud_rate.into()) };
+    nix::errno::Errno::result(res)?;
+    Ok(())
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_env = "musl",
+        target_arch = "powerpc",
+        target_arch = "powerpc64"
+    )
+))]
+fn set_termios_baud_rate(termios: &mut Termios, baud_rate: u32) -> Result<()> {
+    use self::libc::{
+        B1000000, B1152000, B1500000, B2000000, B2500000, B3000000, B3500000, B4000000, B460800,
+        B500000, B576000, B921600,
+    };
+    use self::libc::{
+        B110, B115200, B1200, B134, B150, B1800, B19200, B200, B230400, B2400, B300, B38400, B4800,
+        B50, B57600, B600, B75, B9600,
+    };
+
+    let baud_rate = match baud_rate {
+        50 => B50,
+        75 => B75,
+        110 => B110,
+        134 => B134,
+        150 => B150,
+        200 => B200,
+        300 => B300,
+        600 => B600,
+        1200 => B1200,
+        1800 => B1800,
+        2400 => B2400,
+        4800 => B4800,
+        9600 => B9600,
+        19_200 => B19200,
+        38_400 => B38400,
+        57_600 => B57600,
+        115_200 => B115200,
+        230_400 => B230400,
+        460_800 => B460800,
+        500_000 => B500000,
+        576_000 => B576000,
+        921_600 => B921600,
+        1_000_000 => B1000000,
+        1_152_000 => B1152000,
+        1_500_000 => B1500000,
+        2_000_000 => B2000000,
+        2_500_000 => B2500000,
+        3_000_000 => B3000000,
+        3_500_000 => B3500000,
+        4_000_000 => B4000000,
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate")),
+    };
+    let res = unsafe { libc::cfsetspeed(termios, baud_rate) };
+    nix::errno::Errno::result(res)?;
+    Ok(())
+}
+
+/// Waits with `wait` and then performs `op`, retrying both if `op` fails with `EAGAIN`
+///
+/// Some drivers report `EAGAIN` even though the port was just polled as ready and isn't in
+/// non-blocking mode. Retrying until `timeout` has elapsed keeps blocking operations blocking.
+fn retry_on_eagain<W, O>(timeout: Duration, mut wait: W, mut op: O) -> io::Result<usize>
+where
+    W: FnMut(Duration) -> io::Result<()>,
+    O: FnMut() -> nix::Result<usize>,
+{
+    let start = Instant::now();
+    let mut remaining = timeout;
+    loop {
+        if let Err(e) = wait(remaining) {
+            return Err(io::Error::from(Error::from(e)));
+        }
+
+        match op() {
+            Ok(n) => return Ok(n),
+            Err(nix::Error::Sys(nix::errno::Errno::EAGAIN)) => {
+                remaining = match timeout.checked_sub(start.elapsed()) {
+                    Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "Operation timed out",
+                        ))
+                    }
+                };
+            }
+            Err(e) => return Err(io::Error::from(Error::from(e))),
+        }
+    }
+}
+
+impl Drop for TTYPort {
+    fn drop(&mut self) {
+        // The descriptor was handed over with `into_raw_fd()`
+        if self.fd == -1 {
+            return;
+        }
+        if self.break_set.load(Ordering::SeqCst) {
+            let _ = ioctl::tioccbrk(self.fd);
+        }
+        close(self.fd);
+    }
+}
+
+impl AsRawFd for TTYPort {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
     }
 }
 
 impl IntoRawFd for TTYPort {
-    fn into_raw_fd(self) -> RawFd {
-        // Pull just the file descriptor out. We also prevent the destructor
-        // from being run by calling `mem::forget`. If we didn't do this, the
-        // port would be closed, which would make `into_raw_fd` unusable.
-        let TTYPort { fd, .. } = self;
-        mem::forget(self);
-        fd
+    fn into_raw_fd(mut self) -> RawFd {
+        // Hand the descriptor back in the mode its new owner expects. Clones of the port still
+        // poll before reading or writing, so they keep working, although a large write may then
+        // block past their timeout.
+        if !self.nonblocking.load(Ordering::SeqCst) {
+            let _ = set_file_status(self.fd, false);
+        }
+
+        // The caller owns the descriptor from now on, so it's neither closed nor has its break
+        // cleared when the port is dropped. The rest of the port is dropped as usual, which stops
+        // the watchdog's thread and removes the lock file once no clone holds it anymore.
+        mem::replace(&mut self.fd, -1)
+    }
+}
+
+/// Readiness events for the port's file descriptor, available with the `mio` feature
+///
+/// Registering the port puts it in non-blocking mode: `read()` and `write()` return an error of
+/// kind `WouldBlock` instead of waiting for the port's timeout, so they should only be retried
+/// once the port becomes ready. `write_all()` keeps waiting up to the timeout. Deregistering the
+/// port makes it blocking again.
+#[cfg(feature = "mio")]
+impl mio::Evented for TTYPort {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: mio::Ready,
+        opts: mio::PollOpt,
+    ) -> io::Result<()> {
+        self.set_nonblocking_mode(true);
+        mio::unix::EventedFd(&self.fd).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: mio::Ready,
+        opts: mio::PollOpt,
+    ) -> io::Result<()> {
+        mio::unix::EventedFd(&self.fd).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        mio::unix::EventedFd(&self.fd).deregister(poll)?;
+        self.set_nonblocking_mode(false);
+        Ok(())
     }
 }
 
+#[cfg(feature = "mio07")]
+impl mio07::event::Source for TTYPort {
+    fn register(
+        &mut self,
+        registry: &mio07::Registry,
+        token: mio07::Token,
+        interests: mio07::Interest,
+    ) -> io::Result<()> {
+        self.set_nonblocking_mode(true);
+        mio07::unix::SourceFd(&self.fd).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio07::Registry,
+        token: mio07::Token,
+        interests: mio07::Interest,
+    ) -> io::Result<()> {
+        mio07::unix::SourceFd(&self.fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio07::Registry) -> io::Result<()> {
+        mio07::unix::SourceFd(&self.fd).deregister(registry)?;
+        self.set_nonblocking_mode(false);
+        Ok(())
+    }
+}
+
+impl TTYPort {
+    /// Switches reads and writes between waiting for the port and failing with `WouldBlock`
+    fn set_nonblocking_mode(&self, nonblocking: bool) {
+        self.nonblocking.store(nonblocking, Ordering::SeqCst);
+    }
+}
+
+/// Sets `O_NONBLOCK` on `fd`, which it keeps for as long as a port owns it
+///
+/// The flag belongs to the open file description, which is shared with every clone of the port,
+/// so toggling it for one operation could change the behavior of a clone in the middle of its own.
+/// Reads and writes instead always poll for readiness before calling into the kernel and handle
+/// `EAGAIN`, which also keeps a single large write from blocking past the port's timeout.
+/// Whether they wait is decided by the `nonblocking` flag instead.
+fn set_o_nonblock(fd: RawFd) -> nix::Result<()> {
+    set_file_status(fd, true)
+}
+
+/// Sets or clears `O_NONBLOCK` on `fd`, keeping its other file status flags
+fn set_file_status(fd: RawFd, nonblocking: bool) -> nix::Result<()> {
+    use nix::fcntl::{FcntlArg, OFlag};
+
+    let mut flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL)?);
+    flags.set(OFlag::O_NONBLOCK, nonblocking);
+    fcntl(fd, FcntlArg::F_SETFL(flags)).map(|_| ())
+}
+
 /// Get the baud speed for a port from its file descriptor
+///
+/// Returns 0 if the speed can't be read or isn't consistent.
 #[cfg(any(target_os = "ios", target_os = "macos"))]
 fn get_termios_speed(fd: RawFd) -> u32 {
     let mut termios = MaybeUninit::uninit();
     let res = unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) };
-    nix::errno::Errno::result(res).expect("Failed to get termios data");
+    if nix::errno::Errno::result(res).is_err() {
+        return 0;
+    }
     let termios = unsafe { termios.assume_init() };
-    assert_eq!(termios.c_ospeed, termios.c_ispeed);
-    termios.c_ospeed as u32
+    check_baud_rate(termios.c_ospeed as u32, termios.c_ispeed as u32).unwrap_or(0)
+}
+
+/// Returns the path of the device that `fd` refers to, where the platform can look it up
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn fd_path(fd: RawFd) -> Option<String> {
+    let path = std::fs::read_link(format!("/proc/self/fd/{}", fd)).ok()?;
+    path.into_os_string().into_string().ok()
+}
+
+/// Returns the path of the device that `fd` refers to, where the platform can look it up
+#[cfg(any(target_os = "ios", target_os = "macos"))]
+fn fd_path(fd: RawFd) -> Option<String> {
+    // `F_GETPATH` fills a buffer of `MAXPATHLEN` bytes
+    let mut buf = [0u8; 1024];
+    let res = unsafe { libc::fcntl(fd, libc::F_GETPATH, buf.as_mut_ptr()) };
+    if res == -1 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0)?;
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
+/// Returns the path of the device that `fd` refers to, where the platform can look it up
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "ios",
+    target_os = "linux",
+    target_os = "macos"
+)))]
+fn fd_path(_fd: RawFd) -> Option<String> {
+    None
 }
 
 impl FromRawFd for TTYPort {
+    /// Creates a port from a file descriptor of a TTY device
+    ///
+    /// The descriptor is switched to non-blocking I/O with `O_NONBLOCK`, which the port relies on
+    /// to time out reads and writes. Descriptors sharing its open file description are switched
+    /// as well. This can't report errors, so should that fail, a large write may block past the
+    /// port's timeout.
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        let _ = set_o_nonblock(fd);
         TTYPort {
             fd,
             timeout: Duration::from_millis(100),
             exclusive: ioctl::tiocexcl(fd).is_ok(),
-            // It is not trivial to get the file path corresponding to a file descriptor.
-            // We'll punt on it and set it to `None` here.
-            port_name: None,
+            access_mode: get_access_mode(fd),
+            port_name: fd_path(fd),
             // It's not guaranteed that the baud rate in the `termios` struct is correct, as
             // setting an arbitrary baud rate via the `iossiospeed` ioctl overrides that value,
             // but extract that value anyways as a best-guess of the actual baud rate.
             #[cfg(any(target_os = "ios", target_os = "macos"))]
             baud_rate: get_termios_speed(fd),
+            stats: PortStats::default(),
+            max_read_chunk: 0,
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            break_set: AtomicBool::new(false),
+            nonblocking: Arc::new(AtomicBool::new(false)),
+            parity_check: ParityCheck::Ignore,
+            rx_watchdog: None,
+            modem_counts: None,
+            error_counts: None,
+            null_modem: None,
+            lock: None,
+            cancel: SharedToken::default(),
         }
     }
 }
 
+/// Returns the directions `fd` was opened for, assuming both if they can't be determined
+fn get_access_mode(fd: RawFd) -> AccessMode {
+    let flags = match fcntl(fd, nix::fcntl::F_GETFL) {
+        Ok(flags) => nix::fcntl::OFlag::from_bits_truncate(flags),
+        Err(_) => return AccessMode::ReadWrite,
+    };
+    match flags & nix::fcntl::OFlag::O_ACCMODE {
+        nix::fcntl::OFlag::O_RDONLY => AccessMode::ReadOnly,
+        nix::fcntl::OFlag::O_WRONLY => AccessMode::WriteOnly,
+        _ => AccessMode::ReadWrite,
+    }
+}
+
+/// Limits `buf` to the first `max` bytes, or returns it unchanged if `max` is 0
+fn read_chunk(buf: &mut [u8], max: usize) -> &mut [u8] {
+    if max > 0 && buf.len() > max {
+        &mut buf[..max]
+    } else {
+        buf
+    }
+}
+
 impl io::Read for TTYPort {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if let Err(e) = super::poll::wait_read_fd(self.fd, self.timeout) {
-            return Err(io::Error::from(Error::from(e)));
+        self.access_mode.check_read()?;
+        let fd = self.fd;
+        let buf = read_chunk(buf, self.max_read_chunk);
+        let result = if self.nonblocking.load(Ordering::SeqCst) {
+            nix::unistd::read(fd, buf).map_err(|e| io::Error::from(Error::from(e)))
+        } else {
+            let start = Instant::now();
+            let result = retry_on_eagain(
+                self.timeout,
+                |timeout| self.wait_read(timeout),
+                || nix::unistd::read(fd, buf),
+            );
+            match (result, self.inter_byte_timeout) {
+                (Ok(n), Some(gap)) if n > 0 => Ok(self.read_until_gap(buf, n, gap, start)),
+                (result, _) => result,
+            }
+        };
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
         }
+        result
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let token = self.cancel.get();
+        crate::cancel::read_exact(self, buf, token.as_ref())
+    }
 
-        match nix::unistd::read(self.fd, buf) {
-            Ok(n) => Ok(n),
-            Err(e) => Err(io::Error::from(Error::from(e))),
+    /// Reads into `bufs` with a single `readv()`
+    ///
+    /// Reads limited by `set_max_read_chunk()` or continued by an inter-byte timeout fill only the
+    /// first non-empty buffer instead, like the default implementation.
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut]) -> io::Result<usize> {
+        if self.max_read_chunk > 0 || self.inter_byte_timeout.is_some() {
+            let buf = bufs
+                .iter_mut()
+                .find(|b| !b.is_empty())
+                .map_or(&mut [][..], |b| &mut **b);
+            return self.read(buf);
+        }
+        self.access_mode.check_read()?;
+        let fd = self.fd;
+        let result = if self.nonblocking.load(Ordering::SeqCst) {
+            readv(fd, bufs).map_err(|e| io::Error::from(Error::from(e)))
+        } else {
+            retry_on_eagain(
+                self.timeout,
+                |timeout| self.wait_read(timeout),
+                || readv(fd, bufs),
+            )
+        };
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
         }
+        result
+    }
+}
+
+/// The most buffers passed to `readv()` or `writev()`, which is `IOV_MAX` on all supported
+/// platforms
+const MAX_IO_SLICES: usize = 1024;
+
+fn readv(fd: RawFd, bufs: &mut [io::IoSliceMut]) -> nix::Result<usize> {
+    let count = bufs.len().min(MAX_IO_SLICES) as libc::c_int;
+    // `IoSliceMut` is guaranteed to be ABI compatible with `iovec` on Unix
+    let res = unsafe { libc::readv(fd, bufs.as_mut_ptr() as *const libc::iovec, count) };
+    nix::errno::Errno::result(res).map(|n| n as usize)
+}
+
+fn writev(fd: RawFd, bufs: &[io::IoSlice]) -> nix::Result<usize> {
+    let count = bufs.len().min(MAX_IO_SLICES) as libc::c_int;
+    // `IoSlice` is guaranteed to be ABI compatible with `iovec` on Unix
+    let res = unsafe { libc::writev(fd, bufs.as_ptr() as *const libc::iovec, count) };
+    nix::errno::Errno::result(res).map(|n| n as usize)
+}
+
+impl TTYPort {
+    /// Waits for the port to become readable, failing with `Interrupted` once it's cancelled
+    fn wait_read(&self, timeout: Duration) -> io::Result<()> {
+        self.wait_cancellable(timeout, poll::wait_read_fd, poll::wait_read_fd_or_wake)
+    }
+
+    /// Waits for the port to become writable, failing with `Interrupted` once it's cancelled
+    fn wait_write(&self, timeout: Duration) -> io::Result<()> {
+        self.wait_cancellable(timeout, poll::wait_write_fd, poll::wait_write_fd_or_wake)
+    }
+
+    fn wait_cancellable(
+        &self,
+        timeout: Duration,
+        wait: fn(RawFd, Duration) -> io::Result<()>,
+        wait_or_wake: fn(RawFd, &WakePipe, Duration) -> io::Result<Wakeup>,
+    ) -> io::Result<()> {
+        let token = match self.cancel.get() {
+            Some(token) => token,
+            None => return wait(self.fd, timeout),
+        };
+        token.check()?;
+        match wait_or_wake(self.fd, token.wake_pipe(), timeout)? {
+            Wakeup::Ready => Ok(()),
+            // The token's pipe is never cleared, so it has been cancelled
+            Wakeup::Woken => Err(token.error()),
+        }
+    }
+
+    /// Continues a read that has filled the first `filled` bytes of `buf` until the line is quiet
+    /// for `gap`
+    ///
+    /// The read that started at `start` is still limited by the port's timeout. Errors end the
+    /// read early and are left for the next one to report, as some data has been read already.
+    fn read_until_gap(
+        &self,
+        buf: &mut [u8],
+        mut filled: usize,
+        gap: Duration,
+        start: Instant,
+    ) -> usize {
+        while filled < buf.len() {
+            let remaining = match self.timeout.checked_sub(start.elapsed()) {
+                Some(remaining) => remaining.min(gap),
+                None => break,
+            };
+            if self.wait_read(remaining).is_err() {
+                break;
+            }
+            // `poll()` reported data, so this doesn't block
+            match unistd::read(self.fd, &mut buf[filled..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => filled += n,
+            }
+        }
+        filled
     }
 }
 
 impl io::Write for TTYPort {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        if let Err(e) = super::poll::wait_write_fd(self.fd, self.timeout) {
-            return Err(io::Error::from(Error::from(e)));
+        self.access_mode.check_write()?;
+        if self.inter_byte_write_delay > Duration::from_secs(0) && buf.len() > 1 {
+            for (i, byte) in buf.iter().enumerate() {
+                if i > 0 {
+                    std::thread::sleep(self.inter_byte_write_delay);
+                }
+                match self.write(std::slice::from_ref(byte)) {
+                    Ok(0) => return Ok(i),
+                    Ok(_) => (),
+                    Err(e) if i == 0 => return Err(e),
+                    // Report the bytes that made it out, the error shows up on the next write
+                    Err(_) => return Ok(i),
+                }
+            }
+            return Ok(buf.len());
         }
+        let fd = self.fd;
+        let result = if self.nonblocking.load(Ordering::SeqCst) {
+            nix::unistd::write(fd, buf).map_err(|e| io::Error::from(Error::from(e)))
+        } else {
+            retry_on_eagain(
+                self.timeout,
+                |timeout| self.wait_write(timeout),
+                || nix::unistd::write(fd, buf),
+            )
+        };
+        self.stats.record_write(&result);
+        result
+    }
+
+    /// Writes from `bufs` with a single `writev()`
+    ///
+    /// With an inter-byte write delay only the first non-empty buffer is written, like the default
+    /// implementation.
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        if self.inter_byte_write_delay > Duration::from_secs(0) {
+            let buf = bufs
+                .iter()
+                .find(|b| !b.is_empty())
+                .map_or(&[][..], |b| &**b);
+            return self.write(buf);
+        }
+        self.access_mode.check_write()?;
+        let fd = self.fd;
+        let result = if self.nonblocking.load(Ordering::SeqCst) {
+            writev(fd, bufs).map_err(|e| io::Error::from(Error::from(e)))
+        } else {
+            retry_on_eagain(
+                self.timeout,
+                |timeout| self.wait_write(timeout),
+                || writev(fd, bufs),
+            )
+        };
+        self.stats.record_write(&result);
+        result
+    }
+
+    /// Writes all of `buf`, giving up once the port's timeout has elapsed
+    ///
+    /// Unlike the default implementation the timeout limits the whole call rather than each
+    /// write, so a peer that keeps accepting data slowly can't block it indefinitely. The data is
+    /// written without blocking once the port is writable, as a blocking write of more than fits
+    /// in the kernel's buffer waits for the rest with no timeout at all.
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.access_mode.check_write()?;
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return crate::write_all_nonblocking(self, buf);
+        }
+        if self.inter_byte_write_delay > Duration::from_secs(0) && buf.len() > 1 {
+            for (i, byte) in buf.iter().enumerate() {
+                if i > 0 {
+                    std::thread::sleep(self.inter_byte_write_delay);
+                }
+                self.write_all(std::slice::from_ref(byte))?;
+            }
+            return Ok(());
+        }
+        let fd = self.fd;
+        let start = Instant::now();
+        let mut written = 0;
+        let mut first = true;
+        while written < buf.len() {
+            // Give the first wait the whole timeout in case the time spent getting here already
+            // used up a sub-millisecond timeout
+            let remaining = if first {
+                self.timeout
+            } else {
+                self.timeout
+                    .checked_sub(start.elapsed())
+                    .unwrap_or_else(|| Duration::from_secs(0))
+            };
+            first = false;
+
+            if let Err(e) = self.wait_write(remaining) {
+                // `poll()` truncates the timeout to whole milliseconds, so it can return early
+                if e.kind() == io::ErrorKind::TimedOut && start.elapsed() < self.timeout {
+                    continue;
+                }
+                if e.kind() == io::ErrorKind::TimedOut {
+                    self.stats.timeouts += 1;
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "Operation timed out after writing {} of {} bytes",
+                            written,
+                            buf.len()
+                        ),
+                    ));
+                }
+                return Err(e);
+            }
 
-        match nix::unistd::write(self.fd, buf) {
-            Ok(n) => Ok(n),
-            Err(e) => Err(io::Error::from(Error::from(e))),
+            let result = unistd::write(fd, &buf[written..]).map_err(Error::from);
+            match result {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "Failed to write whole buffer",
+                    ))
+                }
+                Ok(n) => {
+                    self.stats.bytes_written += n as u64;
+                    written += n;
+                }
+                // The buffer filled up again between polling and writing
+                Err(ref e) if e.kind() == ErrorKind::Io(io::ErrorKind::WouldBlock) => (),
+                Err(ref e) if e.kind() == ErrorKind::Io(io::ErrorKind::Interrupted) => (),
+                Err(e) => {
+                    self.stats.write_errors += 1;
+                    return Err(e.into());
+                }
+            }
         }
+        Ok(())
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        nix::sys::termios::tcdrain(self.fd)
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "flush failed"))
+        // Writes go straight to the kernel, so there's nothing buffered to hand over. Waiting for
+        // transmission is done by `SerialPort::drain()`.
+        Ok(())
     }
 }
 
@@ -516,10 +2143,7 @@ impl SerialPort for TTYPort {
     ))]
     fn baud_rate(&self) -> Result<u32> {
         let termios2 = ioctl::tcgets2(self.fd)?;
-
-        assert!(termios2.c_ospeed == termios2.c_ispeed);
-
-        Ok(termios2.c_ospeed as u32)
+        check_baud_rate(termios2.c_ospeed as u32, termios2.c_ispeed as u32)
     }
 
     /// Returns the port's baud rate
@@ -537,10 +2161,7 @@ impl SerialPort for TTYPort {
 
         let ospeed = unsafe { libc::cfgetospeed(&termios) };
         let ispeed = unsafe { libc::cfgetispeed(&termios) };
-
-        assert!(ospeed == ispeed);
-
-        Ok(ospeed as u32)
+        check_baud_rate(ospeed as u32, ispeed as u32)
     }
 
     /// Returns the port's baud rate
@@ -549,7 +2170,18 @@ impl SerialPort for TTYPort {
     /// desired baud rate.
     #[cfg(any(target_os = "ios", target_os = "macos"))]
     fn baud_rate(&self) -> Result<u32> {
-        Ok(self.baud_rate)
+        // The cached rate is 0 if it couldn't be determined
+        check_baud_rate(self.baud_rate, self.baud_rate)
+    }
+
+    /// Reads the baud rate back from the driver
+    ///
+    /// The driver reports the rate last programmed through `IOSSIOSPEED`, if any, so unlike
+    /// `baud_rate()` this reflects what the hardware accepted for non-standard rates.
+    #[cfg(any(target_os = "ios", target_os = "macos"))]
+    fn actual_baud_rate(&self) -> Result<u32> {
+        let termios = self.get_termios()?;
+        check_baud_rate(termios.c_ospeed as u32, termios.c_ispeed as u32)
     }
 
     /// Returns the port's baud rate
@@ -577,8 +2209,8 @@ impl SerialPort for TTYPort {
         let termios = self.get_termios()?;
         let ospeed = unsafe { libc::cfgetospeed(&termios) };
         let ispeed = unsafe { libc::cfgetispeed(&termios) };
-
-        assert!(ospeed == ispeed);
+        // `B0` is 0 and the other constants are nonzero, so this works on them as well
+        check_baud_rate(ospeed as u32, ispeed as u32)?;
 
         let res: u32 = match ospeed {
             B50 => 50,
@@ -611,7 +2243,12 @@ impl SerialPort for TTYPort {
             B3000000 => 3_000_000,
             B3500000 => 3_500_000,
             B4000000 => 4_000_000,
-            _ => unreachable!(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Unknown,
+                    "Port reported an unknown baud rate",
+                ))
+            }
         };
 
         Ok(res)
@@ -619,21 +2256,15 @@ impl SerialPort for TTYPort {
 
     fn data_bits(&self) -> Result<DataBits> {
         let termios = self.get_termios()?;
-        match termios.c_cflag & libc::CSIZE {
-            libc::CS8 => Ok(DataBits::Eight),
-            libc::CS7 => Ok(DataBits::Seven),
-            libc::CS6 => Ok(DataBits::Six),
-            libc::CS5 => Ok(DataBits::Five),
-            _ => Err(Error::new(
-                ErrorKind::Unknown,
-                "Invalid data bits setting encountered",
-            )),
-        }
+        termios_data_bits(&termios)
     }
 
     fn flow_control(&self) -> Result<FlowControl> {
         let termios = self.get_termios()?;
-        if termios.c_cflag & libc::CRTSCTS == libc::CRTSCTS {
+        let dtr_dsr = DTR_DSR_FLOW.filter(|&flags| termios.c_cflag & flags == flags);
+        if dtr_dsr.is_some() {
+            Ok(FlowControl::DtrDsr)
+        } else if termios.c_cflag & libc::CRTSCTS == libc::CRTSCTS {
             Ok(FlowControl::Hardware)
         } else if termios.c_iflag & (libc::IXON | libc::IXOFF) == (libc::IXON | libc::IXOFF) {
             Ok(FlowControl::Software)
@@ -642,13 +2273,21 @@ impl SerialPort for TTYPort {
         }
     }
 
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        let termios = self.get_termios()?;
+        Ok((termios.c_cc[libc::VSTART], termios.c_cc[libc::VSTOP]))
+    }
+
     fn parity(&self) -> Result<Parity> {
         let termios = self.get_termios()?;
         if termios.c_cflag & libc::PARENB == libc::PARENB {
-            if termios.c_cflag & libc::PARODD == libc::PARODD {
-                Ok(Parity::Odd)
-            } else {
-                Ok(Parity::Even)
+            let mark_space = MARK_SPACE_PARITY.filter(|&flag| termios.c_cflag & flag == flag);
+            let odd = termios.c_cflag & libc::PARODD == libc::PARODD;
+            match (mark_space.is_some(), odd) {
+                (true, true) => Ok(Parity::Mark),
+                (true, false) => Ok(Parity::Space),
+                (false, true) => Ok(Parity::Odd),
+                (false, false) => Ok(Parity::Even),
             }
         } else {
             Ok(Parity::None)
@@ -657,185 +2296,110 @@ impl SerialPort for TTYPort {
 
     fn stop_bits(&self) -> Result<StopBits> {
         let termios = self.get_termios()?;
-        if termios.c_cflag & libc::CSTOPB == libc::CSTOPB {
-            Ok(StopBits::Two)
-        } else {
-            Ok(StopBits::One)
-        }
+        Ok(termios_stop_bits(&termios))
     }
 
     fn timeout(&self) -> Duration {
         self.timeout
     }
 
-    #[cfg(any(
-        target_os = "android",
-        all(
-            target_os = "linux",
-            not(any(
-                target_env = "musl",
-                target_arch = "powerpc",
-                target_arch = "powerpc64"
-            ))
-        )
-    ))]
-    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
-        let mut termios2 = ioctl::tcgets2(self.fd)?;
-        termios2.c_cflag &= !nix::libc::CBAUD;
-        termios2.c_cflag |= nix::libc::BOTHER;
-        termios2.c_ispeed = baud_rate;
-        termios2.c_ospeed = baud_rate;
-
-        ioctl::tcsets2(self.fd, &termios2)
+    fn inter_byte_write_delay(&self) -> Duration {
+        self.inter_byte_write_delay
     }
 
-    // BSDs use the baud rate as the constant value so there's no translation necessary
-    #[cfg(any(
-        target_os = "dragonflybsd",
-        target_os = "freebsd",
-        target_os = "netbsd",
-        target_os = "openbsd"
-    ))]
-    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
-        let mut termios = self.get_termios()?;
-        let res = unsafe { libc::cfsetspeed(&mut termios, baud_rate.into()) };
-        nix::errno::Errno::result(res)?;
-        self.set_termios(&termios)
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.inter_byte_timeout
     }
 
-    #[cfg(all(
-        target_os = "linux",
-        any(
-            target_env = "musl",
-            target_arch = "powerpc",
-            target_arch = "powerpc64"
-        )
-    ))]
+    #[cfg(not(any(target_os = "ios", target_os = "macos")))]
     fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
-        use self::libc::{
-            B1000000, B1152000, B1500000, B2000000, B2500000, B3000000, B3500000, B4000000,
-            B460800, B500000, B576000, B921600,
-        };
-        use self::libc::{
-            B110, B115200, B1200, B134, B150, B1800, B19200, B200, B230400, B2400, B300, B38400,
-            B4800, B50, B57600, B600, B75, B9600,
-        };
-
         let mut termios = self.get_termios()?;
-
-        let baud_rate = match baud_rate {
-            50 => B50,
-            75 => B75,
-            110 => B110,
-            134 => B134,
-            150 => B150,
-            200 => B200,
-            300 => B300,
-            600 => B600,
-            1200 => B1200,
-            1800 => B1800,
-            2400 => B2400,
-            4800 => B4800,
-            9600 => B9600,
-            19_200 => B19200,
-            38_400 => B38400,
-            57_600 => B57600,
-            115_200 => B115200,
-            230_400 => B230400,
-            460_800 => B460800,
-            500_000 => B500000,
-            576_000 => B576000,
-            921_600 => B921600,
-            1_000_000 => B1000000,
-            1_152_000 => B1152000,
-            1_500_000 => B1500000,
-            2_000_000 => B2000000,
-            2_500_000 => B2500000,
-            3_000_000 => B3000000,
-            3_500_000 => B3500000,
-            4_000_000 => B4000000,
-            _ => return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate")),
-        };
-        let res = unsafe { libc::cfsetspeed(&mut termios, baud_rate) };
-        nix::errno::Errno::result(res)?;
+        set_termios_baud_rate(&mut termios, baud_rate)?;
         self.set_termios(&termios)
     }
 
     // Mac OS needs special logic for setting arbitrary baud rates.
     #[cfg(any(target_os = "ios", target_os = "macos"))]
     fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
-        self.baud_rate = baud_rate;
         let termios = self.get_termios()?;
-        self.set_termios(&termios)
+        self.set_termios_and_baud_rate(&termios, baud_rate)
     }
 
     fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
         let mut termios = self.get_termios()?;
-        match flow_control {
-            FlowControl::None => {
-                termios.c_iflag &= !(libc::IXON | libc::IXOFF);
-                termios.c_cflag &= !libc::CRTSCTS;
-            }
-            FlowControl::Software => {
-                termios.c_iflag |= libc::IXON | libc::IXOFF;
-                termios.c_cflag &= !libc::CRTSCTS;
-            }
-            FlowControl::Hardware => {
-                termios.c_iflag &= !(libc::IXON | libc::IXOFF);
-                termios.c_cflag |= libc::CRTSCTS;
-            }
-        };
+        set_flow_control_flags(&mut termios, flow_control)?;
+        self.set_termios(&termios)
+    }
+
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        crate::check_xon_xoff_chars(xon, xoff)?;
+        let mut termios = self.get_termios()?;
+        termios.c_cc[libc::VSTART] = xon;
+        termios.c_cc[libc::VSTOP] = xoff;
         self.set_termios(&termios)
     }
 
     fn set_parity(&mut self, parity: Parity) -> Result<()> {
         let mut termios = self.get_termios()?;
-        match parity {
-            Parity::None => {
-                termios.c_cflag &= !(libc::PARENB | libc::PARODD);
-                termios.c_iflag &= !libc::INPCK;
-                termios.c_iflag |= libc::IGNPAR;
-            }
-            Parity::Odd => {
-                termios.c_cflag |= libc::PARENB | libc::PARODD;
-                termios.c_iflag |= libc::INPCK;
-                termios.c_iflag &= !libc::IGNPAR;
-            }
-            Parity::Even => {
-                termios.c_cflag &= !libc::PARODD;
-                termios.c_cflag |= libc::PARENB;
-                termios.c_iflag |= libc::INPCK;
-                termios.c_iflag &= !libc::IGNPAR;
-            }
-        };
+        set_parity_flags(&mut termios, parity, self.parity_check)?;
         self.set_termios(&termios)
     }
 
     fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
-        let size = match data_bits {
-            DataBits::Five => libc::CS5,
-            DataBits::Six => libc::CS6,
-            DataBits::Seven => libc::CS7,
-            DataBits::Eight => libc::CS8,
-        };
-
         let mut termios = self.get_termios()?;
-        termios.c_cflag &= !libc::CSIZE;
-        termios.c_cflag |= size;
+        check_framing(data_bits, termios_stop_bits(&termios))?;
+        set_data_bits_flags(&mut termios, data_bits);
         self.set_termios(&termios)
     }
 
     fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
         let mut termios = self.get_termios()?;
-        match stop_bits {
-            StopBits::One => termios.c_cflag &= !libc::CSTOPB,
-            StopBits::Two => termios.c_cflag |= libc::CSTOPB,
-        };
+        check_framing(termios_data_bits(&termios)?, stop_bits)?;
+        set_stop_bits_flags(&mut termios, stop_bits);
         self.set_termios(&termios)
     }
 
-    fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
-        self.timeout = timeout;
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        check_framing(settings.data_bits, settings.stop_bits)?;
+
+        let mut termios = self.get_termios()?;
+        set_data_bits_flags(&mut termios, settings.data_bits);
+        set_stop_bits_flags(&mut termios, settings.stop_bits);
+        set_parity_flags(&mut termios, settings.parity, self.parity_check)?;
+        set_flow_control_flags(&mut termios, settings.flow_control)?;
+        #[cfg(not(any(target_os = "ios", target_os = "macos")))]
+        set_termios_baud_rate(&mut termios, settings.baud_rate)?;
+
+        #[cfg(any(target_os = "ios", target_os = "macos"))]
+        let result = self.set_termios_and_baud_rate(&termios, settings.baud_rate);
+        #[cfg(not(any(target_os = "ios", target_os = "macos")))]
+        let result = self.set_termios(&termios);
+
+        result?;
+        self.timeout = settings.timeout;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        Ok(mem::replace(&mut self.timeout, timeout))
+    }
+
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        self.inter_byte_write_delay = delay;
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inter_byte_timeout = timeout;
+        Ok(())
+    }
+
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        self.rx_watchdog = Some(RxWatchdog::start(timeout, on_stall)?);
         Ok(())
     }
 
@@ -863,6 +2427,16 @@ impl SerialPort for TTYPort {
         self.read_pin(SerialLines::DATA_CARRIER_DETECT)
     }
 
+    fn signals(&self) -> Result<SignalState> {
+        let lines = self.read_lines()?;
+        Ok(SignalState {
+            clear_to_send: lines.contains(SerialLines::CLEAR_TO_SEND),
+            data_set_ready: lines.contains(SerialLines::DATA_SET_READY),
+            ring_indicator: lines.contains(SerialLines::RING),
+            carrier_detect: lines.contains(SerialLines::DATA_CARRIER_DETECT),
+        })
+    }
+
     fn bytes_to_read(&self) -> Result<u32> {
         ioctl::fionread(self.fd)
     }
@@ -871,6 +2445,10 @@ impl SerialPort for TTYPort {
         ioctl::tiocoutq(self.fd)
     }
 
+    fn drain(&mut self) -> Result<()> {
+        nix::sys::termios::tcdrain(self.fd).map_err(|e| e.into())
+    }
+
     fn clear(&self, buffer_to_clear: ClearBuffer) -> Result<()> {
         let buffer_id = match buffer_to_clear {
             ClearBuffer::Input => libc::TCIFLUSH,
@@ -880,9 +2458,84 @@ impl SerialPort for TTYPort {
 
         let res = unsafe { nix::libc::tcflush(self.fd, buffer_id) };
 
+        // Report failures the same way as `PurgeComm()` failures are on Windows
         nix::errno::Errno::result(res)
             .map(|_| ())
-            .map_err(|e| e.into())
+            .map_err(|e| match e {
+                nix::Error::Sys(errno @ nix::errno::Errno::ENXIO)
+                | nix::Error::Sys(errno @ nix::errno::Errno::ENODEV) => {
+                    Error::new(ErrorKind::NoDevice, errno.desc())
+                }
+                nix::Error::Sys(errno) => {
+                    Error::new(ErrorKind::Io(io::ErrorKind::Other), errno.desc())
+                }
+                e => e.into(),
+            })
+    }
+
+    fn set_break(&self) -> Result<()> {
+        TTYPort::set_break(self)
+    }
+
+    fn clear_break(&self) -> Result<()> {
+        TTYPort::clear_break(self)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.set_nonblocking_mode(nonblocking);
+        Ok(())
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.access_mode.check_write()?;
+        let fd = self.fd;
+        // The descriptor is always non-blocking, see `set_o_nonblock()`
+        let result = unistd::write(fd, buf).map_err(Error::from);
+        if let Ok(n) = result {
+            self.stats.bytes_written += n as u64;
+        }
+        result
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.access_mode.check_read()?;
+        let fd = self.fd;
+        let buf = read_chunk(buf, self.max_read_chunk);
+        // The descriptor is always non-blocking, see `set_o_nonblock()`
+        let result = unistd::read(fd, buf).map_err(Error::from);
+        if let Ok(n) = result {
+            self.stats.bytes_read += n as u64;
+        }
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
+        }
+        result
+    }
+
+    fn read_is_eof_possible(&self) -> bool {
+        true
+    }
+
+    fn stats(&self) -> PortStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = PortStats::default();
+    }
+
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.cancel.get_or_create()
+    }
+
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        let counts = self.error_counts()?;
+        let errors = match self.error_counts {
+            Some(ref previous) => counts.since(previous),
+            None => PortErrors::default(),
+        };
+        self.error_counts = Some(counts);
+        Ok(errors)
     }
 
     fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
@@ -921,3 +2574,228 @@ fn test_ttyport_into_raw_fd() {
     close(master_fd);
     close(slave_fd);
 }
+
+#[test]
+fn test_ttyport_high_fd_timeout() {
+    use std::io::Read;
+
+    // `select()` can't handle file descriptors at or above `FD_SETSIZE` (usually 1024), so make
+    // sure waiting for data still times out correctly on one well above that.
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let high_fd = match fcntl(slave.as_raw_fd(), nix::fcntl::F_DUPFD(4096)) {
+        Ok(fd) => fd,
+        // The file descriptor limit is too low to run this test
+        Err(nix::Error::Sys(nix::errno::Errno::EINVAL)) => return,
+        Err(e) => panic!("Unable to duplicate the slave fd: {}", e),
+    };
+    assert!(high_fd >= 4096);
+
+    let mut port = unsafe { TTYPort::from_raw_fd(high_fd) };
+    port.set_timeout(Duration::from_millis(10)).unwrap();
+    let mut buf = [0u8; 16];
+    let err = port
+        .read(&mut buf)
+        .expect_err("Read succeeded without any data");
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_retry_on_eagain() {
+    use nix::errno::Errno::{EAGAIN, EIO};
+
+    // A spurious `EAGAIN` is retried until data arrives
+    let mut results = vec![Ok(5), Err(nix::Error::Sys(EAGAIN))];
+    let mut waits = 0;
+    let result = retry_on_eagain(
+        Duration::from_millis(100),
+        |_| {
+            waits += 1;
+            Ok(())
+        },
+        || results.pop().unwrap(),
+    );
+    assert_eq!(result.unwrap(), 5);
+    assert_eq!(waits, 2);
+
+    // Persistent `EAGAIN`s eventually time out
+    let result = retry_on_eagain(
+        Duration::from_millis(10),
+        |_| Ok(()),
+        || Err(nix::Error::Sys(EAGAIN)),
+    );
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+    // Other errors are returned immediately
+    let result = retry_on_eagain(
+        Duration::from_millis(100),
+        |_| Ok(()),
+        || Err(nix::Error::Sys(EIO)),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_ttyport_not_controlling_terminal() {
+    // `tcgetpgrp()` fails with `ENOTTY` on terminals that aren't the controlling terminal of the
+    // calling process. This doesn't apply to the master, which reports the state of the slave.
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let res = unsafe { libc::tcgetpgrp(slave.as_raw_fd()) };
+    assert_eq!(res, -1);
+    assert_eq!(nix::errno::Errno::last(), nix::errno::Errno::ENOTTY);
+}
+
+#[test]
+fn test_break_tracking() {
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    assert!(!slave.break_set.load(Ordering::SeqCst));
+
+    slave.set_break().expect("Unable to set break");
+    assert!(slave.break_set.load(Ordering::SeqCst));
+    slave.clear_break().expect("Unable to clear break");
+    assert!(!slave.break_set.load(Ordering::SeqCst));
+
+    // Dropping a port with the break still set clears it
+    slave.set_break().expect("Unable to set break");
+}
+
+#[test]
+fn test_failed_baud_rate_change_is_not_reported() {
+    // A pipe isn't a terminal, so reconfiguring it always fails
+    let (read_fd, write_fd) = unistd::pipe().unwrap();
+    let mut port = unsafe { TTYPort::from_raw_fd(read_fd) };
+    assert!(port.set_baud_rate(115_200).is_err());
+    assert!(port.baud_rate().is_err());
+    unistd::close(write_fd).unwrap();
+}
+
+#[test]
+fn test_clear_error_kind() {
+    let (read_fd, write_fd) = unistd::pipe().unwrap();
+    let port = unsafe { TTYPort::from_raw_fd(read_fd) };
+    let err = port.clear(ClearBuffer::All).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io(io::ErrorKind::Other));
+    unistd::close(write_fd).unwrap();
+}
+
+#[test]
+fn test_modem_signals_since() {
+    let earlier = ModemSignals {
+        clear_to_send: 3,
+        data_set_ready: 0,
+        ring_indicator: 0xFFFF_FFFF,
+        carrier_detect: 7,
+    };
+    let now = ModemSignals {
+        clear_to_send: 4,
+        data_set_ready: 0,
+        ring_indicator: 1,
+        carrier_detect: 10,
+    };
+    let changes = now.since(&earlier);
+    assert_eq!(changes.clear_to_send, 1);
+    assert_eq!(changes.data_set_ready, 0);
+    assert_eq!(changes.ring_indicator, 2);
+    assert_eq!(changes.carrier_detect, 3);
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[test]
+fn test_rs485_config_to_raw() {
+    let raw = Rs485Config::default().to_raw();
+    assert_eq!(
+        raw.flags,
+        ioctl::SER_RS485_ENABLED | ioctl::SER_RS485_RTS_ON_SEND
+    );
+    assert_eq!(raw.delay_rts_before_send, 0);
+
+    let raw = Rs485Config {
+        rts_on_send: false,
+        rts_after_send: true,
+        rx_during_tx: true,
+        delay_before_send: Duration::from_micros(2500),
+        delay_after_send: Duration::from_millis(10),
+    }
+    .to_raw();
+    assert_eq!(
+        raw.flags,
+        ioctl::SER_RS485_ENABLED | ioctl::SER_RS485_RTS_AFTER_SEND | ioctl::SER_RS485_RX_DURING_TX
+    );
+    assert_eq!(raw.delay_rts_before_send, 2);
+    assert_eq!(raw.delay_rts_after_send, 10);
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[test]
+fn test_rs485_config_from_raw() {
+    assert_eq!(Rs485Config::from_raw(ioctl::SerialRs485::default()), None);
+
+    let config = Rs485Config {
+        rts_on_send: false,
+        rts_after_send: true,
+        rx_during_tx: true,
+        delay_before_send: Duration::from_millis(2),
+        delay_after_send: Duration::from_millis(10),
+    };
+    assert_eq!(Rs485Config::from_raw(config.to_raw()), Some(config));
+}
+
+#[test]
+fn test_check_baud_rate() {
+    assert_eq!(check_baud_rate(9600, 9600).unwrap(), 9600);
+    assert_eq!(check_baud_rate(9600, 0).unwrap(), 9600);
+    assert!(check_baud_rate(0, 0).is_err());
+    assert!(check_baud_rate(9600, 19200).is_err());
+}
+
+#[test]
+fn test_check_framing() {
+    assert!(check_framing(DataBits::Five, StopBits::OnePointFive).is_ok());
+    assert!(check_framing(DataBits::Eight, StopBits::Two).is_ok());
+    for &(data_bits, stop_bits) in &[
+        (DataBits::Five, StopBits::Two),
+        (DataBits::Eight, StopBits::OnePointFive),
+    ] {
+        let err = check_framing(data_bits, stop_bits).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_set_parity_flags() {
+    let mut termios: Termios = unsafe { mem::zeroed() };
+    let check_flags = libc::INPCK | libc::IGNPAR | libc::PARMRK;
+
+    set_parity_flags(&mut termios, Parity::Even, ParityCheck::Ignore).unwrap();
+    assert_eq!(termios.c_iflag & check_flags, libc::INPCK);
+    set_parity_flags(&mut termios, Parity::Even, ParityCheck::Drop).unwrap();
+    assert_eq!(termios.c_iflag & check_flags, libc::INPCK | libc::IGNPAR);
+    set_parity_flags(&mut termios, Parity::Odd, ParityCheck::Mark).unwrap();
+    assert_eq!(termios.c_iflag & check_flags, libc::INPCK | libc::PARMRK);
+
+    // Nothing is marked without parity
+    set_parity_flags(&mut termios, Parity::None, ParityCheck::Mark).unwrap();
+    assert_eq!(termios.c_iflag & check_flags, libc::IGNPAR);
+}
+
+#[test]
+fn test_set_mark_space_parity_flags() {
+    let mut termios: Termios = unsafe { mem::zeroed() };
+    let parity_flags = libc::PARENB | libc::PARODD;
+
+    match MARK_SPACE_PARITY {
+        Some(flag) => {
+            set_parity_flags(&mut termios, Parity::Mark, ParityCheck::Ignore).unwrap();
+            assert_eq!(termios.c_cflag & (parity_flags | flag), parity_flags | flag);
+            set_parity_flags(&mut termios, Parity::Space, ParityCheck::Ignore).unwrap();
+            assert_eq!(termios.c_cflag & (parity_flags | flag), libc::PARENB | flag);
+
+            // Switching back to regular parity clears the flag
+            set_parity_flags(&mut termios, Parity::Odd, ParityCheck::Ignore).unwrap();
+            assert_eq!(termios.c_cflag & (parity_flags | flag), parity_flags);
+        }
+        None => {
+            let err = set_parity_flags(&mut termios, Parity::Mark, ParityCheck::Ignore);
+            assert_eq!(err.unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
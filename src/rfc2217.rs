@@ -0,0 +1,1031 @@
+//! A client for serial ports shared over the network with RFC 2217
+//!
+//! RFC 2217 extends Telnet with the COM-PORT-OPTION, which lets a client configure a serial port
+//! on a remote server and follow its modem status lines. Device servers like the Moxa NPort series
+//! and `ser2net` support it.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::cancel::SharedToken;
+use crate::watchdog::RxWatchdog;
+use crate::{
+    AccessMode, CancellationToken, Capabilities, ClearBuffer, DataBits, Error, ErrorKind,
+    FlowControl, Parity, PortBackend, PortErrors, PortStats, Result, SerialPort, SerialPortBuilder,
+    SerialPortSettings, SignalState, StopBits,
+};
+
+/// The prefix of port paths that `SerialPortBuilder::open()` opens with RFC 2217
+const URL_PREFIX: &str = "rfc2217://";
+
+// Telnet commands, from RFC 854
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+// Telnet options
+const BINARY: u8 = 0;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const COM_PORT_OPTION: u8 = 44;
+
+// COM-PORT-OPTION commands sent by the client, the server answers each with the command plus 100
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+const NOTIFY_LINESTATE: u8 = 6;
+const NOTIFY_MODEMSTATE: u8 = 7;
+const SET_LINESTATE_MASK: u8 = 10;
+const SET_MODEMSTATE_MASK: u8 = 11;
+const PURGE_DATA: u8 = 12;
+const SERVER_OFFSET: u8 = 100;
+
+// Values for SET-CONTROL
+const CONTROL_NO_FLOW: u8 = 1;
+const CONTROL_XON_XOFF: u8 = 2;
+const CONTROL_HARDWARE: u8 = 3;
+const CONTROL_BREAK_ON: u8 = 5;
+const CONTROL_BREAK_OFF: u8 = 6;
+const CONTROL_DTR_ON: u8 = 8;
+const CONTROL_DTR_OFF: u8 = 9;
+const CONTROL_RTS_ON: u8 = 11;
+const CONTROL_RTS_OFF: u8 = 12;
+const CONTROL_INBOUND_NO_FLOW: u8 = 14;
+const CONTROL_DTR_INBOUND: u8 = 18;
+const CONTROL_DSR_OUTBOUND: u8 = 19;
+
+// Bits of NOTIFY-MODEMSTATE
+const MODEM_CD: u8 = 0x80;
+const MODEM_RI: u8 = 0x40;
+const MODEM_DSR: u8 = 0x20;
+const MODEM_CTS: u8 = 0x10;
+
+// Bits of NOTIFY-LINESTATE
+const LINE_BREAK: u8 = 0x10;
+const LINE_FRAMING: u8 = 0x08;
+const LINE_PARITY: u8 = 0x04;
+const LINE_OVERRUN: u8 = 0x02;
+
+/// How long to wait for the server to answer negotiations and commands
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The longest a port holds on to the connection while waiting for the server
+///
+/// Clones share the connection, so this bounds how long a blocked read on one of them delays the
+/// others. It's also how often blocked operations check whether they were cancelled.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A serial port on a remote RFC 2217 server
+///
+/// The settings are sent to the server as COM-PORT-OPTION commands and the getters report the
+/// values the server confirmed. The modem status lines are tracked from the server's
+/// notifications, and so are the receive errors returned by `take_errors()`. Servers send those
+/// when the lines change, so the first reads of `signals()` after opening may not reflect the lines
+/// until the server has reported them.
+///
+/// Some operations can't be mapped onto the protocol:
+///
+/// * `set_all()` sends the settings one by one, so a failure can leave only some of them applied.
+/// * `set_xon_xoff_chars()` only accepts the standard XON and XOFF characters.
+/// * `drain()` and `bytes_to_write()` don't account for data still on its way to the server.
+/// * `try_write()` may block while the socket's send buffer is full.
+///
+/// Clones share the connection and take turns reading from it, so a read blocked on one clone
+/// delays the others by up to 20ms at a time.
+#[derive(Debug)]
+pub struct Rfc2217Port {
+    shared: Arc<Shared>,
+    address: String,
+    access_mode: AccessMode,
+    timeout: Duration,
+    inter_byte_write_delay: Duration,
+    inter_byte_timeout: Option<Duration>,
+    /// Whether reads and writes fail with `WouldBlock` instead of waiting. Shared with all clones.
+    nonblocking: Arc<AtomicBool>,
+    stats: PortStats,
+    rx_watchdog: Option<RxWatchdog>,
+    /// Cancels blocking reads and writes once `SerialPort::cancellation_token()` was called.
+    /// Shared with all clones.
+    cancel: SharedToken,
+}
+
+#[derive(Debug)]
+struct Shared {
+    connection: Mutex<Connection>,
+    /// The sending side of the socket, locked so data and commands from clones don't interleave
+    writer: Mutex<TcpStream>,
+}
+
+/// The receiving side of the connection and everything learned from it
+#[derive(Debug)]
+struct Connection {
+    stream: TcpStream,
+    parser: Parser,
+    /// Received serial data that wasn't read yet
+    data: VecDeque<u8>,
+    /// Answers to COM-PORT-OPTION commands that weren't picked up yet, by command
+    replies: VecDeque<(u8, Vec<u8>)>,
+    /// The COM-PORT-OPTION commands waiting for an answer, once for each waiting caller
+    awaiting: Vec<u8>,
+    /// Negotiations sent to the server, which aren't answered again if the server repeats them
+    sent: Vec<(u8, u8)>,
+    /// Answers to the server's negotiations that still need to be sent
+    outgoing: Vec<u8>,
+    /// Whether the server agreed to the COM-PORT-OPTION, once it answered
+    com_port_option: Option<bool>,
+    /// The settings as confirmed by the server, except for the timeout which each port keeps
+    settings: SerialPortSettings,
+    modem_state: u8,
+    errors: PortErrors,
+    closed: bool,
+}
+
+#[derive(Debug)]
+enum Parser {
+    Data,
+    /// After an IAC
+    Command,
+    /// After IAC and DO, DONT, WILL or WONT
+    Negotiation(u8),
+    /// Within a subnegotiation
+    Subnegotiation(Vec<u8>),
+    /// After an IAC within a subnegotiation
+    SubnegotiationCommand(Vec<u8>),
+}
+
+impl Connection {
+    fn parse(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.parser = match mem::replace(&mut self.parser, Parser::Data) {
+                Parser::Data if byte == IAC => Parser::Command,
+                Parser::Data => {
+                    self.data.push_back(byte);
+                    Parser::Data
+                }
+                Parser::Command => match byte {
+                    IAC => {
+                        self.data.push_back(IAC);
+                        Parser::Data
+                    }
+                    DO | DONT | WILL | WONT => Parser::Negotiation(byte),
+                    SB => Parser::Subnegotiation(Vec::new()),
+                    // Other commands, like NOP and GA, carry nothing for a serial port
+                    _ => Parser::Data,
+                },
+                Parser::Negotiation(verb) => {
+                    self.negotiate(verb, byte);
+                    Parser::Data
+                }
+                Parser::Subnegotiation(data) if byte == IAC => Parser::SubnegotiationCommand(data),
+                Parser::Subnegotiation(mut data) => {
+                    data.push(byte);
+                    Parser::Subnegotiation(data)
+                }
+                Parser::SubnegotiationCommand(mut data) => match byte {
+                    IAC => {
+                        data.push(IAC);
+                        Parser::Subnegotiation(data)
+                    }
+                    SE => {
+                        self.subnegotiation(&data);
+                        Parser::Data
+                    }
+                    // A malformed subnegotiation, drop it
+                    _ => Parser::Data,
+                },
+            };
+        }
+    }
+
+    fn negotiate(&mut self, verb: u8, option: u8) {
+        let supported =
+            option == BINARY || option == SUPPRESS_GO_AHEAD || option == COM_PORT_OPTION;
+        let answer = match verb {
+            DO if supported => WILL,
+            DO | DONT => WONT,
+            WILL if supported => DO,
+            _ => DONT,
+        };
+        if option == COM_PORT_OPTION && (verb == DO || verb == DONT) {
+            self.com_port_option = Some(verb == DO);
+        }
+        // Only answer requests that change something, or the two sides could go back and forth
+        if !self.sent.contains(&(answer, option)) {
+            self.sent.retain(|&(_, sent_option)| sent_option != option);
+            self.sent.push((answer, option));
+            self.outgoing.extend_from_slice(&[IAC, answer, option]);
+        }
+    }
+
+    fn subnegotiation(&mut self, data: &[u8]) {
+        if data.len() < 2 || data[0] != COM_PORT_OPTION || data[1] < SERVER_OFFSET {
+            return;
+        }
+        let value = &data[2..];
+        match data[1] - SERVER_OFFSET {
+            NOTIFY_MODEMSTATE if !value.is_empty() => self.modem_state = value[0],
+            NOTIFY_LINESTATE if !value.is_empty() => {
+                let count = |count: &mut u32, flag: u8| {
+                    if value[0] & flag != 0 {
+                        *count = count.saturating_add(1);
+                    }
+                };
+                count(&mut self.errors.parity, LINE_PARITY);
+                count(&mut self.errors.framing, LINE_FRAMING);
+                count(&mut self.errors.overrun, LINE_OVERRUN);
+                count(&mut self.errors.breaks, LINE_BREAK);
+            }
+            // Answers nobody waits for, like repeated or late ones, are dropped, so a server can't
+            // make them pile up
+            command if self.replies_for(command) < self.awaiting_for(command) => {
+                self.replies.push_back((command, value.to_vec()))
+            }
+            _ => (),
+        }
+    }
+
+    fn replies_for(&self, command: u8) -> usize {
+        self.replies
+            .iter()
+            .filter(|&&(replied, _)| replied == command)
+            .count()
+    }
+
+    fn awaiting_for(&self, command: u8) -> usize {
+        self.awaiting
+            .iter()
+            .filter(|&&awaited| awaited == command)
+            .count()
+    }
+
+    /// Stops waiting for an answer to `command`, dropping one that arrived too late to be taken
+    fn stop_awaiting(&mut self, command: u8) {
+        if let Some(i) = self.awaiting.iter().position(|&awaited| awaited == command) {
+            self.awaiting.remove(i);
+        }
+        if self.replies_for(command) > self.awaiting_for(command) {
+            if let Some(i) = self
+                .replies
+                .iter()
+                .position(|&(replied, _)| replied == command)
+            {
+                self.replies.remove(i);
+            }
+        }
+    }
+
+    /// Moves received data into `buf` and returns how much was moved
+    fn take(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.data.len().min(buf.len());
+        for (dst, src) in buf.iter_mut().zip(self.data.drain(..n)) {
+            *dst = src;
+        }
+        n
+    }
+}
+
+impl Rfc2217Port {
+    /// Connects to an RFC 2217 server and configures its port from `builder`
+    ///
+    /// The builder's path is the server's address, as `host:port` with or without a leading
+    /// `rfc2217://`. `SerialPortBuilder::open()` calls this for paths starting with `rfc2217://`,
+    /// so code that takes the path from its configuration can use remote ports unchanged.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` if the server doesn't support the COM-PORT-OPTION.
+    /// * `Io` if the connection fails or isn't accepted in time, or the server doesn't answer in
+    ///   time.
+    pub fn open(builder: &SerialPortBuilder) -> Result<Rfc2217Port> {
+        let address = if builder.path.starts_with(URL_PREFIX) {
+            &builder.path[URL_PREFIX.len()..]
+        } else {
+            &builder.path[..]
+        };
+        let stream = connect(address)?;
+        // Commands and short writes shouldn't wait for more data to fill a segment
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+
+        let mut port = Rfc2217Port {
+            shared: Arc::new(Shared {
+                connection: Mutex::new(Connection {
+                    stream: reader,
+                    parser: Parser::Data,
+                    data: VecDeque::new(),
+                    replies: VecDeque::new(),
+                    awaiting: Vec::new(),
+                    sent: Vec::new(),
+                    outgoing: Vec::new(),
+                    com_port_option: None,
+                    settings: SerialPortSettings::default(),
+                    modem_state: 0,
+                    errors: PortErrors::default(),
+                    closed: false,
+                }),
+                writer: Mutex::new(stream),
+            }),
+            address: address.to_string(),
+            access_mode: builder.access_mode,
+            timeout: builder.timeout,
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            nonblocking: Arc::new(AtomicBool::new(false)),
+            stats: PortStats::default(),
+            rx_watchdog: None,
+            cancel: SharedToken::default(),
+        };
+
+        // Serial data is binary, and go-aheads make no sense for it either
+        let mut offer = Vec::new();
+        {
+            let mut connection = port.connection();
+            for &(verb, option) in &[
+                (WILL, COM_PORT_OPTION),
+                (WILL, BINARY),
+                (DO, BINARY),
+                (WILL, SUPPRESS_GO_AHEAD),
+                (DO, SUPPRESS_GO_AHEAD),
+            ] {
+                connection.sent.push((verb, option));
+                offer.extend_from_slice(&[IAC, verb, option]);
+            }
+        }
+        port.send(&offer, RESPONSE_TIMEOUT)?;
+        let accepted = port
+            .wait_for(RESPONSE_TIMEOUT, |connection| {
+                connection.com_port_option.map(Ok)
+            })
+            .map_err(no_answer)?;
+        if !accepted {
+            return Err(Error::new(
+                ErrorKind::Unknown,
+                "The server doesn't support RFC 2217",
+            ));
+        }
+
+        // Ask for notifications about everything `signals()` and `take_errors()` report. Not all
+        // servers let the masks be changed, which only limits those.
+        let _ = port.command(
+            SET_MODEMSTATE_MASK,
+            &[MODEM_CD | MODEM_RI | MODEM_DSR | MODEM_CTS],
+        );
+        let _ = port.command(
+            SET_LINESTATE_MASK,
+            &[LINE_BREAK | LINE_FRAMING | LINE_PARITY | LINE_OVERRUN],
+        );
+
+        if let Some(dtr) = builder.dtr_on_open {
+            port.write_data_terminal_ready(dtr)?;
+        }
+        if let Some(rts) = builder.rts_on_open {
+            port.write_request_to_send(rts)?;
+        }
+        port.set_all(&builder.settings())?;
+
+        Ok(port)
+    }
+
+    /// Attempts to clone the port
+    ///
+    /// This is the same as `SerialPort::try_clone()` but returns the concrete type instead. The
+    /// clone shares the connection and has its own timeouts and I/O counters.
+    pub fn try_clone_native(&self) -> Result<Rfc2217Port> {
+        Ok(Rfc2217Port {
+            shared: self.shared.clone(),
+            address: self.address.clone(),
+            access_mode: self.access_mode,
+            timeout: self.timeout,
+            inter_byte_write_delay: self.inter_byte_write_delay,
+            inter_byte_timeout: self.inter_byte_timeout,
+            nonblocking: self.nonblocking.clone(),
+            stats: PortStats::default(),
+            rx_watchdog: None,
+            cancel: self.cancel.clone(),
+        })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.shared
+            .connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Sends raw bytes to the server, waiting at most `timeout` if the connection is congested
+    fn send(&self, bytes: &[u8], timeout: Duration) -> io::Result<()> {
+        let mut writer = self
+            .shared
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if timeout > Duration::from_secs(0) {
+            writer.set_write_timeout(Some(timeout))?;
+        } else {
+            writer.set_write_timeout(None)?;
+        }
+        writer.write_all(bytes).map_err(socket_timeout)
+    }
+
+    /// Reads from the socket for at most `timeout` and processes whatever arrived
+    fn receive(&self, connection: &mut Connection, timeout: Duration) -> io::Result<()> {
+        // A zero timeout would make the socket block forever
+        let timeout = timeout.max(Duration::from_millis(1));
+        connection.stream.set_read_timeout(Some(timeout))?;
+        let mut buf = [0u8; 1024];
+        match connection.stream.read(&mut buf) {
+            Ok(0) => connection.closed = true,
+            Ok(n) => connection.parse(&buf[..n]),
+            Err(ref e)
+                if e.kind() == io::ErrorKind::WouldBlock
+                    || e.kind() == io::ErrorKind::TimedOut
+                    || e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+        if !connection.outgoing.is_empty() {
+            let outgoing: Vec<u8> = connection.outgoing.drain(..).collect();
+            self.send(&outgoing, RESPONSE_TIMEOUT)?;
+        }
+        Ok(())
+    }
+
+    /// Processes whatever the server sent without waiting for more
+    fn poll_server(&self) -> io::Result<()> {
+        let mut connection = self.connection();
+        if connection.closed {
+            return Ok(());
+        }
+        self.receive(&mut connection, Duration::from_millis(1))
+    }
+
+    /// Receives from the server until `ready` returns a result, for at most `timeout`
+    fn wait_for<T, F>(&self, timeout: Duration, mut ready: F) -> io::Result<T>
+    where
+        F: FnMut(&mut Connection) -> Option<io::Result<T>>,
+    {
+        let start = Instant::now();
+        let mut received = false;
+        loop {
+            if let Some(token) = self.cancel.get() {
+                token.check()?;
+            }
+            let mut connection = self.connection();
+            if let Some(result) = ready(&mut connection) {
+                return result;
+            }
+            if connection.closed {
+                return Err(closed());
+            }
+            let remaining = timeout
+                .checked_sub(start.elapsed())
+                .unwrap_or_else(|| Duration::from_secs(0));
+            // Poll once even without any time left, so a zero timeout still sees what arrived
+            if remaining == Duration::from_secs(0) && received {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Operation timed out",
+                ));
+            }
+            self.receive(&mut connection, remaining.min(POLL_INTERVAL))?;
+            received = true;
+        }
+    }
+
+    /// Sends a COM-PORT-OPTION command and returns the value the server answered with
+    fn command(&self, command: u8, value: &[u8]) -> Result<Vec<u8>> {
+        self.connection().awaiting.push(command);
+
+        let mut message = vec![IAC, SB, COM_PORT_OPTION, command];
+        for &byte in value {
+            message.push(byte);
+            if byte == IAC {
+                message.push(IAC);
+            }
+        }
+        message.extend_from_slice(&[IAC, SE]);
+        let reply = self.send(&message, RESPONSE_TIMEOUT).and_then(|()| {
+            self.wait_for(RESPONSE_TIMEOUT, |connection| {
+                let i = connection
+                    .replies
+                    .iter()
+                    .position(|&(replied, _)| replied == command)?;
+                connection.replies.remove(i).map(|(_, value)| Ok(value))
+            })
+        });
+        self.connection().stop_awaiting(command);
+        reply.map_err(no_answer).map_err(Error::from)
+    }
+
+    fn set_control(&self, value: u8) -> Result<()> {
+        self.command(SET_CONTROL, &[value]).map(|_| ())
+    }
+
+    fn read_data(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.access_mode.check_read()?;
+        let start = Instant::now();
+        let mut filled = self.wait_for(self.timeout, |connection| {
+            if connection.data.is_empty() {
+                None
+            } else {
+                Some(Ok(connection.take(buf)))
+            }
+        })?;
+
+        if let Some(gap) = self.inter_byte_timeout {
+            // Errors end the read early, as some data has been read already
+            while filled < buf.len() {
+                let remaining = match self.timeout.checked_sub(start.elapsed()) {
+                    Some(remaining) => remaining.min(gap),
+                    None => break,
+                };
+                let rest = &mut buf[filled..];
+                match self.wait_for(remaining, |connection| {
+                    if connection.data.is_empty() {
+                        None
+                    } else {
+                        Some(Ok(connection.take(rest)))
+                    }
+                }) {
+                    Ok(n) => filled += n,
+                    Err(_) => break,
+                }
+            }
+        }
+        Ok(filled)
+    }
+
+    fn write_data(&self, buf: &[u8]) -> io::Result<usize> {
+        self.access_mode.check_write()?;
+        if let Some(token) = self.cancel.get() {
+            token.check()?;
+        }
+        let mut escaped = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            escaped.push(byte);
+            if byte == IAC {
+                escaped.push(IAC);
+            }
+        }
+        self.send(&escaped, self.timeout)?;
+        Ok(buf.len())
+    }
+}
+
+/// The built-in backend for `rfc2217://` paths
+pub(crate) struct Rfc2217Backend;
+
+impl PortBackend for Rfc2217Backend {
+    fn scheme(&self) -> &str {
+        "rfc2217"
+    }
+
+    fn open(&self, path: &str, settings: &SerialPortSettings) -> Result<Box<dyn SerialPort>> {
+        let builder = crate::new(path, settings.baud_rate)
+            .data_bits(settings.data_bits)
+            .flow_control(settings.flow_control)
+            .parity(settings.parity)
+            .stop_bits(settings.stop_bits)
+            .timeout(settings.timeout);
+        Rfc2217Port::open(&builder).map(|p| Box::new(p) as Box<dyn SerialPort>)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CONFIGURE
+            | Capabilities::MODEM_CONTROL
+            | Capabilities::MODEM_STATUS
+            | Capabilities::BREAK
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "The server closed the connection",
+    )
+}
+
+/// Makes socket timeouts, which are `WouldBlock` on Unix, consistent with the other ports
+fn socket_timeout(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::WouldBlock {
+        io::Error::new(io::ErrorKind::TimedOut, "Operation timed out")
+    } else {
+        e
+    }
+}
+
+/// Connects to the first of the addresses `address` resolves to that accepts within
+/// `RESPONSE_TIMEOUT`
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for addr in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, RESPONSE_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The server's address didn't resolve to any addresses",
+        )
+    }))
+}
+
+fn no_answer(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::TimedOut {
+        io::Error::new(io::ErrorKind::TimedOut, "The server didn't answer")
+    } else {
+        e
+    }
+}
+
+fn data_bits_value(data_bits: DataBits) -> u8 {
+    match data_bits {
+        DataBits::Five => 5,
+        DataBits::Six => 6,
+        DataBits::Seven => 7,
+        DataBits::Eight => 8,
+    }
+}
+
+fn parity_value(parity: Parity) -> u8 {
+    match parity {
+        Parity::None => 1,
+        Parity::Odd => 2,
+        Parity::Even => 3,
+        Parity::Mark => 4,
+        Parity::Space => 5,
+    }
+}
+
+fn stop_bits_value(stop_bits: StopBits) -> u8 {
+    match stop_bits {
+        StopBits::One => 1,
+        StopBits::Two => 2,
+        StopBits::OnePointFive => 3,
+    }
+}
+
+impl io::Read for Rfc2217Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_read(buf).map_err(io::Error::from);
+        }
+        let result = self.read_data(buf);
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
+        }
+        result
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let token = self.cancel.get();
+        crate::cancel::read_exact(self, buf, token.as_ref())
+    }
+}
+
+impl io::Write for Rfc2217Port {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_write(buf).map_err(io::Error::from);
+        }
+        if self.inter_byte_write_delay > Duration::from_secs(0) && buf.len() > 1 {
+            for (i, byte) in buf.iter().enumerate() {
+                if i > 0 {
+                    std::thread::sleep(self.inter_byte_write_delay);
+                }
+                match self.write(std::slice::from_ref(byte)) {
+                    Ok(0) => return Ok(i),
+                    Ok(_) => (),
+                    Err(e) if i == 0 => return Err(e),
+                    // Report the bytes that made it out, the error shows up on the next write
+                    Err(_) => return Ok(i),
+                }
+            }
+            return Ok(buf.len());
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let result = self.write_data(buf);
+        self.stats.record_write(&result);
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Nagle's algorithm is disabled, so written data is on its way already
+        Ok(())
+    }
+}
+
+impl SerialPort for Rfc2217Port {
+    fn name(&self) -> Option<String> {
+        Some(format!("{}{}", URL_PREFIX, self.address))
+    }
+
+    fn baud_rate(&self) -> Result<u32> {
+        Ok(self.connection().settings.baud_rate)
+    }
+
+    fn data_bits(&self) -> Result<DataBits> {
+        Ok(self.connection().settings.data_bits)
+    }
+
+    fn flow_control(&self) -> Result<FlowControl> {
+        Ok(self.connection().settings.flow_control)
+    }
+
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        Ok((0x11, 0x13))
+    }
+
+    fn parity(&self) -> Result<Parity> {
+        Ok(self.connection().settings.parity)
+    }
+
+    fn stop_bits(&self) -> Result<StopBits> {
+        Ok(self.connection().settings.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn inter_byte_write_delay(&self) -> Duration {
+        self.inter_byte_write_delay
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.inter_byte_timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        // Zero asks the server for the current baud rate instead
+        if baud_rate == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate"));
+        }
+        let reply = self.command(SET_BAUDRATE, &baud_rate.to_be_bytes())?;
+        let confirmed = match reply[..] {
+            [a, b, c, d] if reply != [0, 0, 0, 0] => u32::from_be_bytes([a, b, c, d]),
+            _ => baud_rate,
+        };
+        self.connection().settings.baud_rate = confirmed;
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
+        let reply = self.command(SET_DATASIZE, &[data_bits_value(data_bits)])?;
+        let confirmed = match reply.first() {
+            Some(5) => DataBits::Five,
+            Some(6) => DataBits::Six,
+            Some(7) => DataBits::Seven,
+            Some(8) => DataBits::Eight,
+            _ => data_bits,
+        };
+        self.connection().settings.data_bits = confirmed;
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
+        // Servers may apply the other values to the outbound direction only, which would leave
+        // DTR flow control on for the inbound one
+        let previous = self.connection().settings.flow_control;
+        if previous == FlowControl::DtrDsr && flow_control != FlowControl::DtrDsr {
+            self.set_control(CONTROL_INBOUND_NO_FLOW)?;
+        }
+        match flow_control {
+            FlowControl::None => self.set_control(CONTROL_NO_FLOW)?,
+            FlowControl::Software => self.set_control(CONTROL_XON_XOFF)?,
+            FlowControl::Hardware => self.set_control(CONTROL_HARDWARE)?,
+            // The protocol sets the two directions separately for DTR/DSR
+            FlowControl::DtrDsr => {
+                self.set_control(CONTROL_DSR_OUTBOUND)?;
+                self.set_control(CONTROL_DTR_INBOUND)?;
+            }
+        }
+        self.connection().settings.flow_control = flow_control;
+        Ok(())
+    }
+
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        crate::check_xon_xoff_chars(xon, xoff)?;
+        if (xon, xoff) != (0x11, 0x13) {
+            return Err(Error::new(
+                ErrorKind::Unknown,
+                "RFC 2217 doesn't support changing the XON/XOFF characters",
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> Result<()> {
+        let reply = self.command(SET_PARITY, &[parity_value(parity)])?;
+        let confirmed = match reply.first() {
+            Some(1) => Parity::None,
+            Some(2) => Parity::Odd,
+            Some(3) => Parity::Even,
+            Some(4) => Parity::Mark,
+            Some(5) => Parity::Space,
+            _ => parity,
+        };
+        self.connection().settings.parity = confirmed;
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
+        let reply = self.command(SET_STOPSIZE, &[stop_bits_value(stop_bits)])?;
+        let confirmed = match reply.first() {
+            Some(1) => StopBits::One,
+            Some(2) => StopBits::Two,
+            Some(3) => StopBits::OnePointFive,
+            _ => stop_bits,
+        };
+        self.connection().settings.stop_bits = confirmed;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        Ok(mem::replace(&mut self.timeout, timeout))
+    }
+
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        self.inter_byte_write_delay = delay;
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inter_byte_timeout = timeout;
+        Ok(())
+    }
+
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        self.rx_watchdog = Some(RxWatchdog::start(timeout, on_stall)?);
+        Ok(())
+    }
+
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        self.set_baud_rate(settings.baud_rate)?;
+        self.set_data_bits(settings.data_bits)?;
+        self.set_parity(settings.parity)?;
+        self.set_stop_bits(settings.stop_bits)?;
+        self.set_flow_control(settings.flow_control)?;
+        self.timeout = settings.timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> Result<()> {
+        self.set_control(if level {
+            CONTROL_RTS_ON
+        } else {
+            CONTROL_RTS_OFF
+        })
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> Result<()> {
+        self.set_control(if level {
+            CONTROL_DTR_ON
+        } else {
+            CONTROL_DTR_OFF
+        })
+    }
+
+    fn read_clear_to_send(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.clear_to_send)
+    }
+
+    fn read_data_set_ready(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.data_set_ready)
+    }
+
+    fn read_ring_indicator(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.ring_indicator)
+    }
+
+    fn read_carrier_detect(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.carrier_detect)
+    }
+
+    fn signals(&self) -> Result<SignalState> {
+        self.poll_server()?;
+        let modem_state = self.connection().modem_state;
+        Ok(SignalState {
+            clear_to_send: modem_state & MODEM_CTS != 0,
+            data_set_ready: modem_state & MODEM_DSR != 0,
+            ring_indicator: modem_state & MODEM_RI != 0,
+            carrier_detect: modem_state & MODEM_CD != 0,
+        })
+    }
+
+    fn bytes_to_read(&self) -> Result<u32> {
+        self.poll_server()?;
+        Ok(self.connection().data.len() as u32)
+    }
+
+    fn bytes_to_write(&self) -> Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> Result<()> {
+        let value = match buffer_to_clear {
+            ClearBuffer::Input => 1,
+            ClearBuffer::Output => 2,
+            ClearBuffer::All => 3,
+        };
+        self.command(PURGE_DATA, &[value])?;
+        if let ClearBuffer::Input | ClearBuffer::All = buffer_to_clear {
+            self.connection().data.clear();
+        }
+        Ok(())
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_break(&self) -> Result<()> {
+        self.set_control(CONTROL_BREAK_ON)
+    }
+
+    fn clear_break(&self) -> Result<()> {
+        self.set_control(CONTROL_BREAK_OFF)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.nonblocking.store(nonblocking, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.write_data(buf)?;
+        self.stats.bytes_written += n as u64;
+        Ok(n)
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.access_mode.check_read()?;
+        self.poll_server()?;
+        let mut connection = self.connection();
+        let n = connection.take(buf);
+        if n == 0 && !buf.is_empty() {
+            if connection.closed {
+                return Err(Error::from(closed()));
+            }
+            return Err(Error::new(
+                ErrorKind::Io(io::ErrorKind::WouldBlock),
+                "No data available",
+            ));
+        }
+        drop(connection);
+        self.stats.bytes_read += n as u64;
+        Ok(n)
+    }
+
+    fn read_is_eof_possible(&self) -> bool {
+        false
+    }
+
+    fn stats(&self) -> PortStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = PortStats::default();
+    }
+
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        self.poll_server()?;
+        let mut connection = self.connection();
+        let errors = connection.errors;
+        connection.errors = PortErrors::default();
+        Ok(errors)
+    }
+
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.cancel.get_or_create()
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
+        match self.try_clone_native() {
+            Ok(p) => Ok(Box::new(p)),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,617 @@
+//! Recording the traffic of a port for debugging
+//!
+//! `TracingPort` wraps a port and passes every chunk of data it reads or writes, and every change
+//! of the RTS, DTR and break lines it makes, to a `TraceSink` along with the time since tracing
+//! started. This captures what went over the line in the field without sniffer hardware:
+//!
+//! * `HexLog` writes a human-readable log with the data in hex.
+//! * `Capture` writes a compact binary capture, which `CaptureReader` reads back for replay.
+//! * Closures taking a `&Record` are sinks too, e.g. to forward the records to a logging crate.
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use serialport::trace::{HexLog, TracingPort};
+//! use serialport::VirtualPort;
+//!
+//! let (port, mut device) = VirtualPort::pair();
+//! let mut port = TracingPort::new(port, HexLog::new(Vec::new()));
+//! port.write_all(b"AT\r").unwrap();
+//! device.write_all(b"OK").unwrap();
+//! let mut response = [0; 2];
+//! port.read_exact(&mut response).unwrap();
+//! ```
+
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cancel::SharedToken;
+use crate::{
+    CancellationToken, ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity, PortErrors,
+    PortStats, Result, SerialPort, SerialPortSettings, SignalState, StopBits,
+};
+
+/// Something that happened on a traced port
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Data was read from the port
+    Rx(Vec<u8>),
+    /// Data was written to the port
+    Tx(Vec<u8>),
+    /// RTS was set to the level
+    RequestToSend(bool),
+    /// DTR was set to the level
+    DataTerminalReady(bool),
+    /// A break was started, or ended for `false`
+    Break(bool),
+}
+
+/// An event along with when it happened
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// The time since tracing started
+    pub time: Duration,
+    /// What happened
+    pub event: Event,
+}
+
+/// A destination for the records of a `TracingPort`
+pub trait TraceSink: Send {
+    /// Takes a record
+    ///
+    /// # Errors
+    ///
+    /// * `Io` if the record couldn't be stored.
+    fn record(&mut self, record: &Record) -> Result<()>;
+}
+
+impl<F: FnMut(&Record) -> Result<()> + Send> TraceSink for F {
+    fn record(&mut self, record: &Record) -> Result<()> {
+        self(record)
+    }
+}
+
+/// A sink writing one line of text per record
+///
+/// Lines start with the time in seconds, followed by the direction and the data in hex, or by the
+/// line that changed:
+///
+/// ```text
+///     0.000012 TX 41 54 0d
+///     0.004521 RX 4f 4b
+///     0.010203 RTS on
+/// ```
+#[derive(Debug)]
+pub struct HexLog<W> {
+    writer: W,
+}
+
+impl<W: io::Write + Send> HexLog<W> {
+    /// Writes the log to `writer`
+    pub fn new(writer: W) -> HexLog<W> {
+        HexLog { writer }
+    }
+
+    /// Returns the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write + Send> TraceSink for HexLog<W> {
+    fn record(&mut self, record: &Record) -> Result<()> {
+        let mut line = format!(
+            "{:5}.{:06}",
+            record.time.as_secs(),
+            record.time.subsec_micros()
+        );
+        let (label, data, level) = match record.event {
+            Event::Rx(ref data) => ("RX", Some(data), false),
+            Event::Tx(ref data) => ("TX", Some(data), false),
+            Event::RequestToSend(level) => ("RTS", None, level),
+            Event::DataTerminalReady(level) => ("DTR", None, level),
+            Event::Break(level) => ("BREAK", None, level),
+        };
+        line.push(' ');
+        line.push_str(label);
+        match data {
+            Some(data) => {
+                for byte in data {
+                    line.push_str(&format!(" {:02x}", byte));
+                }
+            }
+            None => line.push_str(if level { " on" } else { " off" }),
+        }
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The bytes every capture starts with, ending in the version of the format
+const CAPTURE_MAGIC: [u8; 8] = *b"SPTRACE\x01";
+
+const KIND_RX: u8 = 0;
+const KIND_TX: u8 = 1;
+const KIND_RTS: u8 = 2;
+const KIND_DTR: u8 = 3;
+const KIND_BREAK: u8 = 4;
+
+/// A sink writing a binary capture that `CaptureReader` can read back
+///
+/// The capture starts with the 8 bytes `SPTRACE\x01`. Each record follows as the time in
+/// microseconds (`u64`), the kind of event (`u8`: 0 for RX, 1 for TX, 2 for RTS, 3 for DTR and 4
+/// for breaks) and the length of the payload (`u32`), all little-endian, and then the payload:
+/// the data for RX and TX, or a single byte of 0 or 1 for the level of a line.
+#[derive(Debug)]
+pub struct Capture<W> {
+    writer: W,
+    /// Whether the magic bytes were written yet
+    started: bool,
+}
+
+impl<W: io::Write + Send> Capture<W> {
+    /// Writes the capture to `writer`
+    ///
+    /// The header is written along with the first record, so creating a capture can't fail.
+    pub fn new(writer: W) -> Capture<W> {
+        Capture {
+            writer,
+            started: false,
+        }
+    }
+
+    /// Returns the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write + Send> TraceSink for Capture<W> {
+    fn record(&mut self, record: &Record) -> Result<()> {
+        let mut bytes = Vec::new();
+        if !self.started {
+            bytes.extend_from_slice(&CAPTURE_MAGIC);
+        }
+        let micros = record.time.as_secs() * 1_000_000 + u64::from(record.time.subsec_micros());
+        bytes.extend_from_slice(&micros.to_le_bytes());
+        let (kind, payload): (u8, &[u8]) = match record.event {
+            Event::Rx(ref data) => (KIND_RX, data),
+            Event::Tx(ref data) => (KIND_TX, data),
+            Event::RequestToSend(on) => (KIND_RTS, level_payload(on)),
+            Event::DataTerminalReady(on) => (KIND_DTR, level_payload(on)),
+            Event::Break(on) => (KIND_BREAK, level_payload(on)),
+        };
+        bytes.push(kind);
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(payload);
+        self.writer.write_all(&bytes)?;
+        self.started = true;
+        Ok(())
+    }
+}
+
+fn level_payload(on: bool) -> &'static [u8] {
+    if on {
+        &[1]
+    } else {
+        &[0]
+    }
+}
+
+/// Reads back the records of a capture written by `Capture`
+///
+/// This is an iterator over the records, which ends at the end of the capture.
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    reader: R,
+}
+
+impl<R: io::Read> CaptureReader<R> {
+    /// Reads a capture from `reader`, starting with its header
+    ///
+    /// An empty capture, from a traced port without any traffic, has no header and no records.
+    ///
+    /// # Errors
+    ///
+    /// * `Io(InvalidData)` if `reader` doesn't hold a capture of a supported version.
+    /// * `Io` for any other error while reading.
+    pub fn new(mut reader: R) -> Result<CaptureReader<R>> {
+        let mut magic = [0u8; 8];
+        if read_all_or_nothing(&mut reader, &mut magic)? && magic != CAPTURE_MAGIC {
+            return Err(invalid_capture(
+                "Not a capture, or of an unsupported version",
+            ));
+        }
+        Ok(CaptureReader { reader })
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>> {
+        let mut header = [0u8; 13];
+        if !read_all_or_nothing(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+        let mut micros = [0u8; 8];
+        micros.copy_from_slice(&header[..8]);
+        let micros = u64::from_le_bytes(micros);
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&header[9..]);
+        let mut payload = vec![0; u32::from_le_bytes(len) as usize];
+        if !read_all_or_nothing(&mut self.reader, &mut payload)? {
+            return Err(truncated_capture());
+        }
+
+        let level = match payload[..] {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        };
+        let event = match (header[8], level) {
+            (KIND_RX, _) => Event::Rx(payload),
+            (KIND_TX, _) => Event::Tx(payload),
+            (KIND_RTS, Some(level)) => Event::RequestToSend(level),
+            (KIND_DTR, Some(level)) => Event::DataTerminalReady(level),
+            (KIND_BREAK, Some(level)) => Event::Break(level),
+            (KIND_RTS, None) | (KIND_DTR, None) | (KIND_BREAK, None) => {
+                return Err(invalid_capture("Invalid line level in capture"))
+            }
+            _ => return Err(invalid_capture("Unknown event in capture")),
+        };
+        Ok(Some(Record {
+            time: Duration::from_micros(micros),
+            event,
+        }))
+    }
+}
+
+impl<R: io::Read> Iterator for CaptureReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Result<Record>> {
+        match self.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Fills `buf`, returning `false` if `reader` was already at its end
+fn read_all_or_nothing<R: io::Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(truncated_capture()),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+fn invalid_capture(description: &str) -> Error {
+    Error::new(ErrorKind::Io(io::ErrorKind::InvalidData), description)
+}
+
+fn truncated_capture() -> Error {
+    Error::new(
+        ErrorKind::Io(io::ErrorKind::UnexpectedEof),
+        "Capture ends within a record",
+    )
+}
+
+/// The sink shared by a traced port and its clones
+struct Tracer {
+    sink: Box<dyn TraceSink>,
+    start: Instant,
+}
+
+/// A port that records its traffic to a `TraceSink`
+///
+/// Reads and writes are recorded with the data that was actually transferred, so a write the
+/// driver only partly accepted shows up with the accepted bytes. Setting RTS, DTR and the break
+/// state is recorded whether or not the port accepts it. Errors of the sink are ignored, so a full
+/// disk doesn't disturb the traffic being traced.
+///
+/// Clones made with `try_clone()` record to the same sink.
+pub struct TracingPort<P: ?Sized> {
+    tracer: Arc<Mutex<Tracer>>,
+    /// The wrapped port's cancellation token once it was asked for. Shared with all clones.
+    cancel: SharedToken,
+    // Boxed so ports opened with `open()` can be used as they are
+    port: Box<P>,
+}
+
+impl<P: ?Sized> fmt::Debug for TracingPort<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TracingPort").finish()
+    }
+}
+
+impl<P: SerialPort> TracingPort<P> {
+    /// Traces the traffic of `port` to `sink`
+    pub fn new<S: TraceSink + 'static>(port: P, sink: S) -> TracingPort<P> {
+        TracingPort::from_box(Box::new(port), sink)
+    }
+}
+
+impl<P: SerialPort + ?Sized> TracingPort<P> {
+    /// Traces the traffic of a boxed port, like those returned by `open()`, to `sink`
+    pub fn from_box<S: TraceSink + 'static>(port: Box<P>, sink: S) -> TracingPort<P> {
+        TracingPort {
+            tracer: Arc::new(Mutex::new(Tracer {
+                sink: Box::new(sink),
+                start: Instant::now(),
+            })),
+            cancel: SharedToken::default(),
+            port,
+        }
+    }
+
+    /// Returns the port
+    ///
+    /// Traffic through the port itself isn't recorded.
+    pub fn get_ref(&self) -> &P {
+        &self.port
+    }
+
+    /// Returns the port mutably
+    ///
+    /// Traffic through the port itself isn't recorded.
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.port
+    }
+
+    /// Returns the port, ending the tracing
+    pub fn into_inner(self) -> Box<P> {
+        self.port
+    }
+
+    fn record(&self, event: Event) {
+        let mut tracer = self
+            .tracer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let record = Record {
+            time: tracer.start.elapsed(),
+            event,
+        };
+        let _ = tracer.sink.record(&record);
+    }
+}
+
+impl<P: SerialPort + ?Sized> io::Read for TracingPort<P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.port.read(buf)?;
+        if n > 0 {
+            self.record(Event::Rx(buf[..n].to_vec()));
+        }
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let token = self.cancel.get();
+        crate::cancel::read_exact(self, buf, token.as_ref())
+    }
+}
+
+impl<P: SerialPort + ?Sized> io::Write for TracingPort<P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.port.write(buf)?;
+        if n > 0 {
+            self.record(Event::Tx(buf[..n].to_vec()));
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+}
+
+impl<P: SerialPort + ?Sized> SerialPort for TracingPort<P> {
+    fn name(&self) -> Option<String> {
+        self.port.name()
+    }
+
+    fn baud_rate(&self) -> Result<u32> {
+        self.port.baud_rate()
+    }
+
+    fn actual_baud_rate(&self) -> Result<u32> {
+        self.port.actual_baud_rate()
+    }
+
+    fn data_bits(&self) -> Result<DataBits> {
+        self.port.data_bits()
+    }
+
+    fn flow_control(&self) -> Result<FlowControl> {
+        self.port.flow_control()
+    }
+
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        self.port.xon_xoff_chars()
+    }
+
+    fn parity(&self) -> Result<Parity> {
+        self.port.parity()
+    }
+
+    fn stop_bits(&self) -> Result<StopBits> {
+        self.port.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.port.timeout()
+    }
+
+    fn inter_byte_write_delay(&self) -> Duration {
+        self.port.inter_byte_write_delay()
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.port.inter_byte_timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        self.port.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
+        self.port.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
+        self.port.set_flow_control(flow_control)
+    }
+
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        self.port.set_xon_xoff_chars(xon, xoff)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> Result<()> {
+        self.port.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
+        self.port.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        self.port.set_timeout(timeout)
+    }
+
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        self.port.set_inter_byte_write_delay(delay)
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.port.set_inter_byte_timeout(timeout)
+    }
+
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        self.port.set_rx_watchdog(timeout, on_stall)
+    }
+
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        self.port.set_all(settings)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> Result<()> {
+        self.record(Event::RequestToSend(level));
+        self.port.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> Result<()> {
+        self.record(Event::DataTerminalReady(level));
+        self.port.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&mut self) -> Result<bool> {
+        self.port.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> Result<bool> {
+        self.port.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> Result<bool> {
+        self.port.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> Result<bool> {
+        self.port.read_carrier_detect()
+    }
+
+    fn signals(&self) -> Result<SignalState> {
+        self.port.signals()
+    }
+
+    fn bytes_to_read(&self) -> Result<u32> {
+        self.port.bytes_to_read()
+    }
+
+    fn has_data(&self) -> Result<bool> {
+        self.port.has_data()
+    }
+
+    fn bytes_to_write(&self) -> Result<u32> {
+        self.port.bytes_to_write()
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> Result<()> {
+        self.port.clear(buffer_to_clear)
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        self.port.drain()
+    }
+
+    fn set_break(&self) -> Result<()> {
+        self.record(Event::Break(true));
+        self.port.set_break()
+    }
+
+    fn clear_break(&self) -> Result<()> {
+        self.record(Event::Break(false));
+        self.port.clear_break()
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.port.set_nonblocking(nonblocking)
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.port.try_write(buf)?;
+        if n > 0 {
+            self.record(Event::Tx(buf[..n].to_vec()));
+        }
+        Ok(n)
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.port.try_read(buf)?;
+        if n > 0 {
+            self.record(Event::Rx(buf[..n].to_vec()));
+        }
+        Ok(n)
+    }
+
+    fn read_is_eof_possible(&self) -> bool {
+        self.port.read_is_eof_possible()
+    }
+
+    fn stats(&self) -> PortStats {
+        self.port.stats()
+    }
+
+    fn reset_stats(&mut self) {
+        self.port.reset_stats()
+    }
+
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        self.port.take_errors()
+    }
+
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        let token = self.port.cancellation_token()?;
+        self.cancel.set(token.clone());
+        Ok(token)
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
+        Ok(Box::new(TracingPort {
+            tracer: self.tracer.clone(),
+            cancel: self.cancel.clone(),
+            port: self.port.try_clone()?,
+        }))
+    }
+}
@@ -0,0 +1,595 @@
+//! In-process pairs of connected ports for testing without serial hardware
+
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::cancel::SharedToken;
+use crate::watchdog::RxWatchdog;
+use crate::{
+    CancellationToken, ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity, PortErrors,
+    PortStats, Result, SerialPort, SerialPortSettings, SignalState, StopBits,
+};
+
+/// How many bytes can be in flight towards each end of a pair
+const BUFFER_SIZE: usize = 4096;
+
+/// How often blocked operations check whether they were cancelled
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// One end of an in-process pair of connected ports
+///
+/// Bytes written to one end can be read from the other, as with a null-modem cable between two
+/// real ports. This makes it possible to test code using the `SerialPort` trait on machines
+/// without serial hardware, and on every platform. On Unix, `TTYPort::pair()` creates a
+/// pseudo-terminal pair instead, which goes through the kernel's TTY layer.
+///
+/// The modem control lines are wired like `TTYPort::null_modem_pair()`: RTS of each end drives
+/// CTS of the other, and DTR drives both DSR and CD. RI is never asserted. The settings of each end
+/// are stored but don't affect the transfer, so the two ends don't need to match. Breaks have no
+/// effect and no receive errors occur, and written data is available to the other end right away.
+///
+/// Each direction buffers up to 4 KiB, so writes block, or time out, once the other end stops
+/// reading. Once all ports at one end, including clones, have been dropped, writing to the other
+/// end and reading from it after the remaining data fails with `BrokenPipe`.
+#[derive(Debug)]
+pub struct VirtualPort {
+    shared: Arc<Shared>,
+    /// Which end of the pair this port is
+    end: usize,
+    settings: SerialPortSettings,
+    xon_xoff_chars: (u8, u8),
+    inter_byte_write_delay: Duration,
+    inter_byte_timeout: Option<Duration>,
+    /// Whether reads and writes fail with `WouldBlock` instead of waiting. Shared with all clones.
+    nonblocking: Arc<AtomicBool>,
+    stats: PortStats,
+    rx_watchdog: Option<RxWatchdog>,
+    /// Cancels blocking reads and writes once `SerialPort::cancellation_token()` was called.
+    /// Shared with all clones.
+    cancel: SharedToken,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    /// Notified whenever data is written or read, lines change, or a port is dropped
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The data sent towards each end that it hasn't read yet
+    received: [VecDeque<u8>; 2],
+    /// Whether each end asserts RTS
+    request_to_send: [bool; 2],
+    /// Whether each end asserts DTR
+    data_terminal_ready: [bool; 2],
+    /// The number of ports open at each end, counting clones
+    open: [usize; 2],
+}
+
+impl VirtualPort {
+    /// Creates a pair of connected ports
+    ///
+    /// Both ends start with the default `SerialPortSettings`, except for a timeout of 100ms, and
+    /// with RTS and DTR deasserted.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use serialport::VirtualPort;
+    ///
+    /// let (mut a, mut b) = VirtualPort::pair();
+    /// a.write_all(b"ping").unwrap();
+    /// let mut buf = [0u8; 4];
+    /// b.read_exact(&mut buf).unwrap();
+    /// assert_eq!(&buf, b"ping");
+    /// ```
+    pub fn pair() -> (VirtualPort, VirtualPort) {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                open: [1, 1],
+                ..State::default()
+            }),
+            changed: Condvar::new(),
+        });
+        (
+            VirtualPort::new(shared.clone(), 0),
+            VirtualPort::new(shared, 1),
+        )
+    }
+
+    /// Attempts to clone the port
+    ///
+    /// This is the same as `SerialPort::try_clone()` but returns the concrete type instead. The
+    /// clone is another port at the same end of the pair, with its own settings and I/O counters.
+    pub fn try_clone_native(&self) -> Result<VirtualPort> {
+        self.state().open[self.end] += 1;
+        let mut port = VirtualPort::new(self.shared.clone(), self.end);
+        port.settings = self.settings;
+        port.xon_xoff_chars = self.xon_xoff_chars;
+        port.inter_byte_write_delay = self.inter_byte_write_delay;
+        port.inter_byte_timeout = self.inter_byte_timeout;
+        port.nonblocking = self.nonblocking.clone();
+        port.cancel = self.cancel.clone();
+        Ok(port)
+    }
+
+    fn new(shared: Arc<Shared>, end: usize) -> VirtualPort {
+        VirtualPort {
+            shared,
+            end,
+            settings: SerialPortSettings {
+                timeout: Duration::from_millis(100),
+                ..SerialPortSettings::default()
+            },
+            xon_xoff_chars: (0x11, 0x13),
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            nonblocking: Arc::new(AtomicBool::new(false)),
+            stats: PortStats::default(),
+            rx_watchdog: None,
+            cancel: SharedToken::default(),
+        }
+    }
+
+    /// The other end of the pair
+    fn peer(&self) -> usize {
+        1 - self.end
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Waits until `ready` holds for the shared state, for at most `timeout`
+    fn wait<'a, F>(
+        &'a self,
+        mut state: MutexGuard<'a, State>,
+        timeout: Duration,
+        ready: F,
+    ) -> io::Result<MutexGuard<'a, State>>
+    where
+        F: Fn(&State) -> bool,
+    {
+        let start = Instant::now();
+        loop {
+            if let Some(token) = self.cancel.get() {
+                token.check()?;
+            }
+            if ready(&state) {
+                return Ok(state);
+            }
+            let remaining = match timeout.checked_sub(start.elapsed()) {
+                Some(remaining) if remaining > Duration::from_secs(0) => remaining,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Operation timed out",
+                    ))
+                }
+            };
+            // Cancelling doesn't notify the condition variable, so check for it every now and then
+            let remaining = match self.cancel.get() {
+                Some(_) => remaining.min(CANCEL_CHECK_INTERVAL),
+                None => remaining,
+            };
+            state = match self.shared.changed.wait_timeout(state, remaining) {
+                Ok((state, _)) => state,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+    }
+
+    /// Moves received data into `buf` and returns how much was moved
+    fn take(&self, state: &mut State, buf: &mut [u8]) -> usize {
+        let received = &mut state.received[self.end];
+        let n = received.len().min(buf.len());
+        for (dst, src) in buf.iter_mut().zip(received.drain(..n)) {
+            *dst = src;
+        }
+        if n > 0 {
+            // Writers at the other end may be waiting for space
+            self.shared.changed.notify_all();
+        }
+        n
+    }
+
+    /// Moves as much of `buf` as fits towards the other end and returns how much was moved
+    fn put(&self, state: &mut State, buf: &[u8]) -> io::Result<usize> {
+        let peer = self.peer();
+        if state.open[peer] == 0 {
+            return Err(broken_pipe());
+        }
+        let received = &mut state.received[peer];
+        let n = (BUFFER_SIZE - received.len()).min(buf.len());
+        received.extend(&buf[..n]);
+        if n > 0 {
+            self.shared.changed.notify_all();
+        }
+        Ok(n)
+    }
+
+    fn read_blocking(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        let (end, peer) = (self.end, self.peer());
+        let timeout = self.settings.timeout;
+        let mut state = self.wait(self.state(), timeout, |state| {
+            !state.received[end].is_empty() || state.open[peer] == 0
+        })?;
+        let mut filled = self.take(&mut state, buf);
+        if filled == 0 {
+            return Err(broken_pipe());
+        }
+
+        if let Some(gap) = self.inter_byte_timeout {
+            // Errors end the read early, as some data has been read already
+            while filled < buf.len() {
+                let remaining = match timeout.checked_sub(start.elapsed()) {
+                    Some(remaining) => remaining.min(gap),
+                    None => break,
+                };
+                state = match self.wait(state, remaining, |state| !state.received[end].is_empty()) {
+                    Ok(state) => state,
+                    Err(_) => break,
+                };
+                filled += self.take(&mut state, &mut buf[filled..]);
+            }
+        }
+        Ok(filled)
+    }
+
+    fn write_blocking(&self, buf: &[u8]) -> io::Result<usize> {
+        let peer = self.peer();
+        let mut state = self.wait(self.state(), self.settings.timeout, |state| {
+            state.received[peer].len() < BUFFER_SIZE || state.open[peer] == 0
+        })?;
+        self.put(&mut state, buf)
+    }
+}
+
+fn broken_pipe() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "The other end of the pair was closed",
+    )
+}
+
+impl Drop for VirtualPort {
+    fn drop(&mut self) {
+        self.state().open[self.end] -= 1;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl io::Read for VirtualPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_read(buf).map_err(io::Error::from);
+        }
+        let result = self.read_blocking(buf);
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
+        }
+        result
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let token = self.cancel.get();
+        crate::cancel::read_exact(self, buf, token.as_ref())
+    }
+}
+
+impl io::Write for VirtualPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_write(buf).map_err(io::Error::from);
+        }
+        if self.inter_byte_write_delay > Duration::from_secs(0) && buf.len() > 1 {
+            for (i, byte) in buf.iter().enumerate() {
+                if i > 0 {
+                    std::thread::sleep(self.inter_byte_write_delay);
+                }
+                match self.write(std::slice::from_ref(byte)) {
+                    Ok(0) => return Ok(i),
+                    Ok(_) => (),
+                    Err(e) if i == 0 => return Err(e),
+                    // Report the bytes that made it out, the error shows up on the next write
+                    Err(_) => return Ok(i),
+                }
+            }
+            return Ok(buf.len());
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let result = self.write_blocking(buf);
+        self.stats.record_write(&result);
+        result
+    }
+
+    fn write_all(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return crate::write_all_nonblocking(self, buf);
+        }
+        // Writes are only interrupted by cancelling them, so unlike the default implementation
+        // this doesn't retry those
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "Failed to write whole buffer",
+                    ))
+                }
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Written data goes straight to the other end
+        Ok(())
+    }
+}
+
+impl SerialPort for VirtualPort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+
+    fn baud_rate(&self) -> Result<u32> {
+        Ok(self.settings.baud_rate)
+    }
+
+    fn data_bits(&self) -> Result<DataBits> {
+        Ok(self.settings.data_bits)
+    }
+
+    fn flow_control(&self) -> Result<FlowControl> {
+        Ok(self.settings.flow_control)
+    }
+
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        Ok(self.xon_xoff_chars)
+    }
+
+    fn parity(&self) -> Result<Parity> {
+        Ok(self.settings.parity)
+    }
+
+    fn stop_bits(&self) -> Result<StopBits> {
+        Ok(self.settings.stop_bits)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.settings.timeout
+    }
+
+    fn inter_byte_write_delay(&self) -> Duration {
+        self.inter_byte_write_delay
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.inter_byte_timeout
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        if baud_rate == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate"));
+        }
+        self.settings.baud_rate = baud_rate;
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
+        self.settings.data_bits = data_bits;
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
+        self.settings.flow_control = flow_control;
+        Ok(())
+    }
+
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        crate::check_xon_xoff_chars(xon, xoff)?;
+        self.xon_xoff_chars = (xon, xoff);
+        Ok(())
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> Result<()> {
+        self.settings.parity = parity;
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
+        self.settings.stop_bits = stop_bits;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        Ok(mem::replace(&mut self.settings.timeout, timeout))
+    }
+
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        self.inter_byte_write_delay = delay;
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.inter_byte_timeout = timeout;
+        Ok(())
+    }
+
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        self.rx_watchdog = Some(RxWatchdog::start(timeout, on_stall)?);
+        Ok(())
+    }
+
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        if settings.baud_rate == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid baud rate"));
+        }
+        self.settings = *settings;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> Result<()> {
+        self.state().request_to_send[self.end] = level;
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> Result<()> {
+        self.state().data_terminal_ready[self.end] = level;
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.clear_to_send)
+    }
+
+    fn read_data_set_ready(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.data_set_ready)
+    }
+
+    fn read_ring_indicator(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.ring_indicator)
+    }
+
+    fn read_carrier_detect(&mut self) -> Result<bool> {
+        self.signals().map(|signals| signals.carrier_detect)
+    }
+
+    fn signals(&self) -> Result<SignalState> {
+        let state = self.state();
+        let peer = self.peer();
+        Ok(SignalState {
+            clear_to_send: state.request_to_send[peer],
+            data_set_ready: state.data_terminal_ready[peer],
+            ring_indicator: false,
+            carrier_detect: state.data_terminal_ready[peer],
+        })
+    }
+
+    fn bytes_to_read(&self) -> Result<u32> {
+        Ok(self.state().received[self.end].len() as u32)
+    }
+
+    fn bytes_to_write(&self) -> Result<u32> {
+        // Written data goes straight to the other end
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> Result<()> {
+        match buffer_to_clear {
+            ClearBuffer::Input | ClearBuffer::All => {
+                self.state().received[self.end].clear();
+                self.shared.changed.notify_all();
+            }
+            ClearBuffer::Output => (),
+        }
+        Ok(())
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_break(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.nonblocking.store(nonblocking, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state();
+        match self.put(&mut state, buf) {
+            Ok(0) if !buf.is_empty() => Err(Error::new(
+                ErrorKind::Io(io::ErrorKind::WouldBlock),
+                "Driver did not accept any data",
+            )),
+            Ok(n) => {
+                drop(state);
+                self.stats.bytes_written += n as u64;
+                Ok(n)
+            }
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut state = self.state();
+        let n = self.take(&mut state, buf);
+        let result = if n > 0 || buf.is_empty() {
+            Ok(n)
+        } else if state.open[self.peer()] == 0 {
+            Err(Error::from(broken_pipe()))
+        } else {
+            Err(Error::new(
+                ErrorKind::Io(io::ErrorKind::WouldBlock),
+                "No data available",
+            ))
+        };
+        drop(state);
+
+        if let Ok(n) = result {
+            self.stats.bytes_read += n as u64;
+        }
+        result
+    }
+
+    fn read_is_eof_possible(&self) -> bool {
+        false
+    }
+
+    fn stats(&self) -> PortStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = PortStats::default();
+    }
+
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        // The data is never corrupted on the way
+        Ok(PortErrors::default())
+    }
+
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.cancel.get_or_create()
+    }
+
+    fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
+        match self.try_clone_native() {
+            Ok(p) => Ok(Box::new(p)),
+            Err(e) => Err(e),
+        }
+    }
+}
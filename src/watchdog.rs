@@ -0,0 +1,105 @@
+//! Monitoring of received data for stalled links
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Result;
+
+/// A background thread that calls back when no data has been received for a while
+///
+/// The port feeds the watchdog from its read path. The thread is stopped when the watchdog is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct RxWatchdog {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    last_rx: Instant,
+    stopped: bool,
+}
+
+impl RxWatchdog {
+    /// Starts a watchdog that calls `on_stall` for every `timeout` without data
+    pub(crate) fn start(
+        timeout: Duration,
+        mut on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<RxWatchdog> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                last_rx: Instant::now(),
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let monitor = shared.clone();
+        thread::Builder::new()
+            .name("serialport-rx-watchdog".into())
+            .spawn(move || {
+                // The start of the silent period being timed, which moves forward on every stall
+                // so `on_stall` is called again if the silence continues
+                let mut since = monitor.state().last_rx;
+                loop {
+                    let mut state = monitor.state();
+                    while !state.stopped {
+                        if state.last_rx > since {
+                            since = state.last_rx;
+                        }
+                        let elapsed = since.elapsed();
+                        if elapsed >= timeout {
+                            break;
+                        }
+                        state = monitor
+                            .changed
+                            .wait_timeout(state, timeout - elapsed)
+                            .unwrap_or_else(|poisoned| poisoned.into_inner())
+                            .0;
+                    }
+                    if state.stopped {
+                        return;
+                    }
+                    drop(state);
+
+                    on_stall();
+                    since = Instant::now();
+                }
+            })?;
+
+        Ok(RxWatchdog { shared })
+    }
+
+    /// Records the outcome of a read, resetting the watchdog if any data was received
+    pub(crate) fn record_read<E>(&self, result: &std::result::Result<usize, E>) {
+        if let Ok(n) = *result {
+            if n > 0 {
+                self.shared.state().last_rx = Instant::now();
+            }
+        }
+    }
+}
+
+impl Drop for RxWatchdog {
+    fn drop(&mut self) {
+        self.shared.state().stopped = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The guarded values are plain data, so a panic while holding the lock can't leave them in
+        // an inconsistent state.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -1,23 +1,48 @@
+use std::cell::Cell;
 use std::mem::MaybeUninit;
 use std::os::windows::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use std::{io, ptr};
+use std::{io, mem, ptr};
 
 use winapi::shared::minwindef::*;
+use winapi::shared::ntdef::CHAR;
+use winapi::shared::winerror::{ERROR_IO_PENDING, ERROR_OPERATION_ABORTED, WAIT_TIMEOUT};
 use winapi::um::commapi::*;
+use winapi::um::errhandlingapi::GetLastError;
 use winapi::um::fileapi::*;
 use winapi::um::handleapi::*;
+use winapi::um::ioapiset::{CancelIoEx, GetOverlappedResult};
+use winapi::um::minwinbase::OVERLAPPED;
 use winapi::um::processthreadsapi::GetCurrentProcess;
+use winapi::um::synchapi::{CreateEventW, WaitForMultipleObjects};
 use winapi::um::winbase::*;
 use winapi::um::winnt::{
-    DUPLICATE_SAME_ACCESS, FILE_ATTRIBUTE_NORMAL, GENERIC_READ, GENERIC_WRITE, HANDLE,
+    DUPLICATE_SAME_ACCESS, FILE_ATTRIBUTE_NORMAL, GENERIC_READ, GENERIC_WRITE, HANDLE, MAXDWORD,
 };
 
+use crate::cancel::SharedToken;
+use crate::watchdog::RxWatchdog;
 use crate::{
-    ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity, Result, SerialPort,
-    SerialPortBuilder, StopBits,
+    AccessMode, CancellationToken, ClearBuffer, DataBits, Error, ErrorKind, FlowControl, Parity,
+    PortErrors, PortStats, ReadHalf, Result, SerialPort, SerialPortBuilder, SerialPortSettings,
+    SignalMask, SignalState, StopBits, WriteHalf,
 };
 
+// Communication events from `<winbase.h>`, which `winapi` doesn't provide
+const EV_CTS: DWORD = 0x0008;
+const EV_DSR: DWORD = 0x0010;
+const EV_RLSD: DWORD = 0x0020;
+const EV_RING: DWORD = 0x0100;
+
+// Communication errors from `<winbase.h>`, which `winapi` doesn't provide either
+const CE_RXOVER: DWORD = 0x0001;
+const CE_OVERRUN: DWORD = 0x0002;
+const CE_RXPARITY: DWORD = 0x0004;
+const CE_FRAME: DWORD = 0x0008;
+const CE_BREAK: DWORD = 0x0010;
+
 /// A serial port implementation for Windows COM ports
 ///
 /// The port will be closed when the value is dropped. However, this struct
@@ -28,7 +53,21 @@ use crate::{
 pub struct COMPort {
     handle: HANDLE,
     timeout: Duration,
+    access_mode: AccessMode,
     port_name: Option<String>,
+    stats: PortStats,
+    inter_byte_write_delay: Duration,
+    inter_byte_timeout: Option<Duration>,
+    /// Whether this port has asserted a break that still needs to be cleared
+    break_set: AtomicBool,
+    /// The receive errors reported by `ClearCommError()` since the last `take_errors()` call
+    errors: Cell<PortErrors>,
+    rx_watchdog: Option<RxWatchdog>,
+    /// Cancels blocking reads and writes once `SerialPort::cancellation_token()` was called.
+    /// Shared with all clones.
+    cancel: SharedToken,
+    /// Whether reads and writes fail with `WouldBlock` instead of waiting. Shared with all clones.
+    nonblocking: Arc<AtomicBool>,
 }
 
 unsafe impl Send for COMPort {}
@@ -36,33 +75,42 @@ unsafe impl Send for COMPort {}
 impl COMPort {
     /// Opens a COM port as a serial device.
     ///
-    /// `port` should be the name of a COM port, e.g., `COM1`.
+    /// `port` should be the name of a COM port, e.g., `COM1`, or a full device path such as
+    /// `\\.\COM10`. Plain port names are opened through the `\\.\` device namespace, which is
+    /// required for ports above `COM9`.
+    ///
+    /// The port is opened for overlapped I/O, so a read that's waiting for data doesn't hold up
+    /// writes through a clone of the port in another thread.
     ///
     /// If the COM port handle needs to be opened with special flags, use
     /// `from_raw_handle` method to create the `COMPort`. Note that you should
-    /// set the different settings before using the serial port using `set_all`.
+    /// set the different settings before using the serial port using `set_all`. Handles opened
+    /// without `FILE_FLAG_OVERLAPPED` work as well, but their reads and writes are serialized.
     ///
     /// ## Errors
     ///
-    /// * `NoDevice` if the device could not be opened. This could indicate that
-    ///    the device is already in use.
+    /// * `Busy` if the device is already open, in this or any other process.
+    /// * `NoDevice` if the device could not be opened.
     /// * `InvalidInput` if `port` is not a valid device name.
     /// * `Io` for any other I/O error while opening or initializing the device.
     pub fn open(builder: &SerialPortBuilder) -> Result<COMPort> {
-        let mut name = Vec::<u16>::with_capacity(4 + builder.path.len() + 1);
-
-        name.extend(r"\\.\".encode_utf16());
-        name.extend(builder.path.encode_utf16());
+        let mut name: Vec<u16> = device_path(&builder.path).encode_utf16().collect();
         name.push(0);
 
+        let access = match builder.access_mode {
+            AccessMode::ReadOnly => GENERIC_READ,
+            AccessMode::WriteOnly => GENERIC_WRITE,
+            AccessMode::ReadWrite => GENERIC_READ | GENERIC_WRITE,
+        };
+
         let handle = unsafe {
             CreateFileW(
                 name.as_ptr(),
-                GENERIC_READ | GENERIC_WRITE,
+                access,
                 0,
                 ptr::null_mut(),
                 OPEN_EXISTING,
-                FILE_ATTRIBUTE_NORMAL,
+                FILE_ATTRIBUTE_NORMAL | FILE_FLAG_OVERLAPPED,
                 0 as HANDLE,
             )
         };
@@ -70,12 +118,51 @@ impl COMPort {
         if handle != INVALID_HANDLE_VALUE {
             let mut com = COMPort::open_from_raw_handle(handle as RawHandle);
             com.port_name = Some(builder.path.clone());
+            com.access_mode = builder.access_mode;
+
+            // Apply everything, including the line states, with a single `SetCommState()` so a
+            // failure can't leave the port with only some of the settings. Setting the lines with
+            // `EscapeCommFunction()` instead wouldn't last, as `SetCommState()` applies the line
+            // states kept in the DCB. If any setting fails the port is dropped here, which closes
+            // the handle.
+            com.apply_settings(
+                &builder.settings(),
+                builder.dtr_on_open,
+                builder.rts_on_open,
+            )?;
             Ok(com)
         } else {
             Err(super::error::last_os_error())
         }
     }
 
+    /// Returns the exclusivity of the port
+    ///
+    /// COM ports are always opened without sharing, so this always returns `true`. Trying to open
+    /// a port that's already open, in this or any other process, fails with `Busy`.
+    pub fn exclusive(&self) -> bool {
+        true
+    }
+
+    /// Sets the exclusivity of the port
+    ///
+    /// This exists for parity with `TTYPort::set_exclusive()`. COM ports can't be shared, so only
+    /// `true` is accepted.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if `exclusive` is `false`.
+    pub fn set_exclusive(&mut self, exclusive: bool) -> Result<()> {
+        if exclusive {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                "COM ports can't be opened by more than one handle",
+            ))
+        }
+    }
+
     /// Attempts to clone the `SerialPort`. This allow you to write and read simultaneously from the
     /// same serial connection. Please note that if you want a real asynchronous serial port you
     /// should look at [mio-serial](https://crates.io/crates/mio-serial) or
@@ -108,6 +195,15 @@ impl COMPort {
                     handle: cloned_handle,
                     port_name: self.port_name.clone(),
                     timeout: self.timeout,
+                    access_mode: self.access_mode,
+                    stats: PortStats::default(),
+                    inter_byte_write_delay: self.inter_byte_write_delay,
+                    inter_byte_timeout: self.inter_byte_timeout,
+                    break_set: AtomicBool::new(false),
+                    errors: Cell::new(PortErrors::default()),
+                    rx_watchdog: None,
+                    cancel: self.cancel.clone(),
+                    nonblocking: self.nonblocking.clone(),
                 })
             } else {
                 Err(super::error::last_os_error())
@@ -115,6 +211,208 @@ impl COMPort {
         }
     }
 
+    /// Splits the port into a reading and a writing half
+    ///
+    /// The writing half uses a clone of the port made with `try_clone_native()`, so the halves
+    /// can be used from different threads without sharing the port between them. Ports opened
+    /// with `open()` use overlapped I/O, so the halves can read and write at the same time. On a
+    /// handle passed to `from_raw_handle()` without `FILE_FLAG_OVERLAPPED`, Windows serializes
+    /// the I/O, and a write waits for a read that's in progress to complete or time out.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the serial port couldn't be cloned.
+    pub fn split(self) -> Result<(ReadHalf<COMPort>, WriteHalf<COMPort>)> {
+        crate::split(self, COMPort::try_clone_native)
+    }
+
+    /// Starts transmitting a break
+    ///
+    /// The line is held in the break state until `clear_break()` is called. A break that is still
+    /// asserted when the port is dropped is cleared then, so an early return or a panic can't
+    /// leave the line stuck. Each clone of a port keeps track of the breaks it set itself.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while setting the break.
+    pub fn set_break(&self) -> Result<()> {
+        match unsafe { SetCommBreak(self.handle) } {
+            0 => Err(super::error::last_os_error()),
+            _ => {
+                self.break_set.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    }
+
+    /// Stops transmitting a break
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while clearing the break.
+    pub fn clear_break(&self) -> Result<()> {
+        match unsafe { ClearCommBreak(self.handle) } {
+            0 => Err(super::error::last_os_error()),
+            _ => {
+                self.break_set.store(false, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets the byte substituted for characters received with a parity error
+    ///
+    /// This only has an effect when parity checking is enabled. `None` disables substitution, so
+    /// bytes with parity errors are passed through unchanged.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while applying the setting.
+    pub fn set_error_char(&mut self, ch: Option<u8>) -> Result<()> {
+        let mut dcb = self.get_dcb()?;
+        match ch {
+            Some(ch) => {
+                dcb.set_fErrorChar(1);
+                dcb.ErrorChar = ch as CHAR;
+            }
+            None => dcb.set_fErrorChar(0),
+        }
+        self.set_dcb(dcb)
+    }
+
+    /// Returns the input buffer levels at which software flow control sends XON and XOFF
+    ///
+    /// See `set_xon_xoff_limits()` for what the levels mean.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other error while reading the settings.
+    pub fn xon_xoff_limits(&self) -> Result<(u16, u16)> {
+        let dcb = self.get_dcb()?;
+        Ok((dcb.XonLim, dcb.XoffLim))
+    }
+
+    /// Sets the input buffer levels at which software flow control sends XON and XOFF
+    ///
+    /// XON is sent once no more than `xon_limit` bytes are left in the input buffer, and XOFF
+    /// once no more than `xoff_limit` bytes of it are free. These are the `XonLim` and `XoffLim`
+    /// fields of the DCB, which only have an effect with `FlowControl::Software`.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` if the driver rejects the limits, e.g. because they exceed the buffer size.
+    pub fn set_xon_xoff_limits(&mut self, xon_limit: u16, xoff_limit: u16) -> Result<()> {
+        let mut dcb = self.get_dcb()?;
+        dcb.XonLim = xon_limit;
+        dcb.XoffLim = xoff_limit;
+        self.set_dcb(dcb)
+    }
+
+    /// Blocks until one of the modem status lines in `mask` changes, then returns all their levels
+    ///
+    /// This avoids polling the lines to catch an incoming call on RI or a carrier loss on CD. The
+    /// wait uses `WaitCommEvent()` on the port's handle and is cancelled once `timeout` elapses.
+    /// Reads and writes through clones of the port can go on in the meantime, unless the handle was
+    /// passed to `from_raw_handle()` without `FILE_FLAG_OVERLAPPED`. Such handles also block until a
+    /// line changes, regardless of `timeout`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if `mask` is empty.
+    /// * `NoDevice` if the device was disconnected.
+    /// * `TimedOut` if none of the lines changed within `timeout`.
+    /// * `Io` for any other type of I/O error.
+    pub fn wait_for_signal_change(
+        &self,
+        mask: SignalMask,
+        timeout: Duration,
+    ) -> Result<SignalState> {
+        if mask.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "No modem status lines to wait for",
+            ));
+        }
+        let mut events = 0;
+        if mask.contains(SignalMask::CLEAR_TO_SEND) {
+            events |= EV_CTS;
+        }
+        if mask.contains(SignalMask::DATA_SET_READY) {
+            events |= EV_DSR;
+        }
+        if mask.contains(SignalMask::RING_INDICATOR) {
+            events |= EV_RING;
+        }
+        if mask.contains(SignalMask::CARRIER_DETECT) {
+            events |= EV_RLSD;
+        }
+
+        if unsafe { SetCommMask(self.handle, events) } == 0 {
+            return Err(super::error::last_os_error());
+        }
+        let milliseconds = timeout
+            .as_secs()
+            .saturating_mul(1000)
+            .saturating_add(u64::from(timeout.subsec_nanos()) / 1_000_000)
+            .min(u64::from(INFINITE - 1)) as DWORD;
+        let mut occurred: DWORD = 0;
+        let result = match run_overlapped_timeout(
+            self.handle,
+            self.cancel.get().as_ref(),
+            milliseconds,
+            |overlapped| unsafe { WaitCommEvent(self.handle, &mut occurred, overlapped) },
+        ) {
+            Ok(_) => self.signals(),
+            Err(e) => Err(Error::from(e)),
+        };
+        // Don't keep collecting events nobody waits for
+        unsafe { SetCommMask(self.handle, 0) };
+        result
+    }
+
+    /// Returns the directions the port was opened for
+    pub fn access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
+    /// Sets the recommended sizes of the driver's input and output buffers in bytes
+    ///
+    /// The default buffers can be too small for high-rate bursts, leading to overruns. This calls
+    /// `SetupComm()`, so the driver is free to round the sizes or ignore them entirely.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device was disconnected.
+    /// * `Io` for any other I/O error, including the driver rejecting the requested sizes.
+    pub fn set_driver_buffer_sizes(&mut self, rx: u32, tx: u32) -> Result<()> {
+        match unsafe { SetupComm(self.handle, rx as DWORD, tx as DWORD) } {
+            0 => Err(super::error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enables or disables the driver's low latency mode
+    ///
+    /// This exists for parity with `TTYPort::set_low_latency()`. Windows has no API for it, so
+    /// this always fails. The FTDI driver reads its latency timer from the `LatencyTimer` registry
+    /// value under the device's `Device Parameters` key, or the Advanced port settings in the
+    /// Device Manager, which take effect once the device is reconnected.
+    ///
+    /// ## Errors
+    ///
+    /// * `Unknown` as the platform doesn't support low latency mode.
+    pub fn set_low_latency(&mut self, _enabled: bool) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unknown,
+            "Low latency mode is not supported on this platform",
+        ))
+    }
+
     fn escape_comm_function(&mut self, function: DWORD) -> Result<()> {
         match unsafe { EscapeCommFunction(self.handle, function) } {
             0 => Err(super::error::last_os_error()),
@@ -123,22 +421,135 @@ impl COMPort {
     }
 
     fn read_pin(&mut self, pin: DWORD) -> Result<bool> {
+        self.modem_status().map(|status| status & pin != 0)
+    }
+
+    fn modem_status(&self) -> Result<DWORD> {
         let mut status: DWORD = 0;
 
         match unsafe { GetCommModemStatus(self.handle, &mut status) } {
             0 => Err(super::error::last_os_error()),
-            _ => Ok(status & pin != 0),
+            _ => Ok(status),
         }
     }
 
+    /// Wraps `handle` without touching the device
+    ///
+    /// The handle's `COMMTIMEOUTS` are left as they are, so they may not match `timeout` yet.
     fn open_from_raw_handle(handle: RawHandle) -> Self {
         // It is not trivial to get the file path corresponding to a handle.
         // We'll punt and set it `None` here.
         COMPort {
             handle: handle as HANDLE,
             timeout: Duration::from_millis(100),
+            access_mode: AccessMode::ReadWrite,
             port_name: None,
+            stats: PortStats::default(),
+            inter_byte_write_delay: Duration::from_secs(0),
+            inter_byte_timeout: None,
+            break_set: AtomicBool::new(false),
+            errors: Cell::new(PortErrors::default()),
+            rx_watchdog: None,
+            cancel: SharedToken::default(),
+            nonblocking: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn apply_timeout(&mut self, timeout: Duration) -> Result<()> {
+        let mut timeouts = comm_timeouts(timeout);
+        if let Some(gap) = self.inter_byte_timeout {
+            set_interval_timeout(&mut timeouts, gap);
+        }
+
+        if unsafe { SetCommTimeouts(self.handle, &mut timeouts) } == 0 {
+            return Err(super::error::last_os_error());
+        }
+
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn read_overlapped(&self, buf: &mut [u8]) -> io::Result<usize> {
+        run_overlapped(
+            self.handle,
+            self.cancel.get().as_ref(),
+            |overlapped| unsafe {
+                ReadFile(
+                    self.handle,
+                    buf.as_mut_ptr() as LPVOID,
+                    buf.len() as DWORD,
+                    ptr::null_mut(),
+                    overlapped,
+                )
+            },
+        )
+        .map(|len| len as usize)
+    }
+
+    /// Reads into `buf`, cancelling the read after `milliseconds` and returning the bytes read
+    /// until then
+    fn read_overlapped_timeout(&self, buf: &mut [u8], milliseconds: DWORD) -> io::Result<usize> {
+        run_overlapped_partial(
+            self.handle,
+            self.cancel.get().as_ref(),
+            milliseconds,
+            |overlapped| unsafe {
+                ReadFile(
+                    self.handle,
+                    buf.as_mut_ptr() as LPVOID,
+                    buf.len() as DWORD,
+                    ptr::null_mut(),
+                    overlapped,
+                )
+            },
+        )
+        .map(|(len, _)| len as usize)
+    }
+
+    fn write_overlapped(&self, buf: &[u8]) -> io::Result<usize> {
+        self.write_overlapped_timeout(buf, INFINITE)
+            .map(|(len, _)| len)
+    }
+
+    /// Writes `buf`, cancelling the write after `milliseconds`
+    ///
+    /// Returns the number of bytes written, and whether the write was cancelled. Timing the write
+    /// out here rather than with `COMMTIMEOUTS` leaves the timeouts of the device, which all clones
+    /// of the port share, untouched.
+    fn write_overlapped_timeout(
+        &self,
+        buf: &[u8],
+        milliseconds: DWORD,
+    ) -> io::Result<(usize, bool)> {
+        run_overlapped_partial(
+            self.handle,
+            self.cancel.get().as_ref(),
+            milliseconds,
+            |overlapped| unsafe {
+                WriteFile(
+                    self.handle,
+                    buf.as_ptr() as LPVOID,
+                    buf.len() as DWORD,
+                    ptr::null_mut(),
+                    overlapped,
+                )
+            },
+        )
+        .map(|(len, timed_out)| (len as usize, timed_out))
+    }
+
+    /// Returns the port's status, keeping the errors that `ClearCommError()` reports and clears
+    fn clear_comm_error(&self) -> Result<COMSTAT> {
+        let mut errors: DWORD = 0;
+        let mut comstat = MaybeUninit::uninit();
+
+        if unsafe { ClearCommError(self.handle, &mut errors, comstat.as_mut_ptr()) } == 0 {
+            return Err(super::error::last_os_error());
         }
+        let mut recorded = self.errors.get();
+        record_comm_errors(&mut recorded, errors);
+        self.errors.set(recorded);
+        Ok(unsafe { comstat.assume_init() })
     }
 
     fn get_dcb(&self) -> Result<DCB> {
@@ -159,11 +570,310 @@ impl COMPort {
             return Err(super::error::last_os_error());
         }
     }
+
+    /// Applies `settings` along with the DTR and RTS states, if any, in a single DCB
+    fn apply_settings(
+        &mut self,
+        settings: &SerialPortSettings,
+        dtr: Option<bool>,
+        rts: Option<bool>,
+    ) -> Result<()> {
+        let mut dcb = self.get_dcb()?;
+        dcb.BaudRate = settings.baud_rate as DWORD;
+        set_dcb_data_bits(&mut dcb, settings.data_bits);
+        set_dcb_parity(&mut dcb, settings.parity);
+        set_dcb_stop_bits(&mut dcb, settings.stop_bits);
+        set_dcb_flow_control(&mut dcb, settings.flow_control);
+        set_dcb_line_states(&mut dcb, dtr, rts);
+        self.set_dcb(dcb)?;
+        // Unlike `set_timeout()` this doesn't skip unchanged timeouts, as the handle's may not
+        // match the cached one yet
+        self.apply_timeout(settings.timeout)
+    }
+}
+
+/// Returns the path to open for the port named `port`
+///
+/// COM ports above `COM9` can only be opened through the Win32 device namespace, so plain port
+/// names get the `\\.\` prefix, dropping the trailing `:` of the DOS form `COM10:`. Paths that
+/// already start with `\\` or `//`, such as the device interface paths (`\\?\USB#VID_...`) from
+/// SetupAPI, are used as-is.
+fn device_path(port: &str) -> String {
+    if port.starts_with(r"\\") || port.starts_with("//") {
+        port.to_owned()
+    } else {
+        format!(r"\\.\{}", port.trim_end_matches(':'))
+    }
+}
+
+fn set_dcb_data_bits(dcb: &mut DCB, data_bits: DataBits) {
+    dcb.ByteSize = match data_bits {
+        DataBits::Five => 5,
+        DataBits::Six => 6,
+        DataBits::Seven => 7,
+        DataBits::Eight => 8,
+    };
+}
+
+fn set_dcb_parity(dcb: &mut DCB, parity: Parity) {
+    dcb.Parity = match parity {
+        Parity::None => NOPARITY as u8,
+        Parity::Odd => ODDPARITY as u8,
+        Parity::Even => EVENPARITY as u8,
+        Parity::Mark => MARKPARITY,
+        Parity::Space => SPACEPARITY,
+    };
+}
+
+fn set_dcb_stop_bits(dcb: &mut DCB, stop_bits: StopBits) {
+    dcb.StopBits = match stop_bits {
+        StopBits::One => ONESTOPBIT as u8,
+        StopBits::OnePointFive => ONE5STOPBITS,
+        StopBits::Two => TWOSTOPBITS as u8,
+    };
+}
+
+/// Sets the states the DTR and RTS lines are kept in, if any
+///
+/// Lines that are driven by flow control are left to it.
+fn set_dcb_line_states(dcb: &mut DCB, dtr: Option<bool>, rts: Option<bool>) {
+    if let Some(dtr) = dtr {
+        if dcb.fDtrControl() != DTR_CONTROL_HANDSHAKE {
+            dcb.set_fDtrControl(if dtr {
+                DTR_CONTROL_ENABLE
+            } else {
+                DTR_CONTROL_DISABLE
+            });
+        }
+    }
+    if let Some(rts) = rts {
+        let control = dcb.fRtsControl();
+        if control != RTS_CONTROL_HANDSHAKE && control != RTS_CONTROL_TOGGLE {
+            dcb.set_fRtsControl(if rts {
+                RTS_CONTROL_ENABLE
+            } else {
+                RTS_CONTROL_DISABLE
+            });
+        }
+    }
+}
+
+fn set_dcb_flow_control(dcb: &mut DCB, flow_control: FlowControl) {
+    // Leave DTR asserted when switching away from DTR/DSR flow control
+    if dcb.fDtrControl() == DTR_CONTROL_HANDSHAKE {
+        dcb.set_fDtrControl(DTR_CONTROL_ENABLE);
+    }
+    dcb.set_fOutxDsrFlow(0);
+    match flow_control {
+        FlowControl::None => {
+            dcb.set_fOutxCtsFlow(0);
+            dcb.set_fRtsControl(0);
+            dcb.set_fOutX(0);
+            dcb.set_fInX(0);
+        }
+        FlowControl::Software => {
+            dcb.set_fOutxCtsFlow(0);
+            dcb.set_fRtsControl(0);
+            dcb.set_fOutX(1);
+            dcb.set_fInX(1);
+        }
+        FlowControl::Hardware => {
+            dcb.set_fOutxCtsFlow(1);
+            dcb.set_fRtsControl(1);
+            dcb.set_fOutX(0);
+            dcb.set_fInX(0);
+        }
+        FlowControl::DtrDsr => {
+            dcb.set_fOutxCtsFlow(0);
+            dcb.set_fRtsControl(0);
+            dcb.set_fOutxDsrFlow(1);
+            dcb.set_fDtrControl(DTR_CONTROL_HANDSHAKE);
+            dcb.set_fOutX(0);
+            dcb.set_fInX(0);
+        }
+    }
+}
+
+/// Builds the `COMMTIMEOUTS` for a read timeout of `timeout`
+///
+/// With `ReadIntervalTimeout` and `ReadTotalTimeoutMultiplier` set to `MAXDWORD`, a read waits up
+/// to `ReadTotalTimeoutConstant` for the first byte and then returns as soon as any data arrived.
+/// Any other combination either waits for the whole buffer to fill or lets the interval timeout
+/// end the read before a single byte was received. That constant has to be between 0 and
+/// `MAXDWORD` exclusive, so a zero timeout returns immediately with whatever is buffered instead,
+/// which matches the behavior on POSIX platforms.
+fn comm_timeouts(timeout: Duration) -> COMMTIMEOUTS {
+    let milliseconds = timeout
+        .as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(timeout.subsec_nanos()) / 1_000_000);
+
+    if milliseconds == 0 {
+        COMMTIMEOUTS {
+            ReadIntervalTimeout: MAXDWORD,
+            ReadTotalTimeoutMultiplier: 0,
+            ReadTotalTimeoutConstant: 0,
+            WriteTotalTimeoutMultiplier: 0,
+            WriteTotalTimeoutConstant: 0,
+        }
+    } else {
+        COMMTIMEOUTS {
+            ReadIntervalTimeout: MAXDWORD,
+            ReadTotalTimeoutMultiplier: MAXDWORD,
+            ReadTotalTimeoutConstant: milliseconds.min(u64::from(MAXDWORD - 1)) as DWORD,
+            WriteTotalTimeoutMultiplier: 0,
+            WriteTotalTimeoutConstant: 0,
+        }
+    }
+}
+
+/// Makes reads with `timeouts` return once no data has been received for `gap`
+///
+/// The interval timer only starts once the first byte has been received, so the total timeout
+/// still limits the wait for it. A read with a zero timeout already returns right away.
+fn set_interval_timeout(timeouts: &mut COMMTIMEOUTS, gap: Duration) {
+    if timeouts.ReadTotalTimeoutConstant == 0 {
+        return;
+    }
+    let milliseconds = gap
+        .as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(gap.subsec_nanos()) / 1_000_000);
+    // `MAXDWORD` has a special meaning, while 0 disables the interval timeout
+    timeouts.ReadIntervalTimeout = milliseconds.max(1).min(u64::from(MAXDWORD - 1)) as DWORD;
+    timeouts.ReadTotalTimeoutMultiplier = 0;
+}
+
+/// Counts the errors in `errors`, as reported by `ClearCommError()`
+fn record_comm_errors(recorded: &mut PortErrors, errors: DWORD) {
+    let count = |count: &mut u32, flag: DWORD| {
+        if errors & flag != 0 {
+            *count = count.saturating_add(1);
+        }
+    };
+    count(&mut recorded.parity, CE_RXPARITY);
+    count(&mut recorded.framing, CE_FRAME);
+    count(&mut recorded.overrun, CE_OVERRUN);
+    count(&mut recorded.buffer_overrun, CE_RXOVER);
+    count(&mut recorded.breaks, CE_BREAK);
+}
+
+/// Runs an I/O call with its own `OVERLAPPED` event and waits for it to complete
+///
+/// Handles opened with `FILE_FLAG_OVERLAPPED` need an `OVERLAPPED` for every operation, and each
+/// concurrent operation needs a separate event to signal its completion. On other handles the call
+/// just completes before returning. Either way this returns the number of bytes transferred.
+/// Pending operations are cancelled with `CancelIoEx()` once `cancel` is.
+fn run_overlapped<F>(
+    handle: HANDLE,
+    cancel: Option<&CancellationToken>,
+    start: F,
+) -> io::Result<DWORD>
+where
+    F: FnOnce(*mut OVERLAPPED) -> BOOL,
+{
+    run_overlapped_timeout(handle, cancel, INFINITE, start)
+}
+
+/// The same as `run_overlapped()`, cancelling a pending operation after `milliseconds` with a
+/// `TimedOut` error
+fn run_overlapped_timeout<F>(
+    handle: HANDLE,
+    cancel: Option<&CancellationToken>,
+    milliseconds: DWORD,
+    start: F,
+) -> io::Result<DWORD>
+where
+    F: FnOnce(*mut OVERLAPPED) -> BOOL,
+{
+    match run_overlapped_partial(handle, cancel, milliseconds, start)? {
+        (_, true) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Operation timed out",
+        )),
+        (len, false) => Ok(len),
+    }
+}
+
+/// The same as `run_overlapped_timeout()`, except that an operation cancelled after
+/// `milliseconds` returns the number of bytes transferred until then, along with `true`
+///
+/// Handles opened without `FILE_FLAG_OVERLAPPED` complete the operation before returning, so it
+/// can't time out.
+fn run_overlapped_partial<F>(
+    handle: HANDLE,
+    cancel: Option<&CancellationToken>,
+    milliseconds: DWORD,
+    start: F,
+) -> io::Result<(DWORD, bool)>
+where
+    F: FnOnce(*mut OVERLAPPED) -> BOOL,
+{
+    if let Some(token) = cancel {
+        token.check()?;
+    }
+    let event = unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, ptr::null()) };
+    if event.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+    // Setting the low bit keeps the completion from being queued to an I/O completion port the
+    // handle may be associated with, e.g. by `MioCOMPort`, which only expects its own operations
+    overlapped.hEvent = (event as usize | 1) as HANDLE;
+
+    let completed = start(&mut overlapped) != 0;
+    let result = if !completed && unsafe { GetLastError() } != ERROR_IO_PENDING {
+        Err(io::Error::last_os_error())
+    } else {
+        let mut cancelled = false;
+        let mut timed_out = false;
+        if !completed && (cancel.is_some() || milliseconds != INFINITE) {
+            let events = match cancel {
+                Some(token) => vec![event, token.event()],
+                None => vec![event],
+            };
+            let woken = unsafe {
+                WaitForMultipleObjects(events.len() as DWORD, events.as_ptr(), FALSE, milliseconds)
+            };
+            if woken == WAIT_OBJECT_0 + 1 {
+                unsafe { CancelIoEx(handle, &mut overlapped) };
+                cancelled = true;
+            } else if woken == WAIT_TIMEOUT {
+                unsafe { CancelIoEx(handle, &mut overlapped) };
+                timed_out = true;
+            }
+        }
+        // Waiting here also keeps the buffer and `overlapped` alive until the driver is done,
+        // which it may still take a moment to be after cancelling
+        let mut len: DWORD = 0;
+        match unsafe { GetOverlappedResult(handle, &mut overlapped, &mut len, TRUE) } {
+            0 => {
+                let e = io::Error::last_os_error();
+                let aborted = e.raw_os_error() == Some(ERROR_OPERATION_ABORTED as i32);
+                match cancel {
+                    Some(token) if cancelled && aborted => Err(token.error()),
+                    _ if timed_out && aborted => Ok((len, true)),
+                    _ => Err(e),
+                }
+            }
+            // The operation may have completed right before it was cancelled
+            _ => Ok((len, false)),
+        }
+    };
+    unsafe { CloseHandle(event) };
+    result
 }
 
 impl Drop for COMPort {
     fn drop(&mut self) {
+        // The handle was handed over with `into_raw_handle()`
+        if self.handle == INVALID_HANDLE_VALUE {
+            return;
+        }
         unsafe {
+            if self.break_set.load(Ordering::SeqCst) {
+                ClearCommBreak(self.handle);
+            }
             CloseHandle(self.handle);
         }
     }
@@ -175,64 +885,157 @@ impl AsRawHandle for COMPort {
     }
 }
 
+impl IntoRawHandle for COMPort {
+    fn into_raw_handle(mut self) -> RawHandle {
+        // The caller owns the handle from now on, so it's neither closed nor reset when the port
+        // is dropped
+        mem::replace(&mut self.handle, INVALID_HANDLE_VALUE) as RawHandle
+    }
+}
+
 impl FromRawHandle for COMPort {
+    /// Creates a port from a handle to a COM device
+    ///
+    /// The handle's `COMMTIMEOUTS` are replaced by those for the default timeout of 100ms, so
+    /// `timeout()` reports what's in effect. This can't report errors, so should that fail, the
+    /// handle keeps its own timeouts until `set_all()` or `set_timeout()` with a different
+    /// timeout is called.
     unsafe fn from_raw_handle(handle: RawHandle) -> Self {
-        COMPort::open_from_raw_handle(handle)
+        let mut com = COMPort::open_from_raw_handle(handle);
+        let _ = com.apply_timeout(com.timeout);
+        com
     }
 }
 
 impl io::Read for COMPort {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut len: DWORD = 0;
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let token = self.cancel.get();
+        crate::cancel::read_exact(self, buf, token.as_ref())
+    }
 
-        match unsafe {
-            ReadFile(
-                self.handle,
-                buf.as_mut_ptr() as LPVOID,
-                buf.len() as DWORD,
-                &mut len,
-                ptr::null_mut(),
-            )
-        } {
-            0 => Err(io::Error::last_os_error()),
-            _ => {
-                if len != 0 {
-                    Ok(len as usize)
-                } else {
-                    Err(io::Error::new(
-                        io::ErrorKind::TimedOut,
-                        "Operation timed out",
-                    ))
-                }
-            }
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.access_mode.check_read()?;
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_read(buf).map_err(io::Error::from);
+        }
+        let result = match self.read_overlapped(buf) {
+            Ok(0) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Operation timed out",
+            )),
+            result => result,
+        };
+        self.stats.record_read(&result);
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
         }
+        result
     }
 }
 
 impl io::Write for COMPort {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut len: DWORD = 0;
+        self.access_mode.check_write()?;
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return self.try_write(buf).map_err(io::Error::from);
+        }
+        if self.inter_byte_write_delay > Duration::from_secs(0) && buf.len() > 1 {
+            for (i, byte) in buf.iter().enumerate() {
+                if i > 0 {
+                    std::thread::sleep(self.inter_byte_write_delay);
+                }
+                match self.write(std::slice::from_ref(byte)) {
+                    Ok(0) => return Ok(i),
+                    Ok(_) => (),
+                    Err(e) if i == 0 => return Err(e),
+                    // Report the bytes that made it out, the error shows up on the next write
+                    Err(_) => return Ok(i),
+                }
+            }
+            return Ok(buf.len());
+        }
+        let result = self.write_overlapped(buf);
+        self.stats.record_write(&result);
+        result
+    }
 
-        match unsafe {
-            WriteFile(
-                self.handle,
-                buf.as_ptr() as LPVOID,
-                buf.len() as DWORD,
-                &mut len,
-                ptr::null_mut(),
-            )
-        } {
-            0 => Err(io::Error::last_os_error()),
-            _ => Ok(len as usize),
+    /// Writes from `bufs` with a single `WriteFile()`
+    ///
+    /// Windows has no scatter/gather I/O for serial ports, so the buffers are copied into one
+    /// first when more than one of them holds data.
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let mut filled = bufs.iter().filter(|b| !b.is_empty());
+        match (filled.next(), filled.next()) {
+            (None, _) => self.write(&[]),
+            (Some(buf), None) => self.write(buf),
+            (Some(_), Some(_)) => {
+                let mut buf = Vec::with_capacity(bufs.iter().map(|b| b.len()).sum());
+                for b in bufs {
+                    buf.extend_from_slice(b);
+                }
+                self.write(&buf)
+            }
         }
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        match unsafe { FlushFileBuffers(self.handle) } {
-            0 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
+    /// Writes all of `buf`, giving up once the port's timeout has elapsed
+    ///
+    /// Writes normally wait without a timeout on Windows. Here the timeout is applied to the
+    /// whole buffer, which is written in a single call that is cancelled if it expires. Handles
+    /// passed to `from_raw_handle()` that weren't opened for overlapped I/O can't be cancelled, so
+    /// writes to them wait for all of the data.
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.access_mode.check_write()?;
+        if self.nonblocking.load(Ordering::SeqCst) {
+            return crate::write_all_nonblocking(self, buf);
+        }
+        if self.inter_byte_write_delay > Duration::from_secs(0) && buf.len() > 1 {
+            for (i, byte) in buf.iter().enumerate() {
+                if i > 0 {
+                    std::thread::sleep(self.inter_byte_write_delay);
+                }
+                self.write_all(std::slice::from_ref(byte))?;
+            }
+            return Ok(());
+        }
+
+        let milliseconds = self
+            .timeout
+            .as_secs()
+            .saturating_mul(1000)
+            .saturating_add(u64::from(self.timeout.subsec_nanos()) / 1_000_000);
+        // Give a zero timeout the shortest wait possible, like the other platforms
+        let milliseconds = milliseconds.max(1).min(u64::from(INFINITE - 1)) as DWORD;
+
+        match self.write_overlapped_timeout(buf, milliseconds) {
+            Err(e) => {
+                self.stats.write_errors += 1;
+                Err(e)
+            }
+            Ok((len, _)) => {
+                self.stats.bytes_written += len as u64;
+                if len < buf.len() {
+                    self.stats.timeouts += 1;
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "Operation timed out after writing {} of {} bytes",
+                            len,
+                            buf.len()
+                        ),
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
         }
     }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Writes go straight to the driver, so there's nothing buffered to hand over. Waiting for
+        // transmission is done by `SerialPort::drain()`.
+        Ok(())
+    }
 }
 
 impl SerialPort for COMPort {
@@ -244,22 +1047,44 @@ impl SerialPort for COMPort {
         self.timeout
     }
 
-    fn set_timeout(&mut self, timeout: Duration) -> Result<()> {
-        let milliseconds = timeout.as_secs() * 1000 + timeout.subsec_nanos() as u64 / 1_000_000;
+    fn inter_byte_write_delay(&self) -> Duration {
+        self.inter_byte_write_delay
+    }
 
-        let mut timeouts = COMMTIMEOUTS {
-            ReadIntervalTimeout: 0,
-            ReadTotalTimeoutMultiplier: 0,
-            ReadTotalTimeoutConstant: milliseconds as DWORD,
-            WriteTotalTimeoutMultiplier: 0,
-            WriteTotalTimeoutConstant: 0,
-        };
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.inter_byte_timeout
+    }
 
-        if unsafe { SetCommTimeouts(self.handle, &mut timeouts) } == 0 {
-            return Err(super::error::last_os_error());
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        let previous = self.timeout;
+        // Avoid the syscall for code that keeps setting the same timeout
+        if timeout != previous {
+            self.apply_timeout(timeout)?;
         }
+        Ok(previous)
+    }
 
-        self.timeout = timeout;
+    fn set_inter_byte_write_delay(&mut self, delay: Duration) -> Result<()> {
+        self.inter_byte_write_delay = delay;
+        Ok(())
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        let previous = mem::replace(&mut self.inter_byte_timeout, timeout);
+        let current = self.timeout;
+        if let Err(e) = self.apply_timeout(current) {
+            self.inter_byte_timeout = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn set_rx_watchdog(
+        &mut self,
+        timeout: Duration,
+        on_stall: Box<dyn FnMut() + Send>,
+    ) -> Result<()> {
+        self.rx_watchdog = Some(RxWatchdog::start(timeout, on_stall)?);
         Ok(())
     }
 
@@ -295,6 +1120,16 @@ impl SerialPort for COMPort {
         self.read_pin(MS_RLSD_ON)
     }
 
+    fn signals(&self) -> Result<SignalState> {
+        let status = self.modem_status()?;
+        Ok(SignalState {
+            clear_to_send: status & MS_CTS_ON != 0,
+            data_set_ready: status & MS_DSR_ON != 0,
+            ring_indicator: status & MS_RING_ON != 0,
+            carrier_detect: status & MS_RLSD_ON != 0,
+        })
+    }
+
     fn baud_rate(&self) -> Result<u32> {
         let dcb = self.get_dcb()?;
         Ok(dcb.BaudRate as u32)
@@ -314,12 +1149,19 @@ impl SerialPort for COMPort {
         }
     }
 
+    fn xon_xoff_chars(&self) -> Result<(u8, u8)> {
+        let dcb = self.get_dcb()?;
+        Ok((dcb.XonChar as u8, dcb.XoffChar as u8))
+    }
+
     fn parity(&self) -> Result<Parity> {
         let dcb = self.get_dcb()?;
         match dcb.Parity {
             ODDPARITY => Ok(Parity::Odd),
             EVENPARITY => Ok(Parity::Even),
             NOPARITY => Ok(Parity::None),
+            MARKPARITY => Ok(Parity::Mark),
+            SPACEPARITY => Ok(Parity::Space),
             _ => Err(Error::new(
                 ErrorKind::Unknown,
                 "Invalid parity bits setting encountered",
@@ -331,6 +1173,7 @@ impl SerialPort for COMPort {
         let dcb = self.get_dcb()?;
         match dcb.StopBits {
             TWOSTOPBITS => Ok(StopBits::Two),
+            ONE5STOPBITS => Ok(StopBits::OnePointFive),
             ONESTOPBIT => Ok(StopBits::One),
             _ => Err(Error::new(
                 ErrorKind::Unknown,
@@ -341,7 +1184,9 @@ impl SerialPort for COMPort {
 
     fn flow_control(&self) -> Result<FlowControl> {
         let dcb = self.get_dcb()?;
-        if dcb.fOutxCtsFlow() != 0 || dcb.fRtsControl() != 0 {
+        if dcb.fOutxDsrFlow() != 0 || dcb.fDtrControl() == DTR_CONTROL_HANDSHAKE {
+            Ok(FlowControl::DtrDsr)
+        } else if dcb.fOutxCtsFlow() != 0 || dcb.fRtsControl() != 0 {
             Ok(FlowControl::Hardware)
         } else if dcb.fOutX() != 0 || dcb.fInX() != 0 {
             Ok(FlowControl::Software)
@@ -352,6 +1197,8 @@ impl SerialPort for COMPort {
 
     fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
         let mut dcb = self.get_dcb()?;
+        // The `CBR_*` constants for the standard rates are defined as the rates themselves, e.g.
+        // `CBR_9600` is 9600, so drivers that only accept those get them without any mapping.
         dcb.BaudRate = baud_rate as DWORD;
 
         self.set_dcb(dcb)
@@ -359,82 +1206,52 @@ impl SerialPort for COMPort {
 
     fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
         let mut dcb = self.get_dcb()?;
-        dcb.ByteSize = match data_bits {
-            DataBits::Five => 5,
-            DataBits::Six => 6,
-            DataBits::Seven => 7,
-            DataBits::Eight => 8,
-        };
+        set_dcb_data_bits(&mut dcb, data_bits);
+        self.set_dcb(dcb)
+    }
 
+    fn set_xon_xoff_chars(&mut self, xon: u8, xoff: u8) -> Result<()> {
+        crate::check_xon_xoff_chars(xon, xoff)?;
+        let mut dcb = self.get_dcb()?;
+        dcb.XonChar = xon as CHAR;
+        dcb.XoffChar = xoff as CHAR;
         self.set_dcb(dcb)
     }
 
     fn set_parity(&mut self, parity: Parity) -> Result<()> {
         let mut dcb = self.get_dcb()?;
-        dcb.Parity = match parity {
-            Parity::None => NOPARITY as u8,
-            Parity::Odd => ODDPARITY as u8,
-            Parity::Even => EVENPARITY as u8,
-        };
-
+        set_dcb_parity(&mut dcb, parity);
         self.set_dcb(dcb)
     }
 
     fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
         let mut dcb = self.get_dcb()?;
-        dcb.StopBits = match stop_bits {
-            StopBits::One => ONESTOPBIT as u8,
-            StopBits::Two => TWOSTOPBITS as u8,
-        };
-
+        set_dcb_stop_bits(&mut dcb, stop_bits);
         self.set_dcb(dcb)
     }
 
     fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
         let mut dcb = self.get_dcb()?;
-        match flow_control {
-            FlowControl::None => {
-                dcb.set_fOutxCtsFlow(0);
-                dcb.set_fRtsControl(0);
-                dcb.set_fOutX(0);
-                dcb.set_fInX(0);
-            }
-            FlowControl::Software => {
-                dcb.set_fOutxCtsFlow(0);
-                dcb.set_fRtsControl(0);
-                dcb.set_fOutX(1);
-                dcb.set_fInX(1);
-            }
-            FlowControl::Hardware => {
-                dcb.set_fOutxCtsFlow(1);
-                dcb.set_fRtsControl(1);
-                dcb.set_fOutX(0);
-                dcb.set_fInX(0);
-            }
-        }
-
+        set_dcb_flow_control(&mut dcb, flow_control);
         self.set_dcb(dcb)
     }
 
-    fn bytes_to_read(&self) -> Result<u32> {
-        let mut errors: DWORD = 0;
-        let mut comstat = MaybeUninit::uninit();
+    fn set_all(&mut self, settings: &SerialPortSettings) -> Result<()> {
+        self.apply_settings(settings, None, None)
+    }
 
-        if unsafe { ClearCommError(self.handle, &mut errors, comstat.as_mut_ptr()) != 0 } {
-            unsafe { Ok(comstat.assume_init().cbInQue) }
-        } else {
-            Err(super::error::last_os_error())
-        }
+    fn bytes_to_read(&self) -> Result<u32> {
+        self.clear_comm_error().map(|comstat| comstat.cbInQue)
     }
 
     fn bytes_to_write(&self) -> Result<u32> {
-        let mut errors: DWORD = 0;
-        let mut comstat = MaybeUninit::uninit();
+        self.clear_comm_error().map(|comstat| comstat.cbOutQue)
+    }
 
-        if unsafe { ClearCommError(self.handle, &mut errors, comstat.as_mut_ptr()) != 0 } {
-            unsafe { Ok(comstat.assume_init().cbOutQue) }
-        } else {
-            Err(super::error::last_os_error())
+    fn drain(&mut self) -> Result<()> {
+        match unsafe { FlushFileBuffers(self.handle) } {
+            0 => Err(super::error::last_os_error()),
+            _ => Ok(()),
         }
     }
 
@@ -452,6 +1269,83 @@ impl SerialPort for COMPort {
         }
     }
 
+    fn set_break(&self) -> Result<()> {
+        COMPort::set_break(self)
+    }
+
+    fn clear_break(&self) -> Result<()> {
+        COMPort::clear_break(self)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.nonblocking.store(nonblocking, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn try_write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.access_mode.check_write()?;
+
+        // Cancelling the write leaves the timeouts of the device, which clones share, untouched
+        match self.write_overlapped_timeout(buf, 1) {
+            Err(e) => Err(Error::from(e)),
+            Ok((0, _)) if !buf.is_empty() => Err(Error::new(
+                ErrorKind::Io(io::ErrorKind::WouldBlock),
+                "Driver did not accept any data",
+            )),
+            Ok((len, _)) => {
+                self.stats.bytes_written += len as u64;
+                Ok(len)
+            }
+        }
+    }
+
+    fn try_read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.access_mode.check_read()?;
+
+        // Reading no more than is buffered completes right away whatever the device's timeouts,
+        // which clones share, are set to. Should a clone take the data first, the read is
+        // cancelled rather than waiting for more.
+        let available = self.clear_comm_error()?.cbInQue as usize;
+        let len = buf.len().min(available);
+        let result = match self.read_overlapped_timeout(&mut buf[..len], 0) {
+            Err(e) => Err(Error::from(e)),
+            Ok(0) if !buf.is_empty() => Err(Error::new(
+                ErrorKind::Io(io::ErrorKind::WouldBlock),
+                "No data available",
+            )),
+            Ok(len) => {
+                self.stats.bytes_read += len as u64;
+                Ok(len)
+            }
+        };
+
+        if let Some(ref watchdog) = self.rx_watchdog {
+            watchdog.record_read(&result);
+        }
+        result
+    }
+
+    fn read_is_eof_possible(&self) -> bool {
+        false
+    }
+
+    fn stats(&self) -> PortStats {
+        self.stats
+    }
+
+    fn reset_stats(&mut self) {
+        self.stats = PortStats::default();
+    }
+
+    fn cancellation_token(&mut self) -> Result<CancellationToken> {
+        self.cancel.get_or_create()
+    }
+
+    fn take_errors(&mut self) -> Result<PortErrors> {
+        self.clear_comm_error()?;
+        Ok(self.errors.replace(PortErrors::default()))
+    }
+
     fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
         match self.try_clone_native() {
             Ok(p) => Ok(Box::new(p)),
@@ -459,3 +1353,136 @@ impl SerialPort for COMPort {
         }
     }
 }
+
+#[test]
+fn test_device_path() {
+    assert_eq!(device_path("COM9"), r"\\.\COM9");
+    assert_eq!(device_path("COM10"), r"\\.\COM10");
+    assert_eq!(device_path(r"\\.\COM10"), r"\\.\COM10");
+    assert_eq!(device_path(r"\\?\COM10"), r"\\?\COM10");
+    assert_eq!(device_path("com10:"), r"\\.\com10");
+    assert_eq!(device_path("//./COM10"), "//./COM10");
+    let interface = r"\\?\USB#VID_0403&PID_6001#A50285BI#{86e0d1e0-8089-11d0-9ce4-08003e301f73}";
+    assert_eq!(device_path(interface), interface);
+}
+
+#[test]
+fn test_into_raw_handle() {
+    let com = unsafe { COMPort::from_raw_handle(0x1234 as RawHandle) };
+    assert_eq!(com.as_raw_handle(), 0x1234 as RawHandle);
+    // Dropping the port would try to close the made-up handle
+    assert_eq!(com.into_raw_handle(), 0x1234 as RawHandle);
+}
+
+#[test]
+fn test_set_timeout_unchanged() {
+    // Any syscall on a null handle fails, so this only succeeds if the unchanged timeout is skipped
+    let mut com = COMPort::open_from_raw_handle(ptr::null_mut());
+    assert!(com.set_timeout(com.timeout).is_ok());
+    assert!(com.set_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(com.timeout(), Duration::from_millis(100));
+}
+
+#[test]
+fn test_set_dcb_line_states() {
+    let mut dcb: DCB = unsafe { MaybeUninit::zeroed().assume_init() };
+    set_dcb_flow_control(&mut dcb, FlowControl::None);
+    set_dcb_line_states(&mut dcb, Some(false), Some(true));
+    assert_eq!(dcb.fDtrControl(), DTR_CONTROL_DISABLE);
+    assert_eq!(dcb.fRtsControl(), RTS_CONTROL_ENABLE);
+
+    // Lines without a requested state are left alone
+    set_dcb_line_states(&mut dcb, None, None);
+    assert_eq!(dcb.fDtrControl(), DTR_CONTROL_DISABLE);
+    assert_eq!(dcb.fRtsControl(), RTS_CONTROL_ENABLE);
+
+    // Lines driven by flow control are left to it
+    set_dcb_flow_control(&mut dcb, FlowControl::DtrDsr);
+    set_dcb_line_states(&mut dcb, Some(false), None);
+    assert_eq!(dcb.fDtrControl(), DTR_CONTROL_HANDSHAKE);
+    dcb.set_fRtsControl(RTS_CONTROL_HANDSHAKE);
+    set_dcb_line_states(&mut dcb, None, Some(false));
+    assert_eq!(dcb.fRtsControl(), RTS_CONTROL_HANDSHAKE);
+
+    // CTS flow control alone doesn't drive RTS
+    set_dcb_flow_control(&mut dcb, FlowControl::Hardware);
+    set_dcb_line_states(&mut dcb, None, Some(false));
+    assert_eq!(dcb.fRtsControl(), RTS_CONTROL_DISABLE);
+}
+
+#[test]
+fn test_comm_timeouts() {
+    let timeouts = comm_timeouts(Duration::from_secs(30));
+    assert_eq!(timeouts.ReadIntervalTimeout, MAXDWORD);
+    assert_eq!(timeouts.ReadTotalTimeoutMultiplier, MAXDWORD);
+    assert_eq!(timeouts.ReadTotalTimeoutConstant, 30_000);
+
+    let timeouts = comm_timeouts(Duration::from_secs(u64::from(MAXDWORD)));
+    assert_eq!(timeouts.ReadTotalTimeoutConstant, MAXDWORD - 1);
+
+    let timeouts = comm_timeouts(Duration::from_secs(!0));
+    assert_eq!(timeouts.ReadTotalTimeoutConstant, MAXDWORD - 1);
+
+    let timeouts = comm_timeouts(Duration::from_millis(0));
+    assert_eq!(timeouts.ReadIntervalTimeout, MAXDWORD);
+    assert_eq!(timeouts.ReadTotalTimeoutMultiplier, 0);
+    assert_eq!(timeouts.ReadTotalTimeoutConstant, 0);
+}
+
+#[test]
+fn test_record_comm_errors() {
+    let mut errors = PortErrors::default();
+    record_comm_errors(&mut errors, CE_FRAME | CE_RXOVER);
+    record_comm_errors(&mut errors, CE_FRAME);
+    record_comm_errors(&mut errors, 0);
+    assert_eq!(
+        errors,
+        PortErrors {
+            framing: 2,
+            buffer_overrun: 1,
+            ..PortErrors::default()
+        }
+    );
+}
+
+#[test]
+fn test_set_interval_timeout() {
+    let mut timeouts = comm_timeouts(Duration::from_secs(1));
+    set_interval_timeout(&mut timeouts, Duration::from_millis(20));
+    assert_eq!(timeouts.ReadIntervalTimeout, 20);
+    assert_eq!(timeouts.ReadTotalTimeoutMultiplier, 0);
+    assert_eq!(timeouts.ReadTotalTimeoutConstant, 1000);
+
+    let mut timeouts = comm_timeouts(Duration::from_secs(1));
+    set_interval_timeout(&mut timeouts, Duration::from_micros(10));
+    assert_eq!(timeouts.ReadIntervalTimeout, 1);
+
+    let mut timeouts = comm_timeouts(Duration::from_secs(1));
+    set_interval_timeout(&mut timeouts, Duration::from_secs(!0));
+    assert_eq!(timeouts.ReadIntervalTimeout, MAXDWORD - 1);
+
+    let mut timeouts = comm_timeouts(Duration::from_millis(0));
+    set_interval_timeout(&mut timeouts, Duration::from_millis(20));
+    assert_eq!(timeouts.ReadIntervalTimeout, MAXDWORD);
+}
+
+#[test]
+fn test_standard_baud_rates_match_cbr_constants() {
+    let rates = [
+        (CBR_110, 110),
+        (CBR_300, 300),
+        (CBR_600, 600),
+        (CBR_1200, 1200),
+        (CBR_2400, 2400),
+        (CBR_4800, 4800),
+        (CBR_9600, 9600),
+        (CBR_14400, 14400),
+        (CBR_19200, 19200),
+        (CBR_38400, 38400),
+        (CBR_57600, 57600),
+        (CBR_115200, 115_200),
+    ];
+    for &(constant, rate) in rates.iter() {
+        assert_eq!(constant, rate as DWORD);
+    }
+}
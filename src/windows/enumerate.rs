@@ -4,6 +4,7 @@ use std::{mem, ptr};
 use regex::Regex;
 use winapi::shared::guiddef::*;
 use winapi::shared::minwindef::*;
+use winapi::shared::ntddser::GUID_DEVINTERFACE_COMPORT;
 use winapi::shared::ntdef::CHAR;
 use winapi::shared::winerror::*;
 use winapi::um::cguid::GUID_NULL;
@@ -90,8 +91,22 @@ impl PortDevices {
     // Creates PortDevices object which represents the set of devices associated with a particular
     // Ports class (given by `guid`).
     pub fn new(guid: &GUID) -> Self {
+        PortDevices::with_flags(guid, DIGCF_PRESENT)
+    }
+
+    // Creates PortDevices object which represents the set of devices exposing the COM port device
+    // interface. Drivers register this no matter which device class they install into, so it also
+    // finds ports that aren't in the Ports class, such as some CDC-ACM devices and modems.
+    pub fn com_port_interfaces() -> Self {
+        PortDevices::with_flags(
+            &GUID_DEVINTERFACE_COMPORT,
+            DIGCF_PRESENT | DIGCF_DEVICEINTERFACE,
+        )
+    }
+
+    fn with_flags(guid: &GUID, flags: DWORD) -> Self {
         PortDevices {
-            hdi: unsafe { SetupDiGetClassDevsA(guid, ptr::null(), ptr::null_mut(), DIGCF_PRESENT) },
+            hdi: unsafe { SetupDiGetClassDevsA(guid, ptr::null(), ptr::null_mut(), flags) },
             dev_idx: 0,
         }
     }
@@ -237,10 +252,56 @@ impl PortDevice {
                     }
                 }
             }
+
+            // The enumerator at the start of the instance ID tells what the device is attached to,
+            // e.g. BTHENUM\{00001101-0000-1000-8000-00805F9B34FB}_LOCALMFG&0002\7&1B0E2C5C&0&0
+            let enumerator = hardware_id.split('\\').next().unwrap_or("");
+            if enumerator.eq_ignore_ascii_case("BTHENUM") {
+                return SerialPortType::BluetoothPort;
+            } else if enumerator.eq_ignore_ascii_case("PCI") {
+                return SerialPortType::PciPort;
+            }
         }
         SerialPortType::Unknown
     }
 
+    // Retrieves where the device is attached, e.g. `Port_#0001.Hub_#0003` for a USB device.
+    pub fn location(&mut self) -> Option<String> {
+        self.property(SPDRP_LOCATION_INFORMATION)
+    }
+
+    // Retrieves the service name of the device's driver, e.g. `usbser`.
+    pub fn driver(&mut self) -> Option<String> {
+        self.property(SPDRP_SERVICE)
+    }
+
+    // Retrieves the name of the device shown in the Device Manager, e.g. `USB Serial Port (COM7)`.
+    pub fn friendly_name(&mut self) -> Option<String> {
+        self.property(SPDRP_FRIENDLYNAME)
+    }
+
+    // Retrieves the hardware IDs of the device, most specific first.
+    pub fn hardware_ids(&mut self) -> Vec<String> {
+        let mut result_buf = [0u8; 4096];
+        let mut required_size: DWORD = 0;
+        let res = unsafe {
+            SetupDiGetDeviceRegistryPropertyA(
+                self.hdi,
+                &mut self.devinfo_data,
+                SPDRP_HARDWAREID,
+                ptr::null_mut(),
+                result_buf.as_mut_ptr(),
+                result_buf.len() as DWORD,
+                &mut required_size,
+            )
+        };
+        if res == FALSE {
+            return Vec::new();
+        }
+        let len = (required_size as usize).min(result_buf.len());
+        parse_multi_sz(&result_buf[..len])
+    }
+
     // Retrieves a device property and returns it, if it exists. Returns None if the property
     // doesn't exist.
     fn property(&mut self, property_id: DWORD) -> Option<String> {
@@ -271,11 +332,23 @@ impl PortDevice {
     }
 }
 
+/// Splits a `REG_MULTI_SZ` value into its strings
+fn parse_multi_sz(buf: &[u8]) -> Vec<String> {
+    buf.split(|&b| b == 0)
+        .take_while(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
 /// List available serial ports on the system.
 pub fn available_ports() -> Result<Vec<SerialPortInfo>> {
-    let mut ports = Vec::new();
-    for guid in get_ports_guids()? {
-        let port_devices = PortDevices::new(&guid);
+    let mut ports: Vec<SerialPortInfo> = Vec::new();
+    let device_sets = get_ports_guids()?
+        .iter()
+        .map(PortDevices::new)
+        .chain(Some(PortDevices::com_port_interfaces()))
+        .collect::<Vec<_>>();
+    for port_devices in device_sets {
         for mut port_device in port_devices {
             let port_name = port_device.name();
 
@@ -289,12 +362,34 @@ pub fn available_ports() -> Result<Vec<SerialPortInfo>> {
             if port_name.starts_with("LPT") {
                 continue;
             }
+            // Ports in the Ports class usually expose the COM port interface as well
+            if port_name.is_empty() || ports.iter().any(|p| p.port_name == port_name) {
+                continue;
+            }
 
             ports.push(SerialPortInfo {
                 port_name: port_name,
                 port_type: port_device.port_type(),
+                location: port_device.location(),
+                aliases: Vec::new(),
+                driver: port_device.driver(),
+                friendly_name: port_device.friendly_name(),
+                hardware_ids: port_device.hardware_ids(),
             });
         }
     }
     Ok(ports)
 }
+
+#[test]
+fn test_parse_multi_sz() {
+    assert_eq!(
+        parse_multi_sz(b"USB\\VID_0403&PID_6001&REV_0600\0USB\\VID_0403&PID_6001\0\0"),
+        vec![
+            r"USB\VID_0403&PID_6001&REV_0600".to_string(),
+            r"USB\VID_0403&PID_6001".to_string(),
+        ]
+    );
+    assert!(parse_multi_sz(b"\0\0").is_empty());
+    assert!(parse_multi_sz(b"").is_empty());
+}
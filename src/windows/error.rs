@@ -14,12 +14,28 @@ use crate::{Error, ErrorKind};
 pub fn last_os_error() -> Error {
     let errno = errno();
 
-    let kind = match errno {
-        ERROR_FILE_NOT_FOUND | ERROR_PATH_NOT_FOUND | ERROR_ACCESS_DENIED => ErrorKind::NoDevice,
-        _ => ErrorKind::Io(io::ErrorKind::Other),
-    };
+    Error::new(error_kind(errno), error_string(errno).trim()).with_os_error(errno as i32)
+}
 
-    Error::new(kind, error_string(errno).trim())
+/// Maps the `GetLastError()` code `errno` to the closest kind of error
+fn error_kind(errno: u32) -> ErrorKind {
+    match errno {
+        // Opening a port that's already open fails with `ERROR_ACCESS_DENIED`
+        ERROR_ACCESS_DENIED | ERROR_SHARING_VIOLATION | ERROR_BUSY => ErrorKind::Busy,
+        // USB serial adapters that are unplugged while open report one of these
+        ERROR_FILE_NOT_FOUND
+        | ERROR_PATH_NOT_FOUND
+        | ERROR_BAD_COMMAND
+        | ERROR_DEVICE_NOT_CONNECTED
+        | ERROR_DEVICE_REMOVED
+        | ERROR_GEN_FAILURE => ErrorKind::NoDevice,
+        ERROR_INVALID_PARAMETER | ERROR_INVALID_NAME => ErrorKind::InvalidInput,
+        ERROR_SEM_TIMEOUT | WAIT_TIMEOUT => ErrorKind::TimedOut,
+        ERROR_OPERATION_ABORTED => ErrorKind::Io(io::ErrorKind::Interrupted),
+        ERROR_BROKEN_PIPE | ERROR_NO_DATA => ErrorKind::BrokenPipe,
+        ERROR_PRIVILEGE_NOT_HELD => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Io(io::ErrorKind::Other),
+    }
 }
 
 // the rest of this module is borrowed from libstd
@@ -67,3 +83,19 @@ fn error_string(errnum: u32) -> String {
         }
     }
 }
+
+#[test]
+fn test_error_kind() {
+    assert_eq!(error_kind(ERROR_ACCESS_DENIED), ErrorKind::Busy);
+    assert_eq!(error_kind(ERROR_GEN_FAILURE), ErrorKind::NoDevice);
+    assert_eq!(error_kind(ERROR_SEM_TIMEOUT), ErrorKind::TimedOut);
+    assert_eq!(error_kind(ERROR_BROKEN_PIPE), ErrorKind::BrokenPipe);
+    assert_eq!(
+        error_kind(ERROR_PRIVILEGE_NOT_HELD),
+        ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        error_kind(ERROR_NOT_SUPPORTED),
+        ErrorKind::Io(io::ErrorKind::Other)
+    );
+}
@@ -0,0 +1,153 @@
+//! Readiness-based I/O on COM ports with mio 0.7
+
+use std::io;
+use std::os::windows::prelude::*;
+
+use mio07::windows::NamedPipe;
+use mio07::{event, Interest, Registry, Token};
+use winapi::um::commapi::SetCommTimeouts;
+use winapi::um::winbase::COMMTIMEOUTS;
+use winapi::um::winnt::HANDLE;
+
+use crate::windows::COMPort;
+use crate::{Result, SerialPort, SerialPortBuilder};
+
+/// A COM port that can be registered with a mio 0.7 `Poll`, available with the `mio07` feature
+///
+/// Windows reports the completion of overlapped I/O rather than readiness, so reads and writes go
+/// through mio's `NamedPipe`, which works with any handle opened for overlapped I/O and buffers
+/// the data in between. The port has to come from `COMPort::open()`, which opens it with
+/// `FILE_FLAG_OVERLAPPED`, rather than from a handle passed to `from_raw_handle()`.
+///
+/// Reads and writes fail with `WouldBlock` until the port is registered and then whenever it isn't
+/// ready. A read completes as soon as any data has been received, so the port's timeout doesn't
+/// apply. Settings can still be changed through `get_mut()`, except for the timeouts, which reads
+/// through this type rely on.
+#[derive(Debug)]
+pub struct MioCOMPort {
+    // Dropped first, so its pending read is cancelled before the port is closed
+    pipe: NamedPipe,
+    port: COMPort,
+}
+
+impl MioCOMPort {
+    /// Opens the port described by `builder` for use with mio
+    ///
+    /// ## Errors
+    ///
+    /// * Any error that `COMPort::open()` can return.
+    /// * `Io` if the port's timeouts could not be set.
+    pub fn open(builder: &SerialPortBuilder) -> Result<MioCOMPort> {
+        MioCOMPort::from_port(COMPort::open(builder)?)
+    }
+
+    /// Wraps a port opened with `COMPort::open()` for use with mio
+    ///
+    /// ## Errors
+    ///
+    /// * `Io` if the port's timeouts could not be set or its handle could not be duplicated.
+    pub fn from_port(port: COMPort) -> Result<MioCOMPort> {
+        let mut timeouts = readiness_timeouts();
+        if unsafe { SetCommTimeouts(port.as_raw_handle() as HANDLE, &mut timeouts) } == 0 {
+            return Err(super::error::last_os_error());
+        }
+        // The pipe closes its handle when it's dropped, so it gets a handle of its own
+        let handle = port.try_clone_native()?.into_raw_handle();
+        Ok(MioCOMPort {
+            pipe: unsafe { NamedPipe::from_raw_handle(handle) },
+            port,
+        })
+    }
+
+    /// Returns a reference to the underlying port
+    pub fn get_ref(&self) -> &COMPort {
+        &self.port
+    }
+
+    /// Returns a mutable reference to the underlying port
+    ///
+    /// Data read directly from the port isn't seen by reads through this type. Changing the
+    /// port's timeouts breaks those reads.
+    pub fn get_mut(&mut self) -> &mut COMPort {
+        &mut self.port
+    }
+
+    /// Returns the underlying port with its own timeouts applied again
+    ///
+    /// A read through this type that's still pending is cancelled, and any data it received is
+    /// lost.
+    ///
+    /// ## Errors
+    ///
+    /// * `Io` if the port's timeouts could not be restored.
+    pub fn into_inner(self) -> Result<COMPort> {
+        let MioCOMPort { pipe, mut port } = self;
+        drop(pipe);
+        let timeout = port.timeout();
+        port.apply_timeout(timeout)?;
+        Ok(port)
+    }
+}
+
+impl io::Read for MioCOMPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut self.pipe, buf)
+    }
+}
+
+impl io::Write for MioCOMPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Write::write(&mut self.pipe, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::Write::flush(&mut self.pipe)
+    }
+}
+
+impl event::Source for MioCOMPort {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.pipe.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.pipe.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.pipe.deregister(registry)
+    }
+}
+
+/// Returns timeouts that make a read wait for the first byte without a time limit, and return
+/// once no more data has been received for 1ms
+///
+/// `NamedPipe` takes a read that completes without any data for the end of the stream, so the
+/// total timeout has to stay disabled.
+fn readiness_timeouts() -> COMMTIMEOUTS {
+    COMMTIMEOUTS {
+        ReadIntervalTimeout: 1,
+        ReadTotalTimeoutMultiplier: 0,
+        ReadTotalTimeoutConstant: 0,
+        WriteTotalTimeoutMultiplier: 0,
+        WriteTotalTimeoutConstant: 0,
+    }
+}
+
+#[test]
+fn test_readiness_timeouts() {
+    let timeouts = readiness_timeouts();
+    assert_eq!(timeouts.ReadTotalTimeoutMultiplier, 0);
+    assert_eq!(timeouts.ReadTotalTimeoutConstant, 0);
+    assert_eq!(timeouts.ReadIntervalTimeout, 1);
+}
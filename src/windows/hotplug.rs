@@ -0,0 +1,154 @@
+//! Detection of serial devices being added or removed through device notifications
+
+use std::sync::mpsc;
+use std::{mem, ptr, thread};
+
+use winapi::shared::minwindef::{LPARAM, LPVOID, LRESULT, TRUE, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::ERROR_CLASS_ALREADY_EXISTS;
+use winapi::um::dbt::{
+    DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_DEVICEINTERFACE,
+    DEV_BROADCAST_DEVICEINTERFACE_W,
+};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::*;
+
+use super::error;
+use crate::{Error, ErrorKind, Result};
+
+/// Posted to the notification window when a device was added or removed
+const WM_PORTS_CHANGED: UINT = WM_APP;
+/// Posted to the notification window to stop the thread
+const WM_STOP: UINT = WM_APP + 1;
+
+/// A background thread that calls back whenever a device is added or removed
+///
+/// Notifications are delivered to a message-only window owned by the thread. The thread is
+/// stopped when this is dropped.
+#[derive(Debug)]
+pub(crate) struct ChangeMonitor {
+    /// The notification window, which can't be sent between threads as an `HWND`
+    window: usize,
+}
+
+impl ChangeMonitor {
+    /// Starts calling `on_change` for every change until it returns `false`
+    pub(crate) fn start(mut on_change: Box<dyn FnMut() -> bool + Send>) -> Result<Self> {
+        let (ready, started) = mpsc::sync_channel(1);
+        thread::Builder::new()
+            .name("serialport-hotplug".into())
+            .spawn(move || unsafe {
+                let (window, notification) = match create_window() {
+                    Ok(created) => created,
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready.send(Ok(window as usize));
+
+                let mut msg: MSG = mem::zeroed();
+                while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+                    match msg.message {
+                        WM_STOP => break,
+                        WM_PORTS_CHANGED => {
+                            if !on_change() {
+                                break;
+                            }
+                        }
+                        _ => {
+                            DispatchMessageW(&msg);
+                        }
+                    }
+                }
+
+                UnregisterDeviceNotification(notification);
+                DestroyWindow(window);
+            })?;
+
+        match started.recv() {
+            Ok(result) => result.map(|window| ChangeMonitor { window }),
+            Err(_) => Err(Error::new(
+                ErrorKind::Unknown,
+                "The port watcher thread exited unexpectedly",
+            )),
+        }
+    }
+}
+
+impl Drop for ChangeMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            PostMessageW(self.window as HWND, WM_STOP, 0, 0);
+        }
+    }
+}
+
+/// Creates a message-only window that receives notifications for all device interfaces
+unsafe fn create_window() -> Result<(HWND, LPVOID)> {
+    let class: Vec<u16> = "serialport-hotplug\0".encode_utf16().collect();
+    let instance = GetModuleHandleW(ptr::null());
+
+    let mut window_class: WNDCLASSEXW = mem::zeroed();
+    window_class.cbSize = mem::size_of::<WNDCLASSEXW>() as UINT;
+    window_class.lpfnWndProc = Some(window_proc);
+    window_class.hInstance = instance;
+    window_class.lpszClassName = class.as_ptr();
+    // Every watcher uses the same class, so it may have been registered already
+    if RegisterClassExW(&window_class) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+        return Err(error::last_os_error());
+    }
+
+    let window = CreateWindowExW(
+        0,
+        class.as_ptr(),
+        ptr::null(),
+        0,
+        0,
+        0,
+        0,
+        0,
+        HWND_MESSAGE,
+        ptr::null_mut(),
+        instance,
+        ptr::null_mut(),
+    );
+    if window.is_null() {
+        return Err(error::last_os_error());
+    }
+
+    // USB serial drivers don't reliably register the COM port interface class, so any device
+    // interface coming or going triggers a check of the ports
+    let mut filter: DEV_BROADCAST_DEVICEINTERFACE_W = mem::zeroed();
+    filter.dbcc_size = mem::size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32;
+    filter.dbcc_devicetype = DBT_DEVTYP_DEVICEINTERFACE;
+    let notification = RegisterDeviceNotificationW(
+        window as LPVOID,
+        &mut filter as *mut _ as LPVOID,
+        DEVICE_NOTIFY_WINDOW_HANDLE | DEVICE_NOTIFY_ALL_INTERFACE_CLASSES,
+    );
+    if notification.is_null() {
+        let e = error::last_os_error();
+        DestroyWindow(window);
+        return Err(e);
+    }
+
+    Ok((window, notification))
+}
+
+unsafe extern "system" fn window_proc(
+    window: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_DEVICECHANGE {
+        if wparam == DBT_DEVICEARRIVAL || wparam == DBT_DEVICEREMOVECOMPLETE {
+            // Notifications are sent rather than posted, so hand them over to the message loop
+            PostMessageW(window, WM_PORTS_CHANGED, 0, 0);
+        }
+        return TRUE as LRESULT;
+    }
+    DefWindowProcW(window, msg, wparam, lparam)
+}
@@ -1,6 +1,16 @@
 pub use self::com::*;
 pub use self::enumerate::*;
+#[cfg(feature = "mio07")]
+pub use self::evented::MioCOMPort;
+#[cfg(feature = "hotplug")]
+pub(crate) use self::hotplug::ChangeMonitor;
+pub use self::poll::poll;
 
 mod com;
 mod enumerate;
 mod error;
+#[cfg(feature = "mio07")]
+mod evented;
+#[cfg(feature = "hotplug")]
+mod hotplug;
+mod poll;
@@ -0,0 +1,153 @@
+//! Waiting for data on several ports at once
+
+use std::os::windows::prelude::*;
+use std::time::Duration;
+use std::{mem, ptr};
+
+use winapi::shared::minwindef::{DWORD, FALSE, TRUE};
+use winapi::shared::winerror::ERROR_IO_PENDING;
+use winapi::um::commapi::{SetCommMask, WaitCommEvent};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::ioapiset::{CancelIoEx, GetOverlappedResult};
+use winapi::um::minwinbase::OVERLAPPED;
+use winapi::um::synchapi::{CreateEventW, WaitForMultipleObjects};
+use winapi::um::winbase::{INFINITE, WAIT_FAILED};
+use winapi::um::winnt::{HANDLE, MAXIMUM_WAIT_OBJECTS};
+
+use crate::windows::COMPort;
+use crate::{Error, ErrorKind, Result, SerialPort};
+
+// The communication event for received data from `<winbase.h>`, which `winapi` doesn't provide
+const EV_RXCHAR: DWORD = 0x0001;
+
+/// Waits until at least one of `ports` has data to read
+///
+/// Returns the indices into `ports` of all ports with received data, in ascending order, or an
+/// empty list if `timeout` elapsed first. A port whose status can't be read, e.g. because it was
+/// disconnected, is included too, so the error is reported by the next read. This allows a single
+/// thread to serve many ports.
+///
+/// This waits for received data with `WaitCommEvent()`, which needs the ports to be opened for
+/// overlapped I/O as `COMPort::open()` does. It replaces the event mask of each port while
+/// waiting, so it must not be used at the same time as `COMPort::wait_for_signal_change()` on the
+/// same port, which would also make it return an empty list early.
+///
+/// ## Errors
+///
+/// * `InvalidInput` if `ports` is empty or holds more than 64 ports.
+/// * `Io` for any other type of I/O error.
+pub fn poll(ports: &[&COMPort], timeout: Duration) -> Result<Vec<usize>> {
+    if ports.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "No ports to wait for"));
+    }
+    if ports.len() > MAXIMUM_WAIT_OBJECTS as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Can't wait for more than 64 ports at once",
+        ));
+    }
+    let ready = readable(ports);
+    if !ready.is_empty() {
+        return Ok(ready);
+    }
+
+    let mut waits = Vec::with_capacity(ports.len());
+    for port in ports {
+        waits.push(RxWait::start(port.as_raw_handle() as HANDLE)?);
+    }
+    // Data that arrived before a wait started doesn't end it
+    if readable(ports).is_empty() {
+        let events: Vec<HANDLE> = waits.iter().map(|w| w.event).collect();
+        let milliseconds = timeout
+            .as_secs()
+            .saturating_mul(1000)
+            .saturating_add(u64::from(timeout.subsec_millis()));
+        let milliseconds = milliseconds.min(u64::from(INFINITE - 1)) as DWORD;
+        let woken = unsafe {
+            WaitForMultipleObjects(events.len() as DWORD, events.as_ptr(), FALSE, milliseconds)
+        };
+        if woken == WAIT_FAILED {
+            return Err(super::error::last_os_error());
+        }
+    }
+    // Stop the waits that are still pending before checking the ports for the last time
+    drop(waits);
+    Ok(readable(ports))
+}
+
+/// Returns the indices of the ports with received data or a status that can't be read
+fn readable(ports: &[&COMPort]) -> Vec<usize> {
+    ports
+        .iter()
+        .enumerate()
+        .filter(|&(_, port)| match port.bytes_to_read() {
+            Ok(n) => n > 0,
+            Err(_) => true,
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// A `WaitCommEvent()` for received data on one port
+///
+/// Dropping it cancels the wait and clears the port's event mask.
+struct RxWait {
+    handle: HANDLE,
+    event: HANDLE,
+    // Boxed so the driver's pointers to it stay valid while the wait is pending
+    state: Box<WaitState>,
+    pending: bool,
+}
+
+struct WaitState {
+    overlapped: OVERLAPPED,
+    events: DWORD,
+}
+
+impl RxWait {
+    fn start(handle: HANDLE) -> Result<RxWait> {
+        let event = unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, ptr::null()) };
+        if event.is_null() {
+            return Err(super::error::last_os_error());
+        }
+        let mut wait = RxWait {
+            handle,
+            event,
+            state: Box::new(WaitState {
+                overlapped: unsafe { mem::zeroed() },
+                events: 0,
+            }),
+            pending: false,
+        };
+        // Keeps the completion out of any I/O completion port the handle is associated with
+        wait.state.overlapped.hEvent = (event as usize | 1) as HANDLE;
+
+        if unsafe { SetCommMask(handle, EV_RXCHAR) } == 0 {
+            return Err(super::error::last_os_error());
+        }
+        let state = &mut *wait.state;
+        if unsafe { WaitCommEvent(handle, &mut state.events, &mut state.overlapped) } == 0 {
+            if unsafe { GetLastError() } != ERROR_IO_PENDING {
+                return Err(super::error::last_os_error());
+            }
+            wait.pending = true;
+        }
+        Ok(wait)
+    }
+}
+
+impl Drop for RxWait {
+    fn drop(&mut self) {
+        unsafe {
+            if self.pending {
+                CancelIoEx(self.handle, &mut self.state.overlapped);
+                // Wait for the driver to let go of `state` before it's freed
+                let mut len: DWORD = 0;
+                GetOverlappedResult(self.handle, &mut self.state.overlapped, &mut len, TRUE);
+            }
+            SetCommMask(self.handle, 0);
+            CloseHandle(self.event);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "actor")]
+extern crate serialport;
+
+use serialport::actor::{Actor, Control};
+use serialport::mock::MockSerialPort;
+use serialport::{Error, ErrorKind, SerialPort, VirtualPort};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+// Test that data passes through the channels and that control commands reach the port
+#[test]
+fn test_actor_data_and_control() {
+    let (mut port, mut device) = VirtualPort::pair();
+    port.set_timeout(Duration::from_millis(500)).unwrap();
+    device.set_timeout(Duration::from_secs(1)).unwrap();
+    let actor = Actor::spawn(Box::new(port)).unwrap();
+
+    let writer = actor.writer();
+    writer.send(b"hello ".to_vec()).unwrap();
+    writer.send(b"device".to_vec()).unwrap();
+    let mut request = [0; 12];
+    device.read_exact(&mut request).unwrap();
+    assert_eq!(&request, b"hello device");
+
+    device.write_all(b"hi").unwrap();
+    let response = actor
+        .receiver()
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(response, b"hi");
+
+    // RTS is wired to the CTS of the other end
+    actor.control(Control::WriteRequestToSend(true)).unwrap();
+    assert!(device.read_clear_to_send().unwrap());
+    actor.control(Control::SetBaudRate(57_600)).unwrap();
+    let e = actor.control(Control::SetBaudRate(0)).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+
+    // Data queued right before shutting down is still written
+    writer.send(b"bye".to_vec()).unwrap();
+    let port = actor.shutdown().unwrap();
+    assert_eq!(port.baud_rate().unwrap(), 57_600);
+    assert_eq!(port.timeout(), Duration::from_millis(500));
+    let mut last = [0; 3];
+    device.read_exact(&mut last).unwrap();
+    assert_eq!(&last, b"bye");
+}
+
+// Test that many small writes aren't held up by the reads in between
+#[test]
+fn test_actor_writes_without_delay() {
+    let (port, mut device) = VirtualPort::pair();
+    device.set_timeout(Duration::from_secs(1)).unwrap();
+    let actor = Actor::spawn(Box::new(port)).unwrap();
+
+    let start = Instant::now();
+    let writer = actor.writer();
+    for i in 0..200u8 {
+        writer.send(vec![i]).unwrap();
+    }
+    let mut received = [0; 200];
+    device.read_exact(&mut received).unwrap();
+    assert!(received.iter().enumerate().all(|(i, &b)| b == i as u8));
+    // Writing one chunk per read timeout took two seconds
+    assert!(start.elapsed() < Duration::from_secs(1));
+    actor.shutdown().unwrap();
+}
+
+// Test that an I/O error stops the thread and is reported by `shutdown()`
+#[test]
+fn test_actor_stops_on_error() {
+    let port = MockSerialPort::new();
+    port.read_error(Error::new(ErrorKind::NoDevice, "Device was unplugged"));
+    let actor = Actor::spawn(Box::new(port.try_clone_native().unwrap())).unwrap();
+
+    assert!(actor
+        .receiver()
+        .recv_timeout(Duration::from_secs(1))
+        .is_err());
+    let e = actor
+        .control(Control::WriteDataTerminalReady(true))
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+    match actor.shutdown() {
+        Ok(_) => panic!("Shut down cleanly after an error"),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::NoDevice),
+    }
+}
@@ -0,0 +1,140 @@
+extern crate serialport;
+
+use serialport::mock::MockSerialPort;
+use serialport::{
+    Capabilities, Error, ErrorKind, PortBackend, SerialPort, SerialPortInfo, SerialPortSettings,
+    SerialPortType,
+};
+use std::sync::Mutex;
+
+/// A backend handing out clones of a mocked port, which the test keeps to inspect it
+struct MockBackend {
+    scheme: &'static str,
+    port: Mutex<MockSerialPort>,
+}
+
+impl MockBackend {
+    fn new(scheme: &'static str) -> (MockBackend, MockSerialPort) {
+        let port = MockSerialPort::new();
+        let handle = port.try_clone_native().unwrap();
+        let backend = MockBackend {
+            scheme,
+            port: Mutex::new(port),
+        };
+        (backend, handle)
+    }
+}
+
+impl PortBackend for MockBackend {
+    fn scheme(&self) -> &str {
+        self.scheme
+    }
+
+    fn open(
+        &self,
+        _path: &str,
+        settings: &SerialPortSettings,
+    ) -> serialport::Result<Box<dyn SerialPort>> {
+        let mut port = self.port.lock().unwrap().try_clone_native()?;
+        port.set_all(settings)?;
+        Ok(Box::new(port))
+    }
+
+    fn available_ports(&self) -> serialport::Result<Vec<SerialPortInfo>> {
+        Ok(vec![SerialPortInfo {
+            port_name: format!("{}://0", self.scheme),
+            port_type: SerialPortType::Unknown,
+            location: None,
+            aliases: Vec::new(),
+            driver: None,
+            friendly_name: None,
+            hardware_ids: Vec::new(),
+        }])
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CONFIGURE | Capabilities::MODEM_CONTROL | Capabilities::ENUMERATE
+    }
+}
+
+/// A backend that can't list its ports
+struct FailingBackend;
+
+impl PortBackend for FailingBackend {
+    fn scheme(&self) -> &str {
+        "mock-failing"
+    }
+
+    fn open(&self, _path: &str, _: &SerialPortSettings) -> serialport::Result<Box<dyn SerialPort>> {
+        Err(Error::new(ErrorKind::NoDevice, "Bridge is unreachable"))
+    }
+
+    fn available_ports(&self) -> serialport::Result<Vec<SerialPortInfo>> {
+        Err(Error::new(ErrorKind::NoDevice, "Bridge is unreachable"))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::ENUMERATE
+    }
+}
+
+// Test that registered backends open and list their ports
+#[test]
+fn test_backend_open_and_enumerate() {
+    let (backend, handle) = MockBackend::new("mock-open");
+    serialport::register_backend(Box::new(backend)).unwrap();
+
+    let port = serialport::new("MOCK-OPEN://0", 57_600)
+        .dtr_on_open(true)
+        .open()
+        .unwrap();
+    assert_eq!(port.baud_rate().unwrap(), 57_600);
+    assert!(handle.data_terminal_ready());
+
+    let ports = serialport::available_ports().unwrap();
+    assert!(ports.iter().any(|port| port.port_name == "mock-open://0"));
+
+    let backend = serialport::find_backend("mock-open://0").unwrap();
+    assert!(backend.capabilities().contains(Capabilities::ENUMERATE));
+
+    assert!(serialport::unregister_backend("mock-open"));
+    assert!(serialport::find_backend("mock-open://0").is_none());
+    assert!(!serialport::unregister_backend("mock-open"));
+}
+
+// Test that schemes must be valid and unique, and that unknown ones aren't opened as devices
+#[test]
+fn test_backend_registration_errors() {
+    let (backend, _) = MockBackend::new("not a scheme");
+    let e = serialport::register_backend(Box::new(backend)).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+
+    let (backend, _) = MockBackend::new("mock-twice");
+    serialport::register_backend(Box::new(backend)).unwrap();
+    let (backend, _) = MockBackend::new("mock-twice");
+    let e = serialport::register_backend(Box::new(backend)).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    serialport::unregister_backend("mock-twice");
+
+    match serialport::new("mock-unregistered://0", 9600).open() {
+        Ok(_) => panic!("Opened a port without a backend"),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::NoDevice),
+    }
+}
+
+// Test that a backend failing to list its ports doesn't hide the others
+#[test]
+fn test_backend_enumeration_error_is_skipped() {
+    let (backend, _) = MockBackend::new("mock-listed");
+    serialport::register_backend(Box::new(backend)).unwrap();
+    serialport::register_backend(Box::new(FailingBackend)).unwrap();
+
+    let ports = serialport::available_ports().unwrap();
+    assert!(ports.iter().any(|port| port.port_name == "mock-listed://0"));
+    assert!(!ports
+        .iter()
+        .any(|port| port.port_name.starts_with("mock-failing://")));
+
+    serialport::unregister_backend("mock-failing");
+    serialport::unregister_backend("mock-listed");
+}
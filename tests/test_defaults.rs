@@ -0,0 +1,225 @@
+//! Tests for the default implementations of `SerialPort` methods.
+
+use std::io;
+use std::time::Duration;
+
+use serialport::*;
+
+/// A port that only implements the required methods
+#[derive(Default)]
+struct BarePort {
+    settings: SerialPortSettings,
+}
+
+impl io::Read for BarePort {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        unimplemented!()
+    }
+}
+
+impl io::Write for BarePort {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        unimplemented!()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        unimplemented!()
+    }
+}
+
+impl SerialPort for BarePort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+    fn baud_rate(&self) -> Result<u32> {
+        unimplemented!()
+    }
+    fn data_bits(&self) -> Result<DataBits> {
+        unimplemented!()
+    }
+    fn flow_control(&self) -> Result<FlowControl> {
+        unimplemented!()
+    }
+    fn parity(&self) -> Result<Parity> {
+        unimplemented!()
+    }
+    fn stop_bits(&self) -> Result<StopBits> {
+        unimplemented!()
+    }
+    fn timeout(&self) -> Duration {
+        unimplemented!()
+    }
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<()> {
+        self.settings.baud_rate = baud_rate;
+        Ok(())
+    }
+    fn set_data_bits(&mut self, data_bits: DataBits) -> Result<()> {
+        self.settings.data_bits = data_bits;
+        Ok(())
+    }
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> Result<()> {
+        self.settings.flow_control = flow_control;
+        Ok(())
+    }
+    fn set_parity(&mut self, parity: Parity) -> Result<()> {
+        self.settings.parity = parity;
+        Ok(())
+    }
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> Result<()> {
+        self.settings.stop_bits = stop_bits;
+        Ok(())
+    }
+    fn set_timeout(&mut self, timeout: Duration) -> Result<Duration> {
+        Ok(std::mem::replace(&mut self.settings.timeout, timeout))
+    }
+    fn write_request_to_send(&mut self, _level: bool) -> Result<()> {
+        unimplemented!()
+    }
+    fn write_data_terminal_ready(&mut self, _level: bool) -> Result<()> {
+        unimplemented!()
+    }
+    fn read_clear_to_send(&mut self) -> Result<bool> {
+        unimplemented!()
+    }
+    fn read_data_set_ready(&mut self) -> Result<bool> {
+        unimplemented!()
+    }
+    fn read_ring_indicator(&mut self) -> Result<bool> {
+        unimplemented!()
+    }
+    fn read_carrier_detect(&mut self) -> Result<bool> {
+        unimplemented!()
+    }
+    fn bytes_to_read(&self) -> Result<u32> {
+        unimplemented!()
+    }
+    fn bytes_to_write(&self) -> Result<u32> {
+        unimplemented!()
+    }
+    fn clear(&self, _buffer_to_clear: ClearBuffer) -> Result<()> {
+        unimplemented!()
+    }
+    fn try_clone(&self) -> Result<Box<dyn SerialPort>> {
+        unimplemented!()
+    }
+}
+
+#[test]
+fn test_default_stats() {
+    let mut port = BarePort::default();
+    port.reset_stats();
+    assert_eq!(port.stats(), PortStats::default());
+}
+
+#[test]
+fn test_default_read_is_eof_possible() {
+    assert!(BarePort::default().read_is_eof_possible());
+}
+
+#[test]
+fn test_default_try_write() {
+    let e = BarePort::default().try_write(b"data").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_try_read() {
+    let e = BarePort::default().try_read(&mut [0; 4]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_drain() {
+    let e = BarePort::default().drain().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_inter_byte_write_delay() {
+    let mut port = BarePort::default();
+    assert_eq!(port.inter_byte_write_delay(), Duration::from_secs(0));
+    port.set_inter_byte_write_delay(Duration::from_secs(0))
+        .unwrap();
+    let e = port
+        .set_inter_byte_write_delay(Duration::from_millis(1))
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_set_rx_watchdog() {
+    let e = BarePort::default()
+        .set_rx_watchdog(Duration::from_secs(1), Box::new(|| ()))
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_break() {
+    let port = BarePort::default();
+    assert_eq!(port.set_break().unwrap_err().kind(), ErrorKind::Unknown);
+    assert_eq!(port.clear_break().unwrap_err().kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_set_all() {
+    let settings = SerialPortSettings {
+        baud_rate: 115_200,
+        data_bits: DataBits::Seven,
+        flow_control: FlowControl::Hardware,
+        parity: Parity::Even,
+        stop_bits: StopBits::Two,
+        timeout: Duration::from_millis(50),
+    };
+    let mut port = BarePort::default();
+    port.set_all(&settings).unwrap();
+    assert_eq!(port.settings, settings);
+}
+
+#[test]
+fn test_default_signals() {
+    let e = BarePort::default().signals().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_set_nonblocking() {
+    let mut port = BarePort::default();
+    port.set_nonblocking(false).unwrap();
+    let e = port.set_nonblocking(true).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_inter_byte_timeout() {
+    let mut port = BarePort::default();
+    assert_eq!(port.inter_byte_timeout(), None);
+    port.set_inter_byte_timeout(None).unwrap();
+    let e = port
+        .set_inter_byte_timeout(Some(Duration::from_millis(5)))
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_xon_xoff_chars() {
+    let mut port = BarePort::default();
+    assert_eq!(port.xon_xoff_chars().unwrap(), (0x11, 0x13));
+    port.set_xon_xoff_chars(0x11, 0x13).unwrap();
+    let e = port.set_xon_xoff_chars(0x01, 0x02).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+    let e = port.set_xon_xoff_chars(0x01, 0x01).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_default_take_errors() {
+    let e = BarePort::default().take_errors().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn test_default_cancellation_token() {
+    let e = BarePort::default().cancellation_token().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Unknown);
+}
@@ -0,0 +1,120 @@
+extern crate serialport;
+
+use serialport::framing::{Cobs, Codec, Delimited, Framed, LengthPrefixed, Slip};
+use serialport::{ErrorKind, SerialPort, VirtualPort};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn round_trip<C: Codec>(codec: &mut C, frame: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    codec.encode(frame, &mut encoded).unwrap();
+    let decoded = codec.decode(&mut encoded).unwrap().unwrap();
+    assert!(encoded.is_empty(), "Bytes left over after decoding");
+    decoded
+}
+
+// Test that the codecs reproduce their frames, including the edge cases of COBS blocks
+#[test]
+fn test_codec_round_trips() {
+    let long: Vec<u8> = (0..600).map(|i| (i % 256) as u8).collect();
+    let no_zeros = vec![0x55; 254];
+    let frames: [&[u8]; 6] = [&[], &[0], &[0, 0], &[1, 0, 2], &no_zeros, &long];
+    for frame in frames.iter() {
+        assert_eq!(round_trip(&mut Cobs, frame), *frame);
+        // Empty SLIP frames are indistinguishable from the delimiters around frames
+        if !frame.is_empty() {
+            assert_eq!(round_trip(&mut Slip, frame), *frame);
+        }
+        assert_eq!(round_trip(&mut LengthPrefixed::u16_le(), frame), *frame);
+    }
+    assert_eq!(
+        round_trip(&mut Slip, &[0xC0, 0xDB, 0xDC]),
+        [0xC0, 0xDB, 0xDC]
+    );
+    assert_eq!(round_trip(&mut Delimited::new(b'\n'), b"hello"), b"hello");
+}
+
+// Test the encodings against known values
+#[test]
+fn test_codec_encodings() {
+    let mut encoded = Vec::new();
+    Cobs.encode(&[0x11, 0x22, 0x00, 0x33], &mut encoded)
+        .unwrap();
+    assert_eq!(encoded, [0x03, 0x11, 0x22, 0x02, 0x33, 0x00]);
+
+    encoded.clear();
+    Slip.encode(&[0x01, 0xC0, 0xDB], &mut encoded).unwrap();
+    assert_eq!(encoded, [0xC0, 0x01, 0xDB, 0xDC, 0xDB, 0xDD, 0xC0]);
+
+    encoded.clear();
+    LengthPrefixed::u16_be()
+        .encode(b"ab", &mut encoded)
+        .unwrap();
+    assert_eq!(encoded, [0x00, 0x02, b'a', b'b']);
+
+    let e = LengthPrefixed::u8()
+        .encode(&[0; 256], &mut encoded)
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    let e = Delimited::new(b'\n')
+        .encode(b"a\nb", &mut encoded)
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+}
+
+// Test that decoding waits for complete frames and skips malformed ones
+#[test]
+fn test_codec_partial_and_malformed() {
+    let mut buffer = vec![0x03, 0x11];
+    assert_eq!(Cobs.decode(&mut buffer).unwrap(), None);
+    buffer.extend_from_slice(&[0x22, 0x00]);
+    assert_eq!(Cobs.decode(&mut buffer).unwrap(), Some(vec![0x11, 0x22]));
+
+    let mut buffer = vec![0xC0, 0xDB, 0x01, 0xC0, 0x05, 0xC0];
+    let e = Slip.decode(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Io(io::ErrorKind::InvalidData));
+    assert_eq!(Slip.decode(&mut buffer).unwrap(), Some(vec![0x05]));
+}
+
+// Test reading frames that arrive in pieces, with the timeout applying to the whole frame
+#[test]
+fn test_framed_port() {
+    let (a, mut b) = VirtualPort::pair();
+    let mut framed = Framed::new(a, Delimited::new(b'\n'));
+    framed
+        .get_mut()
+        .set_timeout(Duration::from_secs(2))
+        .unwrap();
+
+    let writer = thread::spawn(move || {
+        b.write_all(b"first\nsec").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        b.write_all(b"ond\n").unwrap();
+        b
+    });
+    assert_eq!(framed.read_frame().unwrap(), b"first");
+    assert_eq!(framed.read_frame().unwrap(), b"second");
+    let _b = writer.join().unwrap();
+
+    // The timeout is restored after each frame
+    framed
+        .get_mut()
+        .set_timeout(Duration::from_millis(50))
+        .unwrap();
+    let start = Instant::now();
+    let e = framed.read_frame().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(framed.get_ref().timeout(), Duration::from_millis(50));
+}
+
+// Test framing a boxed port
+#[test]
+fn test_framed_boxed_port() {
+    let (a, b) = VirtualPort::pair();
+    let mut sender = Framed::from_box(Box::new(a) as Box<dyn SerialPort>, Slip);
+    let mut receiver = Framed::new(b, Slip);
+    sender.write_frame(&[0xC0, 0x00]).unwrap();
+    assert_eq!(receiver.read_frame().unwrap(), [0xC0, 0x00]);
+}
@@ -0,0 +1,95 @@
+extern crate serialport;
+
+use serialport::mock::MockSerialPort;
+use serialport::{Error, ErrorKind, SerialPort, SignalState};
+use std::io::{self, Read, Write};
+
+// Test a request and a response split across several reads
+#[test]
+fn test_mock_request_response() {
+    let mock = MockSerialPort::new();
+    mock.expect_write(b"AT\r").respond(b"OK").respond(b"\r\n");
+    let mut port = mock.try_clone().unwrap();
+
+    port.write_all(b"A").unwrap();
+    port.write_all(b"T\r").unwrap();
+    assert_eq!(port.bytes_to_read().unwrap(), 2);
+    let mut buffer = [0; 8];
+    assert_eq!(port.read(&mut buffer).unwrap(), 2);
+    assert_eq!(&buffer[..2], b"OK");
+    assert!(!mock.is_finished());
+    assert_eq!(port.read(&mut buffer).unwrap(), 2);
+    assert_eq!(&buffer[..2], b"\r\n");
+    assert!(mock.is_finished());
+
+    // Nothing left to read
+    let e = port.read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+}
+
+// Test injected timeouts and errors
+#[test]
+fn test_mock_injected_errors() {
+    let mock = MockSerialPort::new();
+    mock.read_timeout()
+        .read_error(Error::new(ErrorKind::NoDevice, "unplugged"))
+        .write_error(Error::new(ErrorKind::BrokenPipe, "gone"));
+    let mut port = mock.try_clone_native().unwrap();
+
+    let mut buffer = [0; 1];
+    let e = port.read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+    let e = port.read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    let e = port.try_write(b"x").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+    assert!(mock.is_finished());
+}
+
+// Test that line changes take effect in script order and outputs are recorded
+#[test]
+fn test_mock_signals() {
+    let mock = MockSerialPort::new();
+    let ready = SignalState {
+        clear_to_send: true,
+        ..SignalState::default()
+    };
+    mock.expect_write(b"x").set_signals(ready);
+    let mut port = mock.try_clone_native().unwrap();
+
+    assert!(!port.read_clear_to_send().unwrap());
+    port.write_all(b"x").unwrap();
+    assert!(port.read_clear_to_send().unwrap());
+
+    port.write_request_to_send(true).unwrap();
+    assert!(mock.request_to_send());
+    assert!(!mock.data_terminal_ready());
+}
+
+// Test that writing something else than expected fails the test
+#[test]
+#[should_panic(expected = "expected a write")]
+fn test_mock_unexpected_write() {
+    let mut port = MockSerialPort::new();
+    port.expect_write(b"AT\r");
+    let _ = port.write(b"ATZ\r");
+}
+
+// Test that data stuck in the output buffer makes `ensure_transmitted()` give up
+#[test]
+fn test_mock_ensure_transmitted_times_out() {
+    use std::time::{Duration, Instant};
+
+    let mock = MockSerialPort::new();
+    mock.set_bytes_to_write(16);
+    let mut port = mock.try_clone_native().unwrap();
+
+    let start = Instant::now();
+    assert!(!port.ensure_transmitted(Duration::from_millis(20)).unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    let e = port.flush_timeout(Duration::from_millis(1)).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+
+    mock.set_bytes_to_write(0);
+    assert!(port.ensure_transmitted(Duration::from_millis(20)).unwrap());
+}
@@ -0,0 +1,216 @@
+#![cfg(feature = "rfc2217")]
+extern crate serialport;
+
+use serialport::{DataBits, ErrorKind, FlowControl, Parity};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const DO: u8 = 253;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const COM_PORT_OPTION: u8 = 44;
+
+/// Sends a COM-PORT-OPTION subnegotiation, escaping its value
+fn send_subnegotiation(stream: &mut TcpStream, command: u8, value: &[u8]) {
+    let mut message = vec![IAC, SB, COM_PORT_OPTION, command];
+    for &byte in value {
+        message.push(byte);
+        if byte == IAC {
+            message.push(IAC);
+        }
+    }
+    message.extend_from_slice(&[IAC, SE]);
+    stream.write_all(&message).unwrap();
+}
+
+/// The COM-PORT-OPTION commands a server received, with their values
+type Commands = Arc<Mutex<Vec<(u8, Vec<u8>)>>>;
+
+/// A minimal RFC 2217 server that acknowledges every command and echoes the data it receives
+///
+/// It reports CTS as asserted once the client offers the COM-PORT-OPTION. Before each echo it
+/// sends the `unsolicited` bytes, which the client didn't ask for.
+fn serve(mut stream: TcpStream, commands: Commands, unsolicited: &[u8]) {
+    let mut received = Vec::new();
+    let mut buf = [0u8; 256];
+    loop {
+        let n = match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        received.extend_from_slice(&buf[..n]);
+
+        let mut echo = Vec::new();
+        let mut i = 0;
+        while i < received.len() {
+            if received[i] != IAC {
+                echo.push(received[i]);
+                i += 1;
+                continue;
+            }
+            match received.get(i + 1) {
+                None => break,
+                Some(&IAC) => {
+                    echo.extend_from_slice(&[IAC, IAC]);
+                    i += 2;
+                }
+                Some(&SB) => {
+                    let end = match received[i..].windows(2).position(|w| w == [IAC, SE]) {
+                        Some(end) => i + end,
+                        None => break,
+                    };
+                    let command = received[i + 3];
+                    let value: Vec<u8> = received[i + 4..end].to_vec();
+                    commands.lock().unwrap().push((command, value.clone()));
+                    send_subnegotiation(&mut stream, command + 100, &value);
+                    i = end + 2;
+                }
+                Some(_) => {
+                    let option = match received.get(i + 2) {
+                        Some(&option) => option,
+                        None => break,
+                    };
+                    if received[i + 1] == WILL && option == COM_PORT_OPTION {
+                        stream.write_all(&[IAC, DO, COM_PORT_OPTION]).unwrap();
+                        send_subnegotiation(&mut stream, 107, &[0x10]);
+                    }
+                    i += 3;
+                }
+            }
+        }
+        received.drain(..i);
+        if !echo.is_empty() {
+            stream.write_all(unsolicited).unwrap();
+        }
+        stream.write_all(&echo).unwrap();
+    }
+}
+
+/// Starts a server for a single connection, returning its URL and the commands it receives
+fn start_server() -> (String, Commands) {
+    start_server_with(Vec::new())
+}
+
+/// Like `start_server()`, for a server that sends `unsolicited` before each echo
+fn start_server_with(unsolicited: Vec<u8>) -> (String, Commands) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let commands = Commands::default();
+    let received = commands.clone();
+    thread::spawn(move || {
+        if let Ok((stream, _)) = listener.accept() {
+            serve(stream, received, &unsolicited);
+        }
+    });
+    (format!("rfc2217://{}", address), commands)
+}
+
+// Test that opening a remote port applies the settings and that data passes through unchanged
+#[test]
+fn test_rfc2217_open_and_echo() {
+    let (url, _) = start_server();
+    let mut port = serialport::new(url.as_str(), 115_200)
+        .parity(Parity::Even)
+        .data_bits(DataBits::Seven)
+        .timeout(Duration::from_secs(1))
+        .open()
+        .expect("Failed to open remote port");
+
+    assert_eq!(port.name(), Some(url));
+    assert_eq!(port.baud_rate().unwrap(), 115_200);
+    assert_eq!(port.parity().unwrap(), Parity::Even);
+    assert_eq!(port.data_bits().unwrap(), DataBits::Seven);
+    assert!(port.read_clear_to_send().unwrap());
+
+    // IAC bytes in the data must be escaped on the way and back
+    let data = [0x01, IAC, 0x02, IAC, IAC, 0x03];
+    port.write_all(&data).unwrap();
+    let mut buffer = [0; 6];
+    port.read_exact(&mut buffer).unwrap();
+    assert_eq!(buffer, data);
+
+    port.set_baud_rate(9600).unwrap();
+    assert_eq!(port.baud_rate().unwrap(), 9600);
+    port.write_data_terminal_ready(true).unwrap();
+}
+
+// Test that leaving DTR/DSR flow control also turns it off for the inbound direction
+#[test]
+fn test_rfc2217_flow_control() {
+    const SET_CONTROL: u8 = 5;
+
+    let (url, commands) = start_server();
+    let mut port = serialport::new(url.as_str(), 9600)
+        .open()
+        .expect("Failed to open remote port");
+    commands.lock().unwrap().clear();
+
+    port.set_flow_control(FlowControl::DtrDsr).unwrap();
+    port.set_flow_control(FlowControl::None).unwrap();
+    assert_eq!(port.flow_control().unwrap(), FlowControl::None);
+    let controls: Vec<Vec<u8>> = commands
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|&&(command, _)| command == SET_CONTROL)
+        .map(|(_, value)| value.clone())
+        .collect();
+    assert_eq!(controls, [[19], [18], [14], [1]]);
+}
+
+// Test that answers to commands nobody waits for are dropped instead of being taken for the answers
+// to later commands
+#[test]
+fn test_rfc2217_unsolicited_replies() {
+    const SET_BAUDRATE_REPLY: u8 = 101;
+
+    // Lots of answers claiming a baud rate of 1234, as a misbehaving server might send
+    let mut unsolicited = Vec::new();
+    for _ in 0..1000 {
+        unsolicited.extend_from_slice(&[IAC, SB, COM_PORT_OPTION, SET_BAUDRATE_REPLY]);
+        unsolicited.extend_from_slice(&1234u32.to_be_bytes());
+        unsolicited.extend_from_slice(&[IAC, SE]);
+    }
+    let (url, _) = start_server_with(unsolicited);
+    let mut port = serialport::new(url.as_str(), 9600)
+        .timeout(Duration::from_secs(1))
+        .open()
+        .expect("Failed to open remote port");
+
+    port.write_all(b"x").unwrap();
+    let mut buffer = [0; 1];
+    port.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, b"x");
+
+    port.set_baud_rate(19_200).unwrap();
+    assert_eq!(port.baud_rate().unwrap(), 19_200);
+}
+
+// Test that a server without the COM-PORT-OPTION is rejected
+#[test]
+fn test_rfc2217_unsupported_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        if let Ok((mut stream, _)) = listener.accept() {
+            // Refuse every option
+            stream.write_all(&[IAC, 254, COM_PORT_OPTION]).unwrap();
+            let mut buf = [0u8; 64];
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+            }
+        }
+    });
+
+    match serialport::new(format!("rfc2217://{}", address), 9600).open() {
+        Ok(_) => panic!("Opened a port on a server without RFC 2217"),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::Unknown),
+    }
+}
@@ -41,6 +41,14 @@ fn test_configuring_ports() {
         .open();
 }
 
+#[test]
+fn test_configuring_modem_lines_on_open() {
+    let _port = serialport::new("/dev/ttyUSB0", 9600)
+        .dtr_on_open(false)
+        .rts_on_open(false)
+        .open();
+}
+
 #[test]
 fn test_duplicating_port_config() {
     let port1_config = serialport::new("/dev/ttyUSB0", 9600)
@@ -55,3 +63,188 @@ fn test_duplicating_port_config() {
     let _port1 = port1_config.open();
     let _port1 = port2_config.open();
 }
+
+#[test]
+fn test_reusing_port_config() {
+    let mut config = serialport::new("/dev/ttyUSB0", 9600).timeout(Duration::from_millis(1));
+
+    let _port1 = config.open();
+    config = config.path("/dev/ttyUSB1");
+    let _port2 = config.open();
+    let _port2 = config.open_native();
+}
+
+#[test]
+fn test_baud_rate_divisors() {
+    assert_eq!(serialport::baud_rate_from_divisor(1_843_200, 12), 9600);
+    assert_eq!(serialport::baud_rate_from_divisor(3_686_400, 2), 115_200);
+
+    assert_eq!(serialport::nearest_divisor(9600, 1_843_200), (12, 0.0));
+    let (divisor, error) = serialport::nearest_divisor(9600, 16_000_000);
+    assert_eq!(divisor, 104);
+    assert!(error > 0.0 && error < 0.002);
+    assert_eq!(serialport::nearest_divisor(1_000_000, 1_843_200).0, 1);
+}
+
+#[test]
+fn test_setting_codes() {
+    assert_eq!(DataBits::Five.code(), 5);
+    assert_eq!(DataBits::Eight.code(), 8);
+    assert_eq!(Parity::None.code(), 0);
+    assert_eq!(Parity::Odd.code(), 1);
+    assert_eq!(Parity::Even.code(), 2);
+    assert_eq!(Parity::Mark.code(), 3);
+    assert_eq!(Parity::Space.code(), 4);
+    assert_eq!(StopBits::One.code(), 1);
+    assert_eq!(StopBits::Two.code(), 2);
+    assert_eq!(StopBits::OnePointFive.code(), 3);
+}
+
+#[test]
+fn test_error_kind_survives_io_error() {
+    let error = Error::new(ErrorKind::NoDevice, "Device was disconnected");
+    let io_error = std::io::Error::from(error);
+    assert_eq!(io_error.kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(Error::from(io_error).kind(), ErrorKind::NoDevice);
+
+    let io_error = std::io::Error::new(std::io::ErrorKind::TimedOut, "Operation timed out");
+    assert_eq!(Error::from(io_error).kind(), ErrorKind::TimedOut);
+
+    let error = Error::new(ErrorKind::Busy, "Device is locked");
+    let io_error = std::io::Error::from(error);
+    assert_eq!(io_error.kind(), std::io::ErrorKind::Other);
+    assert_eq!(Error::from(io_error).kind(), ErrorKind::Busy);
+
+    let io_error = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+    assert_eq!(Error::from(io_error).kind(), ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn test_parsing_settings() {
+    assert_eq!("8".parse::<DataBits>().unwrap(), DataBits::Eight);
+    assert_eq!("Odd".parse::<Parity>().unwrap(), Parity::Odd);
+    assert_eq!("EVEN".parse::<Parity>().unwrap(), Parity::Even);
+    assert_eq!("2".parse::<StopBits>().unwrap(), StopBits::Two);
+    assert_eq!("1.5".parse::<StopBits>().unwrap(), StopBits::OnePointFive);
+    assert_eq!(
+        "hardware".parse::<FlowControl>().unwrap(),
+        FlowControl::Hardware
+    );
+    assert_eq!(
+        "9".parse::<DataBits>().unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+    assert_eq!("Space".parse::<Parity>().unwrap(), Parity::Space);
+    assert!("stick".parse::<Parity>().is_err());
+
+    // Displaying a setting gives back a string that parses to it
+    for &data_bits in &[
+        DataBits::Five,
+        DataBits::Six,
+        DataBits::Seven,
+        DataBits::Eight,
+    ] {
+        assert_eq!(
+            data_bits.to_string().parse::<DataBits>().unwrap(),
+            data_bits
+        );
+    }
+    for &parity in &[
+        Parity::None,
+        Parity::Odd,
+        Parity::Even,
+        Parity::Mark,
+        Parity::Space,
+    ] {
+        assert_eq!(parity.to_string().parse::<Parity>().unwrap(), parity);
+    }
+    for &stop_bits in &[StopBits::One, StopBits::OnePointFive, StopBits::Two] {
+        assert_eq!(
+            stop_bits.to_string().parse::<StopBits>().unwrap(),
+            stop_bits
+        );
+    }
+    for &flow_control in &[
+        FlowControl::None,
+        FlowControl::Software,
+        FlowControl::Hardware,
+        FlowControl::DtrDsr,
+    ] {
+        assert_eq!(
+            flow_control.to_string().parse::<FlowControl>().unwrap(),
+            flow_control
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_settings_serde_round_trip() {
+    let settings = SerialPortSettings {
+        baud_rate: 115_200,
+        data_bits: DataBits::Seven,
+        flow_control: FlowControl::DtrDsr,
+        parity: Parity::Even,
+        stop_bits: StopBits::OnePointFive,
+        timeout: Duration::from_millis(250),
+    };
+    let json = serde_json::to_value(settings).unwrap();
+    assert_eq!(json["baud_rate"], 115_200);
+    assert_eq!(json["data_bits"], "seven");
+    assert_eq!(json["flow_control"], "dtr_dsr");
+    assert_eq!(json["parity"], "even");
+    assert_eq!(json["stop_bits"], "one_point_five");
+    assert_eq!(
+        serde_json::from_value::<SerialPortSettings>(json).unwrap(),
+        settings
+    );
+
+    let info = SerialPortInfo {
+        port_name: "/dev/ttyUSB0".to_string(),
+        port_type: SerialPortType::UsbPort(UsbPortInfo {
+            vid: 0x0403,
+            pid: 0x6001,
+            serial_number: Some("A12345".to_string()),
+            manufacturer: None,
+            product: None,
+        }),
+        location: None,
+        aliases: Vec::new(),
+        driver: Some("ftdi_sio".to_string()),
+        friendly_name: None,
+        hardware_ids: Vec::new(),
+    };
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(serde_json::from_str::<SerialPortInfo>(&json).unwrap(), info);
+}
+
+#[test]
+fn test_port_filter() {
+    let port = |port_type| SerialPortInfo {
+        port_name: "COM3".to_string(),
+        port_type,
+        location: None,
+        aliases: Vec::new(),
+        driver: None,
+        friendly_name: None,
+        hardware_ids: Vec::new(),
+    };
+    let ftdi = port(SerialPortType::UsbPort(UsbPortInfo {
+        vid: 0x0403,
+        pid: 0x6001,
+        serial_number: Some("A702TB52A".to_string()),
+        manufacturer: None,
+        product: None,
+    }));
+    let pci = port(SerialPortType::PciPort);
+
+    assert!(PortFilter::new().matches(&ftdi));
+    assert!(PortFilter::new().matches(&pci));
+    assert!(PortFilter::new().vid(0x0403).pid(0x6001).matches(&ftdi));
+    assert!(!PortFilter::new().vid(0x0403).pid(0x6015).matches(&ftdi));
+    assert!(PortFilter::new().serial_number("tb52").matches(&ftdi));
+    assert!(!PortFilter::new().serial_number("B52B").matches(&ftdi));
+
+    // USB criteria never match other ports
+    assert!(!PortFilter::new().vid(0x0403).matches(&pci));
+}
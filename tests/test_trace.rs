@@ -0,0 +1,112 @@
+extern crate serialport;
+
+use serialport::trace::{Capture, CaptureReader, Event, HexLog, Record, TraceSink, TracingPort};
+use serialport::{ErrorKind, SerialPort, VirtualPort};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Returns a sink collecting the events it's given, and the list they're collected in
+fn collector() -> (impl TraceSink, Arc<Mutex<Vec<Event>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let collected = events.clone();
+    let sink = move |record: &Record| {
+        collected.lock().unwrap().push(record.event.clone());
+        Ok(())
+    };
+    (sink, events)
+}
+
+// Test that data and line changes are recorded in order, also through clones
+#[test]
+fn test_tracing_port_records_traffic() {
+    let (port, mut device) = VirtualPort::pair();
+    let (sink, events) = collector();
+    let mut port = TracingPort::new(port, sink);
+
+    port.write_all(b"AT\r").unwrap();
+    let mut command = [0; 3];
+    device.read_exact(&mut command).unwrap();
+    device.write_all(b"OK").unwrap();
+    let mut response = [0; 2];
+    port.read_exact(&mut response).unwrap();
+    port.write_request_to_send(true).unwrap();
+    port.send_break(Duration::from_millis(1)).unwrap();
+
+    let mut clone = port.try_clone().unwrap();
+    clone.write_data_terminal_ready(false).unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            Event::Tx(b"AT\r".to_vec()),
+            Event::Rx(b"OK".to_vec()),
+            Event::RequestToSend(true),
+            Event::Break(true),
+            Event::Break(false),
+            Event::DataTerminalReady(false),
+        ]
+    );
+}
+
+// Test that captures read back as the records that were written
+#[test]
+fn test_capture_round_trip() {
+    let records = vec![
+        Record {
+            time: Duration::from_micros(12),
+            event: Event::Tx(vec![0x41, 0x54]),
+        },
+        Record {
+            time: Duration::from_millis(1500),
+            event: Event::Rx(Vec::new()),
+        },
+        Record {
+            time: Duration::from_secs(2),
+            event: Event::Break(true),
+        },
+    ];
+    let mut capture = Capture::new(Vec::new());
+    for record in &records {
+        capture.record(record).unwrap();
+    }
+    let bytes = capture.into_inner();
+    assert_eq!(&bytes[..8], b"SPTRACE\x01");
+
+    let replayed: Vec<Record> = CaptureReader::new(&bytes[..])
+        .unwrap()
+        .collect::<serialport::Result<_>>()
+        .unwrap();
+    assert_eq!(replayed, records);
+
+    // A capture cut off within a record is an error rather than a shorter capture
+    let mut reader = CaptureReader::new(&bytes[..bytes.len() - 1]).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_ok());
+    let e = reader.next().unwrap().unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Io(std::io::ErrorKind::UnexpectedEof));
+
+    let e = CaptureReader::new(&b"not a capture"[..]).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Io(std::io::ErrorKind::InvalidData));
+    assert_eq!(CaptureReader::new(&b""[..]).unwrap().count(), 0);
+}
+
+// Test the format of the hex log
+#[test]
+fn test_hex_log() {
+    let mut log = HexLog::new(Vec::new());
+    log.record(&Record {
+        time: Duration::from_micros(4_000_521),
+        event: Event::Rx(vec![0x4f, 0x0d]),
+    })
+    .unwrap();
+    log.record(&Record {
+        time: Duration::from_millis(10),
+        event: Event::RequestToSend(false),
+    })
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(log.into_inner()).unwrap(),
+        "    4.000521 RX 4f 0d\n    0.010000 RTS off\n"
+    );
+}
@@ -3,12 +3,30 @@
 
 extern crate serialport;
 
-use std::io::{Read, Write};
+use std::io::{IoSlice, IoSliceMut, Read, Write};
 use std::os::unix::prelude::*;
 use std::str;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use serialport::{SerialPort, TTYPort};
+use serialport::{Deadline, SerialPort, StopBits, TTYPort};
+
+/// Checks for processes that may open ttys held in exclusive mode by others
+///
+/// This takes `CAP_SYS_ADMIN` on Linux, which root usually has.
+fn is_root() -> bool {
+    nix::unistd::geteuid().is_root()
+}
+
+/// Counts the file descriptors of this process that refer to `path`
+#[cfg(target_os = "linux")]
+fn open_fds_for(path: &str) -> usize {
+    std::fs::read_dir("/proc/self/fd")
+        .expect("Unable to list open file descriptors")
+        .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+        .filter(|target| target.as_os_str() == path)
+        .count()
+}
 
 #[test]
 fn test_ttyport_pair() {
@@ -64,6 +82,100 @@ fn test_ttyport_pair() {
     );
 }
 
+#[test]
+#[cfg(feature = "mio")]
+fn test_ttyport_mio_evented() {
+    use mio::{Events, Poll, PollOpt, Ready, Token};
+
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let poll = Poll::new().unwrap();
+    poll.register(&slave, Token(0), Ready::readable(), PollOpt::level())
+        .unwrap();
+
+    // Nothing has been sent, so a read fails right away instead of waiting for the timeout
+    slave.set_timeout(Duration::from_secs(5)).unwrap();
+    let mut buf = [0u8; 4];
+    let start = Instant::now();
+    let err = slave.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    master.write_all(b"ping").unwrap();
+    let mut events = Events::with_capacity(4);
+    poll.poll(&mut events, Some(Duration::from_secs(1)))
+        .unwrap();
+    assert!(events.iter().any(|event| event.token() == Token(0)));
+    assert_eq!(slave.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"ping");
+
+    // Deregistering makes the port block up to its timeout again
+    poll.deregister(&slave).unwrap();
+    slave.set_timeout(Duration::from_millis(10)).unwrap();
+    let err = slave.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+#[cfg(feature = "tokio")]
+fn test_serial_stream() {
+    use serialport::SerialStream;
+    use tokio_crate::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave.set_timeout(Duration::from_secs(1)).unwrap();
+    let mut rt = tokio_crate::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_io()
+        .build()
+        .unwrap();
+
+    rt.block_on(async {
+        let mut stream = SerialStream::from_port(master).unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        slave.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        slave.write_all(b"pong").unwrap();
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    });
+}
+
+#[test]
+#[cfg(feature = "mio07")]
+fn test_ttyport_mio_source() {
+    use mio07::{Events, Interest, Poll, Token};
+
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let mut poll = Poll::new().unwrap();
+    poll.registry()
+        .register(&mut slave, Token(0), Interest::READABLE)
+        .unwrap();
+
+    // Nothing has been sent, so a read fails right away instead of waiting for the timeout
+    slave.set_timeout(Duration::from_secs(5)).unwrap();
+    let mut buf = [0u8; 4];
+    let start = Instant::now();
+    let err = slave.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    master.write_all(b"ping").unwrap();
+    let mut events = Events::with_capacity(4);
+    poll.poll(&mut events, Some(Duration::from_secs(1)))
+        .unwrap();
+    assert!(events.iter().any(|event| event.token() == Token(0)));
+    assert_eq!(slave.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"ping");
+
+    // Deregistering makes the port block up to its timeout again
+    poll.registry().deregister(&mut slave).unwrap();
+    slave.set_timeout(Duration::from_millis(10)).unwrap();
+    let err = slave.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
 #[test]
 fn test_ttyport_timeout() {
     let result = std::sync::Arc::new(std::sync::Mutex::new(None));
@@ -105,6 +217,7 @@ fn test_ttyport_set_standard_baud() {
     assert_eq!(slave.baud_rate().unwrap(), 57600);
     slave.set_baud_rate(115_200).unwrap();
     assert_eq!(slave.baud_rate().unwrap(), 115_200);
+    assert_eq!(slave.actual_baud_rate().unwrap(), 115_200);
 }
 
 // On mac this fails because you can't set nonstandard baud rates for these virtual ports
@@ -132,3 +245,1264 @@ fn test_ttyport_set_nonstandard_baud() {
     slave.set_baud_rate(1_200_000).unwrap();
     assert_eq!(slave.baud_rate().unwrap(), 1_200_000);
 }
+
+#[test]
+fn test_ttyport_read_deadline_expires() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let start = Instant::now();
+    let deadline = Deadline::new(Duration::from_millis(50)).expect("Unable to create deadline");
+
+    let mut buf = [0u8; 16];
+    let err = slave
+        .read_deadline(&mut buf, &deadline)
+        .expect_err("Read succeeded without any data");
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn test_ttyport_read_deadline_extended() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let deadline = Deadline::new(Duration::from_millis(100)).expect("Unable to create deadline");
+
+    // Keep extending the deadline past its original end and only then send data
+    let extender = deadline.clone();
+    let writer = thread::spawn(move || {
+        for _ in 0..4 {
+            thread::sleep(Duration::from_millis(50));
+            extender.extend(Duration::from_millis(50));
+        }
+        master.write_all(b"late").expect("Unable to write bytes.");
+        master
+    });
+
+    let mut buf = [0u8; 16];
+    let nbytes = slave
+        .read_deadline(&mut buf, &deadline)
+        .expect("Read timed out despite the deadline being extended");
+    assert_eq!(&buf[..nbytes], b"late");
+    writer.join().unwrap();
+}
+
+#[test]
+fn test_ttyport_read_deadline_cancelled() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let deadline = Deadline::new(Duration::from_secs(10)).expect("Unable to create deadline");
+
+    let canceller = deadline.clone();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        canceller.cancel();
+    });
+
+    let start = Instant::now();
+    let mut buf = [0u8; 16];
+    let err = slave
+        .read_deadline(&mut buf, &deadline)
+        .expect_err("Read succeeded without any data");
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+    let inner = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<serialport::Error>())
+        .expect("Missing serialport error");
+    assert_eq!(inner.kind(), serialport::ErrorKind::Interrupted);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    handle.join().unwrap();
+
+    // A cancelled deadline keeps failing
+    let err = slave.read_deadline(&mut buf, &deadline).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+}
+
+#[test]
+fn test_ttyport_max_read_chunk() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    assert_eq!(slave.max_read_chunk(), 0);
+    slave.set_max_read_chunk(4);
+
+    master
+        .write_all(b"chunked")
+        .expect("Unable to write bytes.");
+    thread::sleep(Duration::from_millis(50));
+
+    let mut buf = [0u8; 16];
+    let nbytes = slave.read(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf[..nbytes], b"chun");
+    let nbytes = slave.read(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf[..nbytes], b"ked");
+}
+
+#[test]
+fn test_ttyport_vectored() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    let bufs = [
+        IoSlice::new(b"head"),
+        IoSlice::new(b""),
+        IoSlice::new(b"payload"),
+    ];
+    let nbytes = master
+        .write_vectored(&bufs)
+        .expect("Unable to write bytes.");
+    assert_eq!(nbytes, 11);
+    thread::sleep(Duration::from_millis(50));
+
+    let mut head = [0u8; 4];
+    let mut payload = [0u8; 16];
+    let nbytes = {
+        let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut payload)];
+        slave
+            .read_vectored(&mut bufs)
+            .expect("Unable to read bytes.")
+    };
+    assert_eq!(nbytes, 11);
+    assert_eq!(&head, b"head");
+    assert_eq!(&payload[..7], b"payload");
+}
+
+#[test]
+fn test_ttyport_take_errors_unsupported() {
+    let (mut master, _slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    // Pseudo-terminals don't count receive errors
+    let err = master
+        .take_errors()
+        .expect_err("Receive errors were counted on a pseudo-terminal");
+    assert_eq!(err.kind(), serialport::ErrorKind::Unknown);
+}
+
+#[test]
+fn test_ttyport_cancellation_token() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_secs(10))
+        .expect("Unable to set timeout on the slave");
+    let token = slave
+        .cancellation_token()
+        .expect("Unable to create cancellation token");
+
+    let reader = thread::spawn(move || {
+        let mut buf = [0u8; 4];
+        let start = Instant::now();
+        let result = slave.read_exact(&mut buf);
+        (result, start.elapsed(), slave)
+    });
+    thread::sleep(Duration::from_millis(50));
+    token.cancel();
+
+    let (result, elapsed, mut slave) = reader.join().unwrap();
+    let err = result.expect_err("The read wasn't cancelled");
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+    assert!(elapsed < Duration::from_secs(5));
+
+    // The port stays cancelled
+    assert!(token.is_cancelled());
+    let mut buf = [0u8; 1];
+    let err = slave.read(&mut buf).expect_err("The read wasn't cancelled");
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+}
+
+#[test]
+fn test_ttyport_split_shares_cancellation_token() {
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let (mut reader, mut writer) = slave.split().expect("Unable to split the slave");
+    reader
+        .set_timeout(Duration::from_secs(10))
+        .expect("Unable to set timeout on the reader");
+    writer
+        .set_timeout(Duration::from_secs(10))
+        .expect("Unable to set timeout on the writer");
+    let token = reader
+        .cancellation_token()
+        .expect("Unable to create cancellation token");
+
+    // Nobody reads from the master, so the write blocks once the buffer is full
+    let writer = thread::spawn(move || {
+        let buf = vec![0u8; 1024 * 1024];
+        let start = Instant::now();
+        (writer.write_all(&buf), start.elapsed())
+    });
+    let reader = thread::spawn(move || {
+        let mut buf = [0u8; 4];
+        let start = Instant::now();
+        (reader.read_exact(&mut buf), start.elapsed())
+    });
+    thread::sleep(Duration::from_millis(50));
+    token.cancel();
+
+    let check = |(result, elapsed): (std::io::Result<()>, Duration)| {
+        let err = result.expect_err("The operation wasn't cancelled");
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert!(elapsed < Duration::from_secs(5));
+    };
+    check(writer.join().unwrap());
+    check(reader.join().unwrap());
+}
+
+#[test]
+fn test_ttyport_poll() {
+    let (mut master1, slave1) = TTYPort::pair().expect("Unable to create ptty pair");
+    let (mut master2, slave2) = TTYPort::pair().expect("Unable to create ptty pair");
+    let ports = [&slave1, &slave2];
+
+    let ready = serialport::poll(&ports, Duration::from_millis(10)).expect("Unable to poll");
+    assert!(ready.is_empty());
+
+    master2.write_all(b"2").expect("Unable to write bytes.");
+    let ready = serialport::poll(&ports, Duration::from_secs(1)).expect("Unable to poll");
+    assert_eq!(ready, vec![1]);
+
+    master1.write_all(b"1").expect("Unable to write bytes.");
+    thread::sleep(Duration::from_millis(50));
+    let ready = serialport::poll(&ports, Duration::from_secs(1)).expect("Unable to poll");
+    assert_eq!(ready, vec![0, 1]);
+
+    let err = serialport::poll(&[], Duration::from_millis(10)).expect_err("Polled no ports");
+    assert_eq!(err.kind(), serialport::ErrorKind::InvalidInput);
+
+    // Timeouts too long to express in milliseconds are waited on as if they were infinite
+    let forever = Duration::new(!0, 999_999_999);
+    let ready = serialport::poll(&ports, forever).expect("Unable to poll");
+    assert_eq!(ready, vec![0, 1]);
+}
+
+#[test]
+fn test_ttyport_try_write() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    let nbytes = master.try_write(b"now").expect("Unable to write bytes.");
+    assert_eq!(nbytes, 3);
+    let mut buf = [0u8; 3];
+    slave.read_exact(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf, b"now");
+
+    // Nobody reads from the slave, so its buffer fills up without the write ever blocking
+    let chunk = [0u8; 1024];
+    let mut result = Ok(0);
+    for _ in 0..1024 {
+        result = master.try_write(&chunk);
+        if result.is_err() {
+            break;
+        }
+    }
+    let err = result.expect_err("The slave's buffer never filled up");
+    assert_eq!(
+        err.kind(),
+        serialport::ErrorKind::Io(std::io::ErrorKind::WouldBlock)
+    );
+}
+
+#[test]
+fn test_ttyport_try_read() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_secs(10))
+        .expect("Unable to set timeout on the slave");
+
+    // The long timeout is ignored
+    let start = Instant::now();
+    let mut buf = [0u8; 16];
+    let err = slave
+        .try_read(&mut buf)
+        .expect_err("Read data that was never sent");
+    assert_eq!(
+        err.kind(),
+        serialport::ErrorKind::Io(std::io::ErrorKind::WouldBlock)
+    );
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    master.write_all(b"ready").expect("Unable to write bytes.");
+    thread::sleep(Duration::from_millis(50));
+    let nbytes = slave.try_read(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf[..nbytes], b"ready");
+}
+
+#[test]
+fn test_ttyport_drain() {
+    let (mut master, _slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    master.write_all(b"drain").expect("Unable to write bytes.");
+    master.flush().expect("Unable to flush the port");
+    master.drain().expect("Unable to drain the port");
+    assert_eq!(master.bytes_to_write().unwrap(), 0);
+}
+
+#[test]
+fn test_ttyport_set_timeout_returns_previous() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    let previous = slave
+        .set_timeout(Duration::from_millis(250))
+        .expect("Unable to set timeout on the slave");
+    assert_eq!(previous, Duration::from_millis(1));
+    let previous = slave.set_timeout(previous).unwrap();
+    assert_eq!(previous, Duration::from_millis(250));
+    assert_eq!(slave.timeout(), Duration::from_millis(1));
+}
+
+#[test]
+fn test_ttyport_write_all_times_out() {
+    let (mut master, _slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    master
+        .set_timeout(Duration::from_millis(100))
+        .expect("Unable to set timeout on the master");
+
+    // Nobody reads from the slave, so this can never complete
+    let start = Instant::now();
+    let buf = vec![0u8; 1024 * 1024];
+    let err = master
+        .write_all(&buf)
+        .expect_err("Wrote more than fits in the slave's buffer");
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(master.stats().bytes_written > 0);
+}
+
+#[test]
+fn test_ttyport_discard_until_idle() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    // Send a burst in several pieces with short gaps, then go quiet
+    let writer = thread::spawn(move || {
+        for _ in 0..4 {
+            master.write_all(b"noise").expect("Unable to write bytes.");
+            thread::sleep(Duration::from_millis(10));
+        }
+        master
+    });
+
+    let discarded = slave
+        .discard_until_idle(Duration::from_millis(100), Duration::from_secs(5))
+        .expect("Line never went idle");
+    assert_eq!(discarded, 20);
+    assert_eq!(slave.timeout(), Duration::from_millis(1));
+    let _master = writer.join().unwrap();
+}
+
+#[test]
+fn test_ttyport_supported_settings() {
+    use serialport::{DataBits, Parity};
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let supported = slave
+        .supported_settings()
+        .expect("Unable to probe the supported settings");
+    assert!(supported.data_bits.contains(&DataBits::Eight));
+    assert!(supported.parity.contains(&Parity::None));
+    assert!(supported.stop_bits.contains(&StopBits::One));
+    // Linux ptys only support 8 data bits without parity
+    #[cfg(target_os = "linux")]
+    {
+        assert_eq!(supported.data_bits, vec![DataBits::Eight]);
+        assert_eq!(supported.parity, vec![Parity::None]);
+        // 1.5 stop bits need 5 data bits
+        assert_eq!(supported.stop_bits, vec![StopBits::One, StopBits::Two]);
+    }
+
+    assert_eq!(slave.data_bits().unwrap(), DataBits::Eight);
+    assert_eq!(slave.parity().unwrap(), Parity::None);
+    assert_eq!(slave.stop_bits().unwrap(), StopBits::One);
+}
+
+#[test]
+fn test_ttyport_send_break() {
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    let start = Instant::now();
+    slave
+        .send_break(Duration::from_millis(20))
+        .expect("Unable to send a break");
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    slave
+        .send_break_for(serialport::BreakDuration::Short)
+        .expect("Unable to send a break");
+}
+
+#[test]
+fn test_ttyport_copy_until() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let done = Arc::new(AtomicBool::new(false));
+
+    // Idle periods much longer than the slave's timeout don't end the copy
+    let writer_done = done.clone();
+    let writer = thread::spawn(move || {
+        master.write_all(b"first").expect("Unable to write bytes.");
+        thread::sleep(Duration::from_millis(50));
+        master.write_all(b"second").expect("Unable to write bytes.");
+        thread::sleep(Duration::from_millis(50));
+        writer_done.store(true, Ordering::SeqCst);
+        master
+    });
+
+    let mut sink = Vec::new();
+    let copied = slave
+        .copy_until(&mut sink, &|| done.load(Ordering::SeqCst))
+        .expect("Unable to copy bytes.");
+    assert_eq!(copied, 11);
+    assert_eq!(sink, b"firstsecond");
+    let _master = writer.join().unwrap();
+}
+
+// Test that a zero timeout doesn't make copying spin while the line is idle
+#[test]
+fn test_ttyport_copy_until_zero_timeout() {
+    use std::cell::Cell;
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_millis(0))
+        .expect("Unable to set timeout on the slave");
+
+    let start = Instant::now();
+    let checks = Cell::new(0);
+    let mut sink = Vec::new();
+    let copied = serialport::copy_until(&mut slave, &mut sink, || {
+        checks.set(checks.get() + 1);
+        start.elapsed() > Duration::from_millis(200)
+    })
+    .expect("Unable to copy bytes.");
+    assert_eq!(copied, 0);
+    // Every check is followed by a read waiting for 10ms
+    assert!(checks.get() < 50, "Checked {} times", checks.get());
+    assert_eq!(slave.timeout(), Duration::from_millis(0));
+}
+
+#[test]
+fn test_ttyport_rx_watchdog() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_millis(100))
+        .expect("Unable to set timeout on the slave");
+    let stalls = Arc::new(AtomicUsize::new(0));
+    let counter = stalls.clone();
+    slave
+        .set_rx_watchdog(
+            Duration::from_millis(200),
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .expect("Unable to set the watchdog");
+
+    // Regular traffic keeps the watchdog quiet
+    let mut buf = [0u8; 4];
+    for _ in 0..5 {
+        thread::sleep(Duration::from_millis(50));
+        master.write_all(b"ping").expect("Unable to write bytes.");
+        slave.read_exact(&mut buf).expect("Unable to read bytes.");
+    }
+    assert_eq!(stalls.load(Ordering::SeqCst), 0);
+
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(stalls.load(Ordering::SeqCst), 1);
+
+    // Dropping the port stops the watchdog
+    drop(slave);
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(stalls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_ttyport_into_raw_fd_stops_rx_watchdog() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let stalls = Arc::new(AtomicUsize::new(0));
+    let counter = stalls.clone();
+    slave
+        .set_rx_watchdog(
+            Duration::from_millis(50),
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .expect("Unable to set the watchdog");
+
+    // The descriptor outlives the port, but the watchdog doesn't
+    let fd = slave.into_raw_fd();
+    let stalls_then = stalls.load(Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(stalls.load(Ordering::SeqCst), stalls_then);
+    nix::unistd::close(fd).expect("Unable to close the descriptor");
+}
+
+#[test]
+fn test_ttyport_inter_byte_write_delay() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_millis(100))
+        .expect("Unable to set timeout on the slave");
+    assert_eq!(master.inter_byte_write_delay(), Duration::from_secs(0));
+    master
+        .set_inter_byte_write_delay(Duration::from_millis(20))
+        .expect("Unable to set the inter-byte delay");
+
+    let start = Instant::now();
+    master.write_all(b"slow").expect("Unable to write bytes.");
+    assert_eq!(master.write(b"er").expect("Unable to write bytes."), 2);
+    // There's a gap between every byte but the last of each call
+    assert!(start.elapsed() >= Duration::from_millis(80));
+
+    let mut buf = [0u8; 6];
+    slave.read_exact(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf, b"slower");
+}
+
+#[test]
+fn test_ttyport_xon_xoff_chars() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    assert_eq!(slave.xon_xoff_chars().unwrap(), (0x11, 0x13));
+
+    slave
+        .set_xon_xoff_chars(b'Q', b'S')
+        .expect("Unable to set the XON/XOFF bytes");
+    assert_eq!(slave.xon_xoff_chars().unwrap(), (b'Q', b'S'));
+
+    let err = slave.set_xon_xoff_chars(b'Q', b'Q').unwrap_err();
+    assert_eq!(err.kind(), serialport::ErrorKind::InvalidInput);
+    assert_eq!(slave.xon_xoff_chars().unwrap(), (b'Q', b'S'));
+}
+
+#[test]
+fn test_ttyport_inter_byte_timeout() {
+    let (master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_millis(500))
+        .expect("Unable to set timeout on the slave");
+    assert_eq!(slave.inter_byte_timeout(), None);
+
+    // Writes "ab" and "cd" with a short pause in between
+    let write_burst = |mut master: TTYPort| {
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            master.write_all(b"ab").expect("Unable to write bytes.");
+            thread::sleep(Duration::from_millis(30));
+            master.write_all(b"cd").expect("Unable to write bytes.");
+            master
+        })
+    };
+
+    // Without an inter-byte timeout a read returns as soon as some data is available
+    let writer = write_burst(master);
+    let mut buf = [0u8; 16];
+    let n = slave.read(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf[..n], b"ab");
+    let master = writer.join().unwrap();
+    slave
+        .read_exact(&mut buf[..2])
+        .expect("Unable to read bytes.");
+    assert_eq!(&buf[..2], b"cd");
+
+    slave
+        .set_inter_byte_timeout(Some(Duration::from_millis(100)))
+        .expect("Unable to set the inter-byte timeout");
+    assert_eq!(slave.inter_byte_timeout(), Some(Duration::from_millis(100)));
+
+    // With one the read waits for the rest of the burst
+    let writer = write_burst(master);
+    let n = slave.read(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf[..n], b"abcd");
+    let _master = writer.join().unwrap();
+}
+
+#[test]
+fn test_ttyport_flush_timeout() {
+    let (mut master, _slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    master.write_all(b"flush").expect("Unable to write bytes.");
+    master
+        .flush_timeout(Duration::from_millis(100))
+        .expect("Output buffer did not empty");
+}
+
+#[test]
+fn test_ttyport_ensure_transmitted() {
+    let (mut master, _slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    master.write_all(b"sent").expect("Unable to write bytes.");
+    assert!(master
+        .ensure_transmitted(Duration::from_millis(100))
+        .expect("Unable to wait for transmission"));
+}
+
+#[test]
+fn test_ttyport_exclusive_open_is_busy() {
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    assert!(slave.exclusive());
+    let path = slave.name().expect("Slave ptty has no name");
+
+    match serialport::new(path, 9600).open() {
+        Ok(_) => assert!(
+            is_root(),
+            "Opened a tty that another port holds exclusively"
+        ),
+        Err(e) => {
+            assert_eq!(e.kind(), serialport::ErrorKind::Busy);
+            assert_eq!(e.raw_os_error(), Some(nix::libc::EBUSY));
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn test_ttyport_from_raw_fd_name() {
+    let (_master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let path = slave.name().expect("Slave ptty has no name");
+
+    let port = unsafe { TTYPort::from_raw_fd(slave.into_raw_fd()) };
+    assert_eq!(port.name(), Some(path));
+}
+
+#[test]
+fn test_ttyport_open_applies_settings() {
+    use serialport::{DataBits, ErrorKind};
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_exclusive(false)
+        .expect("Unable to release exclusive access to the slave");
+    let path = slave.name().expect("Slave ptty has no name");
+
+    // A setting that can't be applied fails the open instead of returning a half-configured port
+    let result = serialport::new(path.clone(), 19200)
+        .data_bits(DataBits::Five)
+        .stop_bits(StopBits::Two)
+        .open();
+    match result {
+        Ok(_) => panic!("5 data bits and 2 stop bits were accepted"),
+        Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidInput),
+    }
+
+    // The failed port was closed, otherwise its exclusive access would make this fail. Root can
+    // open exclusive ttys anyway, so look for a leftover descriptor instead.
+    #[cfg(target_os = "linux")]
+    {
+        if is_root() {
+            assert_eq!(open_fds_for(&path), 1);
+        }
+    }
+    let port = serialport::new(path, 19200)
+        .stop_bits(StopBits::Two)
+        .timeout(Duration::from_millis(25))
+        .open()
+        .expect("Unable to open the slave");
+    assert_eq!(port.baud_rate().unwrap(), 19200);
+    assert_eq!(port.stop_bits().unwrap(), StopBits::Two);
+    assert_eq!(port.timeout(), Duration::from_millis(25));
+}
+
+#[test]
+fn test_ttyport_open_verified() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_exclusive(false)
+        .expect("Unable to release exclusive access to the slave");
+    let path = slave.name().expect("Slave ptty has no name");
+
+    let (port, mismatches) = serialport::new(path, 19200)
+        .stop_bits(StopBits::Two)
+        .open_verified(true)
+        .expect("Settings were not applied to the slave");
+    assert!(mismatches.is_empty());
+    assert_eq!(port.baud_rate().unwrap(), 19200);
+    assert_eq!(port.stop_bits().unwrap(), StopBits::Two);
+}
+
+// Linux ptys always force 8 data bits and no parity, which makes them a convenient example of a
+// driver that ignores some settings.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_ttyport_open_verified_mismatch() {
+    use serialport::{DataBits, Parity, SettingMismatch};
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_exclusive(false)
+        .expect("Unable to release exclusive access to the slave");
+    let path = slave.name().expect("Slave ptty has no name");
+    let builder = serialport::new(path, 9600)
+        .data_bits(DataBits::Seven)
+        .parity(Parity::Even);
+
+    let (_port, mismatches) = builder
+        .clone()
+        .open_verified(false)
+        .expect("Unable to open the slave");
+    assert_eq!(
+        mismatches,
+        vec![
+            SettingMismatch::DataBits {
+                requested: DataBits::Seven,
+                actual: DataBits::Eight,
+            },
+            SettingMismatch::Parity {
+                requested: Parity::Even,
+                actual: Parity::None,
+            },
+        ]
+    );
+
+    assert!(builder.open_verified(true).is_err());
+}
+
+#[test]
+fn test_ttyport_read_only() {
+    use serialport::{AccessMode, ErrorKind};
+
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_exclusive(false)
+        .expect("Unable to release exclusive access to the slave");
+    let path = slave.name().expect("Slave ptty has no name");
+
+    let mut port = serialport::new(path, 9600)
+        .access_mode(AccessMode::ReadOnly)
+        .timeout(Duration::from_millis(100))
+        .open_native()
+        .expect("Unable to open the slave read-only");
+    assert_eq!(port.access_mode(), AccessMode::ReadOnly);
+
+    let err = port.write(b"tx").expect_err("Wrote to a read-only port");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let inner = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<serialport::Error>())
+        .expect("Missing serialport error");
+    assert_eq!(inner.kind(), ErrorKind::InvalidInput);
+
+    master.write_all(b"rx").expect("Unable to write bytes.");
+    let mut buf = [0u8; 2];
+    port.read_exact(&mut buf)
+        .expect("Unable to read from a read-only port");
+    assert_eq!(&buf, b"rx");
+}
+
+#[test]
+fn test_ttyport_rejects_two_stop_bits_with_five_data_bits() {
+    use serialport::{DataBits, ErrorKind};
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_exclusive(false)
+        .expect("Unable to release exclusive access to the slave");
+    let path = slave.name().expect("Slave ptty has no name");
+
+    let err = serialport::new(path, 9600)
+        .data_bits(DataBits::Five)
+        .stop_bits(StopBits::Two)
+        .open_native()
+        .expect_err("1.5 stop bits were configured instead of 2");
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_ttyport_set_all() {
+    use serialport::{DataBits, FlowControl, SerialPortSettings};
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let settings = SerialPortSettings {
+        baud_rate: 19200,
+        flow_control: FlowControl::Software,
+        stop_bits: StopBits::Two,
+        timeout: Duration::from_millis(50),
+        ..SerialPortSettings::default()
+    };
+    slave
+        .set_all(&settings)
+        .expect("Unable to apply the settings");
+    assert_eq!(slave.settings().unwrap(), settings);
+
+    // Nothing is applied if any of the settings is invalid
+    let invalid = SerialPortSettings {
+        baud_rate: 38400,
+        data_bits: DataBits::Five,
+        ..settings
+    };
+    assert!(slave.set_all(&invalid).is_err());
+    assert_eq!(slave.settings().unwrap(), settings);
+}
+
+#[test]
+fn test_ttyport_stats() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_millis(10))
+        .expect("Unable to set timeout on the slave");
+
+    master.write_all(b"stats").expect("Unable to write bytes.");
+    let mut buf = [0u8; 16];
+    let nbytes = slave.read(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(nbytes, 5);
+    assert!(slave.read(&mut buf).is_err());
+
+    assert_eq!(master.stats().bytes_written, 5);
+    let stats = slave.stats();
+    assert_eq!(stats.bytes_read, 5);
+    assert_eq!(stats.timeouts, 1);
+    assert_eq!(stats.read_errors, 0);
+
+    slave.reset_stats();
+    assert_eq!(slave.stats(), serialport::PortStats::default());
+}
+
+#[test]
+fn test_ttyport_strip_high_bit() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_timeout(Duration::from_millis(10))
+        .expect("Unable to set timeout on the slave");
+    assert!(!slave.strip_high_bit().unwrap());
+
+    let mut buf = [0u8; 1];
+    master.write_all(&[0xC1]).expect("Unable to write bytes.");
+    slave.read_exact(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(buf[0], 0xC1);
+
+    slave.set_strip_high_bit(true).unwrap();
+    assert!(slave.strip_high_bit().unwrap());
+    master.write_all(&[0xC1]).expect("Unable to write bytes.");
+    slave.read_exact(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(buf[0], 0x41);
+}
+
+#[test]
+fn test_ttyport_send_idle() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave.set_baud_rate(9600).unwrap();
+
+    // 960 bit periods at 9600 baud is 100ms
+    let start = Instant::now();
+    slave.send_idle(960).expect("Unable to hold the line idle");
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn test_ttyport_null_modem_pair() {
+    let (mut master, mut slave) = TTYPort::null_modem_pair().expect("Unable to create ptty pair");
+
+    assert!(!slave.read_clear_to_send().unwrap());
+    master.write_request_to_send(true).unwrap();
+    assert!(slave.read_clear_to_send().unwrap());
+    assert!(!master.read_clear_to_send().unwrap());
+    assert!(!slave.read_data_set_ready().unwrap());
+
+    slave.write_data_terminal_ready(true).unwrap();
+    assert!(master.read_data_set_ready().unwrap());
+    assert!(master.read_carrier_detect().unwrap());
+    assert!(!master.read_ring_indicator().unwrap());
+
+    let signals = master.signals().unwrap();
+    assert!(!signals.clear_to_send);
+    assert!(signals.data_set_ready);
+    assert!(!signals.ring_indicator);
+    assert!(signals.carrier_detect);
+
+    // Clones share the emulated lines
+    let mut clone = master
+        .try_clone_native()
+        .expect("Unable to clone the master");
+    clone.write_request_to_send(false).unwrap();
+    assert!(!slave.read_clear_to_send().unwrap());
+}
+
+#[test]
+fn test_ttyport_wait_for_signal_change() {
+    use serialport::SignalMask;
+
+    let (mut master, slave) = TTYPort::null_modem_pair().expect("Unable to create ptty pair");
+    let waiter = thread::spawn(move || {
+        slave.wait_for_signal_change(SignalMask::CLEAR_TO_SEND, Duration::from_secs(5))
+    });
+
+    // Changing a line that isn't waited on doesn't end the wait
+    thread::sleep(Duration::from_millis(50));
+    master.write_data_terminal_ready(true).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    master.write_request_to_send(true).unwrap();
+
+    let signals = waiter
+        .join()
+        .unwrap()
+        .expect("Unable to wait for a signal change");
+    assert!(signals.clear_to_send);
+    assert!(signals.data_set_ready);
+}
+
+#[test]
+fn test_ttyport_wait_for_signal_change_times_out() {
+    use serialport::{ErrorKind, SignalMask};
+    use std::time::Instant;
+
+    let (mut master, slave) = TTYPort::null_modem_pair().expect("Unable to create ptty pair");
+    master.write_data_terminal_ready(true).unwrap();
+    let start = Instant::now();
+    let err = slave
+        .wait_for_signal_change(SignalMask::CLEAR_TO_SEND, Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn test_ttyport_wait_for_no_signals() {
+    use serialport::{ErrorKind, SignalMask};
+
+    let (_master, slave) = TTYPort::null_modem_pair().expect("Unable to create ptty pair");
+    let err = slave
+        .wait_for_signal_change(SignalMask::empty(), Duration::from_secs(1))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_ttyport_open_error_keeps_os_error() {
+    use std::error::Error;
+
+    let err = serialport::new("/dev/serialport-rs-does-not-exist", 9600)
+        .open()
+        .err()
+        .expect("Opened a port that doesn't exist");
+    assert_eq!(err.raw_os_error(), Some(nix::libc::ENOENT));
+    let source = err.source().expect("No source for an OS error");
+    assert!(source.downcast_ref::<std::io::Error>().is_some());
+
+    // The OS error survives going through `io::Error` and back
+    let err = serialport::Error::from(std::io::Error::from(err));
+    assert_eq!(err.raw_os_error(), Some(nix::libc::ENOENT));
+}
+
+#[test]
+fn test_ttyport_set_low_latency() {
+    use serialport::ErrorKind;
+
+    // Pseudo-terminals don't have a low latency mode
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let err = slave
+        .set_low_latency(true)
+        .expect_err("ptty accepted low latency mode");
+    assert_eq!(err.kind(), ErrorKind::Unknown);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_ttyport_dtr_dsr_flow_control_unsupported() {
+    use serialport::FlowControl;
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave
+        .set_flow_control(FlowControl::Software)
+        .expect("Unable to set flow control");
+    let err = slave
+        .set_flow_control(FlowControl::DtrDsr)
+        .expect_err("Set DTR/DSR flow control on Linux");
+    assert_eq!(err.kind(), serialport::ErrorKind::InvalidInput);
+    assert_eq!(slave.flow_control().unwrap(), FlowControl::Software);
+}
+
+#[test]
+fn test_ttyport_split() {
+    let (master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let (mut reader, mut writer) = master.split().expect("Unable to split the master");
+    slave
+        .set_timeout(Duration::from_millis(500))
+        .expect("Unable to set timeout on the slave");
+    reader
+        .set_timeout(Duration::from_millis(500))
+        .expect("Unable to set timeout on the reading half");
+
+    let echo = thread::spawn(move || {
+        let mut buf = [0u8; 4];
+        slave.read_exact(&mut buf).expect("Unable to read bytes.");
+        slave.write_all(&buf).expect("Unable to write bytes.");
+        // Closing the slave would hang up the master before it read the echo
+        slave
+    });
+    writer.write_all(b"ping").expect("Unable to write bytes.");
+
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf, b"ping");
+    assert_eq!(reader.timeout(), Duration::from_millis(500));
+    echo.join().unwrap();
+}
+
+// Test that a `write_all()` on one half doesn't undo the mode the other half set meanwhile
+#[test]
+fn test_ttyport_split_set_nonblocking_during_write_all() {
+    let (master, _slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let (mut reader, mut writer) = master.split().expect("Unable to split the master");
+    writer
+        .set_timeout(Duration::from_millis(500))
+        .expect("Unable to set timeout on the writing half");
+
+    // Nobody reads from the slave, so the write waits for its whole timeout
+    let blocked = thread::spawn(move || {
+        let data = vec![0u8; 1024 * 1024];
+        let err = writer.write_all(&data).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    });
+    thread::sleep(Duration::from_millis(100));
+    reader.set_nonblocking(true).unwrap();
+    blocked.join().unwrap();
+
+    let mut buf = [0u8; 4];
+    let start = Instant::now();
+    let err = reader.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(start.elapsed() < Duration::from_millis(100));
+
+    reader.set_nonblocking(false).unwrap();
+    reader.set_timeout(Duration::from_millis(50)).unwrap();
+    let err = reader.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_ttyport_set_nonblocking() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    slave.set_timeout(Duration::from_secs(5)).unwrap();
+    let mut clone = slave.try_clone_native().unwrap();
+    slave.set_nonblocking(true).unwrap();
+
+    let mut buf = [0u8; 4];
+    let start = Instant::now();
+    let err = slave.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    // The mode is shared with clones, including ones made before it was set
+    let err = clone.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    let err = slave
+        .try_clone_native()
+        .unwrap()
+        .read(&mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    master.write_all(b"ping").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(slave.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"ping");
+
+    // Nobody reads from the master, so the slave's buffer eventually fills up
+    let data = vec![0u8; 1024 * 1024];
+    let start = Instant::now();
+    let err = slave.write_all(&data).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    slave.set_nonblocking(false).unwrap();
+    slave.set_timeout(Duration::from_millis(10)).unwrap();
+    let err = slave.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_ttyport_reset_device() {
+    use serialport::ResetMethod;
+
+    let (mut master, mut slave) = TTYPort::null_modem_pair().expect("Unable to create ptty pair");
+
+    let start = Instant::now();
+    master
+        .reset_device(ResetMethod::DtrPulse(Duration::from_millis(20)))
+        .expect("Unable to pulse DTR");
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert!(slave.read_data_set_ready().unwrap());
+    assert!(slave.read_clear_to_send().unwrap());
+
+    master
+        .set_baud_rate(115_200)
+        .expect("Unable to set the baud rate");
+    master
+        .reset_device(ResetMethod::BaudTouch {
+            rate: 1200,
+            hold: Duration::from_millis(20),
+        })
+        .expect("Unable to do a baud rate touch");
+    assert_eq!(master.baud_rate().unwrap(), 115_200);
+    assert!(slave.read_data_set_ready().unwrap());
+    assert!(slave.read_clear_to_send().unwrap());
+}
+
+#[test]
+fn test_ttyport_has_data() {
+    let (mut master, slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    assert!(!slave.has_data().unwrap());
+
+    master.write_all(b"data").expect("Unable to write bytes.");
+    // Give the pty a moment to pass the data through
+    thread::sleep(Duration::from_millis(10));
+    assert!(slave.has_data().unwrap());
+}
+
+#[test]
+fn test_ttyport_apply_diff() {
+    use serialport::SerialPortSettings;
+
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    let current = SerialPortSettings::default();
+    let desired = SerialPortSettings {
+        baud_rate: 19200,
+        timeout: Duration::from_millis(50),
+        ..current
+    };
+    let diff = current.diff(&desired);
+    assert_eq!(diff.baud_rate, Some(19200));
+    assert_eq!(diff.timeout, Some(Duration::from_millis(50)));
+    assert_eq!(diff.data_bits, None);
+    assert!(current.diff(&current).is_empty());
+
+    slave
+        .apply_diff(&diff)
+        .expect("Unable to apply the settings");
+    assert_eq!(slave.baud_rate().unwrap(), 19200);
+    assert_eq!(slave.timeout(), Duration::from_millis(50));
+}
+
+#[test]
+fn test_ttyport_test_hardware_flow() {
+    let (mut master, mut slave) = TTYPort::null_modem_pair().expect("Unable to create ptty pair");
+
+    // A null-modem cable doesn't loop RTS back to CTS on the same end
+    assert!(!master.test_hardware_flow().unwrap());
+    slave.write_request_to_send(true).unwrap();
+    assert!(!master.test_hardware_flow().unwrap());
+}
+
+#[test]
+fn test_ttyport_timeout_is_not_eof() {
+    let (_master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    assert!(slave.read_is_eof_possible());
+
+    let mut buf = [0u8; 16];
+    let err = slave
+        .read(&mut buf)
+        .expect_err("Read returned without any data");
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    assert_eq!(
+        serialport::Error::from(err).kind(),
+        serialport::ErrorKind::TimedOut
+    );
+}
+
+#[test]
+fn test_ttyport_read_exact_or_partial() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let timeout = slave.timeout();
+
+    let mut buf = [0u8; 8];
+    master.write_all(b"part").expect("Unable to write bytes.");
+    let (filled, err) = slave
+        .read_exact_or_partial(&mut buf, Duration::from_millis(50))
+        .expect_err("Read filled the buffer with only half of the data");
+    assert_eq!(filled, 4);
+    assert_eq!(err.kind(), serialport::ErrorKind::TimedOut);
+    assert_eq!(&buf[..filled], b"part");
+    assert_eq!(slave.timeout(), timeout);
+
+    // Resume where the previous read left off
+    master.write_all(b"ial!").expect("Unable to write bytes.");
+    slave
+        .read_exact_or_partial(&mut buf[filled..], Duration::from_millis(50))
+        .expect("Unable to read the rest of the data");
+    assert_eq!(&buf, b"partial!");
+}
+
+#[test]
+fn test_ttyport_read_exact_deadline() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let deadline = Instant::now() + Duration::from_millis(100);
+
+    master.write_all(b"head").expect("Unable to write bytes.");
+    let mut header = [0u8; 4];
+    slave
+        .read_exact_deadline(&mut header, deadline)
+        .expect("Unable to read the header");
+    assert_eq!(&header, b"head");
+
+    // The body shares the deadline, which passes before it's complete
+    master.write_all(b"bo").expect("Unable to write bytes.");
+    let mut body = [0u8; 4];
+    let (filled, err) = slave
+        .read_exact_deadline(&mut body, deadline)
+        .expect_err("Read filled the buffer with only half of the data");
+    assert!(Instant::now() < deadline + Duration::from_millis(50));
+    assert_eq!(filled, 2);
+    assert_eq!(err.kind(), serialport::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_ttyport_read_until() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+    let timeout = slave.timeout();
+
+    master
+        .write_all(b"first\nsecond\nthird")
+        .expect("Unable to write bytes.");
+    let mut line = Vec::new();
+    let read = slave
+        .read_until(b'\n', &mut line, 64, Duration::from_millis(100))
+        .expect("Unable to read the first line");
+    assert_eq!(read, 6);
+    assert_eq!(line, b"first\n");
+    assert_eq!(slave.timeout(), timeout);
+
+    // Nothing after the delimiter was consumed
+    line.clear();
+    let e = slave
+        .read_until(b'\n', &mut line, 3, Duration::from_millis(100))
+        .expect_err("Read a line longer than the maximum length");
+    assert_eq!(
+        e.kind(),
+        serialport::ErrorKind::Io(std::io::ErrorKind::InvalidData)
+    );
+    assert_eq!(line, b"sec");
+
+    line.clear();
+    slave
+        .read_until(b'\n', &mut line, 64, Duration::from_millis(100))
+        .expect("Unable to read the rest of the second line");
+    assert_eq!(line, b"ond\n");
+
+    line.clear();
+    let e = slave
+        .read_until(b'\n', &mut line, 64, Duration::from_millis(50))
+        .expect_err("Read a line without a delimiter");
+    assert_eq!(e.kind(), serialport::ErrorKind::TimedOut);
+    assert_eq!(line, b"third");
+    assert_eq!(slave.timeout(), timeout);
+}
+
+#[test]
+fn test_ttyport_write_all_or_partial() {
+    let (mut master, mut slave) = TTYPort::pair().expect("Unable to create ptty pair");
+
+    master
+        .write_all_or_partial(b"frame", Duration::from_millis(50))
+        .expect("Unable to write bytes.");
+    let mut buf = [0u8; 5];
+    slave.read_exact(&mut buf).expect("Unable to read bytes.");
+    assert_eq!(&buf, b"frame");
+
+    // Nobody reads from the slave, so only part of this fits
+    let start = Instant::now();
+    let data = vec![0u8; 1024 * 1024];
+    let (written, err) = master
+        .write_all_or_partial(&data, Duration::from_millis(100))
+        .expect_err("Wrote more than fits in the slave's buffer");
+    assert!(written > 0 && written < data.len());
+    assert_eq!(err.kind(), serialport::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,144 @@
+extern crate serialport;
+
+use serialport::{ErrorKind, SerialPort, VirtualPort};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+// Test that data flows in both directions
+#[test]
+fn test_virtual_port_transfer() {
+    let (mut a, mut b) = VirtualPort::pair();
+
+    a.write_all(b"ping").unwrap();
+    assert_eq!(b.bytes_to_read().unwrap(), 4);
+    let mut buffer = [0; 4];
+    b.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, b"ping");
+
+    b.write_all(b"pong").unwrap();
+    a.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, b"pong");
+    assert_eq!(a.bytes_to_read().unwrap(), 0);
+}
+
+// Test that a write larger than the buffer completes once the other end reads
+#[test]
+fn test_virtual_port_large_write() {
+    let (mut a, mut b) = VirtualPort::pair();
+    let data: Vec<u8> = (0..20_000).map(|i| i as u8).collect();
+    let expected = data.clone();
+
+    let writer = thread::spawn(move || a.write_all(&data).unwrap());
+    let mut received = vec![0; expected.len()];
+    b.set_timeout(Duration::from_secs(5)).unwrap();
+    b.read_exact(&mut received).unwrap();
+    writer.join().unwrap();
+    assert_eq!(received, expected);
+}
+
+// Test that reads time out without data and fail once the other end is closed
+#[test]
+fn test_virtual_port_timeout_and_close() {
+    let (mut a, b) = VirtualPort::pair();
+    a.set_timeout(Duration::from_millis(10)).unwrap();
+
+    let mut buffer = [0; 1];
+    let e = a.read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+
+    drop(b);
+    let e = a.read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::BrokenPipe);
+    let e = a.write(b"x").unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::BrokenPipe);
+}
+
+// Test that the control lines are wired like a null-modem cable
+#[test]
+fn test_virtual_port_signals() {
+    let (mut a, mut b) = VirtualPort::pair();
+
+    assert!(!b.read_clear_to_send().unwrap());
+    a.write_request_to_send(true).unwrap();
+    assert!(b.read_clear_to_send().unwrap());
+    assert!(!b.read_data_set_ready().unwrap());
+
+    a.write_data_terminal_ready(true).unwrap();
+    assert!(b.read_data_set_ready().unwrap());
+    assert!(b.read_carrier_detect().unwrap());
+    assert!(!b.read_ring_indicator().unwrap());
+    assert!(!a.read_clear_to_send().unwrap());
+}
+
+// Test that clones share their end of the pair
+#[test]
+fn test_virtual_port_clone() {
+    let (a, mut b) = VirtualPort::pair();
+    let mut clone = a.try_clone().unwrap();
+    drop(a);
+
+    clone.write_all(b"abc").unwrap();
+    let mut buffer = [0; 3];
+    b.read_exact(&mut buffer).unwrap();
+    assert_eq!(&buffer, b"abc");
+}
+
+// Test nonblocking reads and cancelling a blocked read
+#[test]
+fn test_virtual_port_nonblocking_and_cancel() {
+    let (mut a, _b) = VirtualPort::pair();
+
+    let mut buffer = [0; 1];
+    let e = a.try_read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Io(io::ErrorKind::WouldBlock));
+
+    a.set_timeout(Duration::from_secs(10)).unwrap();
+    let token = a.cancellation_token().unwrap();
+    let reader = thread::spawn(move || a.read_exact(&mut buffer));
+    thread::sleep(Duration::from_millis(50));
+    token.cancel();
+    let e = reader.join().unwrap().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::Interrupted);
+}
+
+// Test that the non-blocking mode is shared with clones
+#[test]
+fn test_virtual_port_clone_shares_nonblocking() {
+    let (mut a, _b) = VirtualPort::pair();
+    let mut clone = a.try_clone().unwrap();
+    clone.set_timeout(Duration::from_secs(10)).unwrap();
+    a.set_nonblocking(true).unwrap();
+
+    let mut buffer = [0; 1];
+    let e = clone.read(&mut buffer).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+}
+
+// Test that cancelling stops the helpers that retry interrupted reads instead of spinning
+#[test]
+fn test_virtual_port_cancel_helpers() {
+    let (mut a, _b) = VirtualPort::pair();
+    a.set_timeout(Duration::from_secs(10)).unwrap();
+    let token = a.cancellation_token().unwrap();
+    let copier = thread::spawn(move || {
+        let mut copied = Vec::new();
+        let result = a.copy_until(&mut copied, &|| false);
+        (result, a)
+    });
+    thread::sleep(Duration::from_millis(50));
+    token.cancel();
+    let (result, mut a) = copier.join().unwrap();
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Interrupted);
+
+    let mut buffer = [0; 4];
+    let (filled, e) = a
+        .read_exact_or_partial(&mut buffer, Duration::from_secs(10))
+        .unwrap_err();
+    assert_eq!(filled, 0);
+    assert_eq!(e.kind(), ErrorKind::Interrupted);
+    let e = a
+        .read_until(b'\n', &mut Vec::new(), 64, Duration::from_secs(10))
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Interrupted);
+}